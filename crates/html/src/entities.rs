@@ -0,0 +1,142 @@
+use alloc::string::String;
+
+/// Longest entity body we try to match between `&` and `;`.
+const MAX_ENTITY_LEN: usize = 32;
+
+/// Replaces character references like `&amp;`, `&#169;` and `&#xA9;` with
+/// the characters they stand for. Unknown or malformed entities are kept
+/// as they are.
+pub fn decode_entities(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&end| end <= MAX_ENTITY_LEN)
+            .and_then(|end| {
+                decode_entity(&rest[1..end + 1]).map(|ch| (ch, end + 2))
+            });
+
+        match decoded {
+            Some((ch, len)) => {
+                output.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Escapes `&`, `<` and `>` so `input` reads as plain text in html.
+pub fn escape_text(input: &str) -> String {
+    escape(input, false)
+}
+
+/// Like `escape_text`, but also escapes `"` for use in a quoted attribute
+/// value.
+pub fn escape_attribute(input: &str) -> String {
+    escape(input, true)
+}
+
+fn escape(input: &str, quotes: bool) -> String {
+    let mut output = String::with_capacity(input.len());
+    for ch in input.chars() {
+        match ch {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' if quotes => output.push_str("&quot;"),
+            _ => output.push(ch),
+        }
+    }
+
+    output
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let (digits, radix) = match number
+            .strip_prefix('x')
+            .or_else(|| number.strip_prefix('X'))
+        {
+            Some(hex) => (hex, 16),
+            None => (number, 10),
+        };
+        // from_str_radix would also take a sign
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return None;
+        }
+
+        return char::from_u32(u32::from_str_radix(digits, radix).ok()?);
+    }
+
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_named_entities() {
+        assert_eq!(
+            decode_entities("&lt;p&gt; &quot;a&quot; &apos;b&apos;"),
+            "<p> \"a\" 'b'"
+        );
+        assert_eq!(decode_entities("a&nbsp;b"), "a\u{a0}b");
+    }
+
+    #[test]
+    fn test_decode_numeric_entities() {
+        assert_eq!(decode_entities("&#169; &#xA9; &#XA9;"), "© © ©");
+        // signs are not digits
+        assert_eq!(
+            decode_entities("&#+65; &#x+41; &#-65;"),
+            "&#+65; &#x+41; &#-65;"
+        );
+    }
+
+    #[test]
+    fn test_decode_mixed_content() {
+        assert_eq!(decode_entities("AT&amp;T &#x26; more"), "AT&T & more");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape_text(r#"a < b && "c" > d"#),
+            r#"a &lt; b &amp;&amp; "c" &gt; d"#
+        );
+        assert_eq!(
+            escape_attribute(r#"say "hi" & go"#),
+            "say &quot;hi&quot; &amp; go"
+        );
+        assert_eq!(decode_entities(&escape_attribute("<\"&\">")), "<\"&\">");
+    }
+
+    #[test]
+    fn test_unknown_entities_are_kept() {
+        assert_eq!(
+            decode_entities("&unknown; & &#xZZ; &amp"),
+            "&unknown; & &#xZZ; &amp"
+        );
+    }
+}
@@ -1,141 +1,473 @@
-use core::slice::Iter;
-use std::collections::VecDeque;
-use std::iter::Peekable;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
 
 #[derive(Debug, PartialEq)]
 pub enum Token {
     TagBegin(String),
     TagEnd(String),
+    /// Emitted for a tag written as `<name ... />`, after its attributes.
+    TagSelfClose(String),
     Content(String),
     Attribute((String, String)),
+    Comment(String),
+    /// A `<!DOCTYPE ...>` declaration, holding what follows the keyword.
+    Doctype(String),
     EOF,
 }
 
-pub struct Lexer {
-    input: Vec<char>,
+/// Where a token starts in the input. Lines and columns count from 1,
+/// columns in characters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: u32,
+    pub col: u32,
+    pub byte_offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    /// An attribute value was still open at a newline or the end of input.
+    /// `position` is the byte offset of the attribute name.
+    UnterminatedAttribute { position: usize },
+    /// A `<!--` was never closed by `-->`. `position` is the byte offset of
+    /// the opening `<`.
+    UnterminatedComment { position: usize },
+    /// The input ended in the middle of a tag.
+    UnexpectedEof,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::UnterminatedAttribute { position } => {
+                write!(f, "unterminated attribute value at byte {}", position)
+            }
+            LexError::UnterminatedComment { position } => {
+                write!(f, "unterminated comment at byte {}", position)
+            }
+            LexError::UnexpectedEof => write!(f, "unexpected end of input"),
+        }
+    }
+}
+
+impl Error for LexError {}
+
+/// What follows the `<` of a doctype declaration, in any case.
+const DOCTYPE_OPENER: &str = "!doctype";
+
+pub struct Lexer<'a> {
+    chars: Box<dyn Iterator<Item = char> + 'a>,
+    // characters already pulled from `chars` to look ahead, but not
+    // consumed yet
+    lookahead: VecDeque<char>,
+    /// Number of characters consumed so far.
     pub position: usize,
+    // location of the next character to consume
+    cursor: Span,
+    // start of the token last returned by `next_token`
+    token_span: Span,
+    // start of every token of the last `lex`, in the same order
+    spans: Vec<Span>,
+    // set between the name of a start tag and its closing `>`
+    is_lexing_tag: bool,
+    current_tag: String,
+    finished: bool,
 }
 
-impl Lexer {
-    pub fn new(input: &str) -> Self {
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self::from_chars(input.chars())
+    }
+
+    /// Lexes characters as they are pulled from `chars`, so the input never
+    /// has to be held in memory as a whole.
+    pub fn from_chars(chars: impl Iterator<Item = char> + 'a) -> Self {
+        let start = Span {
+            line: 1,
+            col: 1,
+            byte_offset: 0,
+        };
+
         Lexer {
-            input: input.chars().collect(),
+            chars: Box::new(chars),
+            lookahead: VecDeque::new(),
             position: 0,
+            cursor: start,
+            token_span: start,
+            spans: Vec::new(),
+            is_lexing_tag: false,
+            current_tag: String::new(),
+            finished: false,
         }
     }
 
-    pub fn lex(&mut self) -> Option<Vec<Token>> {
-        let mut is_lexing_tag = false;
+    /// Where each token returned by the last `lex` starts, in the same
+    /// order as the tokens.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Where the token last returned by `next_token` starts.
+    pub fn token_span(&self) -> Span {
+        self.token_span
+    }
 
+    /// Lexes the whole input, up to and including `Token::EOF`.
+    pub fn lex(&mut self) -> Result<Vec<Token>, LexError> {
         let mut tokens = Vec::new();
-        let mut iter = self.input.iter().peekable();
+        self.spans.clear();
+
+        while let Some(token) = self.next_token() {
+            tokens.push(token?);
+            self.spans.push(self.token_span);
+        }
+
+        Ok(tokens)
+    }
+
+    /// Reads the next token, pulling only the characters it needs from the
+    /// input. The last token is `Token::EOF`, after it or an error there
+    /// are no more tokens.
+    pub fn next_token(&mut self) -> Option<Result<Token, LexError>> {
+        if self.finished {
+            return None;
+        }
+
         loop {
-            let it = iter.next();
-            if it.is_none() {
-                tokens.push(Token::EOF);
-                break;
+            // tokens start where the character that began them was read
+            self.token_span = self.cursor;
+
+            let Some(ch) = self.next_char() else {
+                self.finished = true;
+                if self.is_lexing_tag {
+                    return Some(Err(LexError::UnexpectedEof));
+                }
+                return Some(Ok(Token::EOF));
+            };
+
+            match self.lex_char(ch) {
+                Ok(Some(token)) => return Some(Ok(token)),
+                Ok(None) => {}
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
             }
+        }
+    }
+
+    /// Lexes what starts with `ch`, which was just consumed. Returns `None`
+    /// when that does not make up a token.
+    fn lex_char(&mut self, ch: char) -> Result<Option<Token>, LexError> {
+        let start = self.token_span;
+
+        let token = match ch {
+            '<' if self.starts_with("!--") => {
+                // skip the "!--" opener
+                self.skip(3);
+
+                // comments do not nest, the first "-->" closes it
+                let mut comment = String::new();
+                loop {
+                    if self.starts_with("-->") {
+                        self.skip(3);
+                        break;
+                    }
 
-            let ch = *it.unwrap();
+                    match self.next_char() {
+                        Some(c) => comment.push(c),
+                        None => {
+                            return Err(LexError::UnterminatedComment {
+                                position: start.byte_offset,
+                            })
+                        }
+                    }
+                }
 
-            self.position += 1;
+                Token::Comment(comment)
+            }
+            '<' if self.starts_with_ignore_case(DOCTYPE_OPENER) => {
+                self.skip(DOCTYPE_OPENER.len());
+
+                let mut doctype = String::new();
+                loop {
+                    match self.next_char() {
+                        Some('>') => break,
+                        Some(c) => doctype.push(c),
+                        None => return Err(LexError::UnexpectedEof),
+                    }
+                }
 
-            // TODO: do not parse comments
-            match ch {
-                '<' => {
-                    if let Some(&&next) = iter.peek() {
-                        let is_close_tag = next == '/';
+                Token::Doctype(doctype.trim().to_string())
+            }
+            // a `<` that can not open a tag, like in `1 < 2`, is text
+            '<' if self.is_lexing_tag || opens_tag(self.peek(0)) => {
+                let Some(next) = self.peek(0) else {
+                    return Err(LexError::UnexpectedEof);
+                };
 
-                        if next == '/' || next == '!' {
-                            iter.next();
-                        }
+                let is_close_tag = next == '/';
 
-                        let element_name = get_next_word(&mut iter);
+                if next == '/' || next == '!' {
+                    self.next_char();
+                }
 
-                        if is_close_tag {
-                            tokens.push(Token::TagEnd(element_name));
-                        } else {
-                            tokens.push(Token::TagBegin(element_name.clone()));
+                let element_name = self.next_word();
 
-                            is_lexing_tag = true;
+                if is_close_tag {
+                    // end tags have no attributes, skip to their `>` so it
+                    // is not taken for text
+                    while let Some(c) = self.next_char() {
+                        if c == '>' {
+                            break;
                         }
                     }
+
+                    Token::TagEnd(element_name)
+                } else {
+                    self.current_tag = element_name.clone();
+                    self.is_lexing_tag = true;
+
+                    Token::TagBegin(element_name)
                 }
-                '>' => is_lexing_tag = false,
-                // handle tag names or attributes
-                _ if ch.is_alphanumeric() || ch == '-' => {
-                    // Collect alphanumeric strings as tags or text.
-                    let mut value = String::new();
-                    value.push(ch);
-
-                    // if there is any current element then this must be an attribute
-                    if is_lexing_tag {
-                        // parse attribute name
-                        while let Some(&&next) = iter.peek() {
-                            if next.is_alphanumeric() || next == '-' {
-                                value.push(iter.next().unwrap().clone());
-                            } else {
-                                break;
-                            }
-                        }
+            }
+            '/' if self.is_lexing_tag && self.peek(0) == Some('>') => {
+                self.next_char();
+                self.is_lexing_tag = false;
 
-                        // parse attribute value if exists
-                        let mut attr_value = String::new();
-                        if let Some(&&next) = iter.peek() {
-                            if next == '=' {
-                                iter.next();
-
-                                let mut quote_opened = false;
-
-                                // parse the quote till it's ended
-                                loop {
-                                    match iter.next() {
-                                        // some validation
-                                        Some(&str_c) if str_c == '"' => {
-                                            if quote_opened {
-                                                break;
-                                            }
-
-                                            quote_opened = true;
-                                        }
-                                        Some(&str_c) if str_c == '\n' => return None,
-                                        // parse the content
-                                        Some(&str_c) => {
-                                            attr_value.push(str_c);
-                                        }
-                                        None => return None,
-                                    }
-                                }
-                            }
-                        }
+                Token::TagSelfClose(self.current_tag.clone())
+            }
+            '>' if self.is_lexing_tag => {
+                let is_raw_text = Lexer::is_raw_text_tag(&self.current_tag);
+                self.is_lexing_tag = false;
+
+                // the body of a raw text element is a single piece of
+                // content, whatever it contains
+                if !is_raw_text {
+                    return Ok(None);
+                }
+
+                // the text starts after the `>`
+                self.token_span = self.cursor;
+                let text = self.read_raw_text();
+                if text.is_empty() {
+                    return Ok(None);
+                }
 
-                        tokens.push(Token::Attribute((value, attr_value)));
-                    } else {
-                        // parse until the next element starts
-                        while let Some(&&next) = iter.peek() {
-                            if next == '<' {
-                                break;
-                            }
+                Token::Content(text)
+            }
+            // inside a tag words are attributes
+            _ if self.is_lexing_tag && (ch.is_alphanumeric() || ch == '-') => {
+                let mut name = String::new();
+                name.push(ch);
+                name.push_str(&self.next_word());
+
+                // attributes without a value, like `disabled`, are
+                // left with an empty one
+                let value = if self.skip_to_equals() {
+                    self.read_attribute_value(start)?
+                } else {
+                    String::new()
+                };
+
+                Token::Attribute((name, value))
+            }
+            // outside of tags anything else is text up to the next tag,
+            // whitespace included as it separates inline elements
+            _ if !self.is_lexing_tag => {
+                let mut text = String::new();
+                text.push(ch);
+
+                while let Some(next) = self.peek(0) {
+                    if next == '<' && opens_tag(self.peek(1)) {
+                        break;
+                    }
 
-                            value.push(iter.next().unwrap().clone());
-                        }
+                    text.push(next);
+                    self.next_char();
+                }
+
+                Token::Content(text)
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(token))
+    }
+
+    /// Consumes the `=` after an attribute name and the whitespace before
+    /// it. Leaves the input alone when there is none.
+    fn skip_to_equals(&mut self) -> bool {
+        let mut n = 0;
+        while self.peek(n).is_some_and(char::is_whitespace) {
+            n += 1;
+        }
+
+        if self.peek(n) != Some('=') {
+            return false;
+        }
 
-                        tokens.push(Token::Content(value));
+        self.skip(n + 1);
+        true
+    }
+
+    /// Reads the value of an attribute after its `=`. Values can be double
+    /// quoted, single quoted or unquoted, in which case they run until
+    /// whitespace or the end of the tag.
+    fn read_attribute_value(
+        &mut self,
+        start: Span,
+    ) -> Result<String, LexError> {
+        while self.peek(0).is_some_and(char::is_whitespace) {
+            self.next_char();
+        }
+
+        let mut value = String::new();
+        match self.peek(0) {
+            Some(quote @ ('"' | '\'')) => {
+                self.next_char();
+
+                loop {
+                    match self.next_char() {
+                        Some(c) if c == quote => break,
+                        Some('\n') | None => {
+                            return Err(LexError::UnterminatedAttribute {
+                                position: start.byte_offset,
+                            })
+                        }
+                        Some(c) => value.push(c),
                     }
                 }
+            }
+            Some(_) => {
+                while let Some(next) = self.peek(0) {
+                    if next.is_whitespace() || next == '>' {
+                        break;
+                    }
 
-                _ => {}
+                    value.push(next);
+                    self.next_char();
+                }
             }
+            None => return Err(LexError::UnexpectedEof),
+        }
+
+        // the tag has to be closed after the value
+        if self.peek(0).is_none() {
+            return Err(LexError::UnexpectedEof);
+        }
+
+        Ok(value)
+    }
+
+    /// Consumes the next character, keeping track of where the lexer is.
+    fn next_char(&mut self) -> Option<char> {
+        let ch = self.lookahead.pop_front().or_else(|| self.chars.next())?;
+
+        self.position += 1;
+        self.cursor.byte_offset += ch.len_utf8();
+        if ch == '\n' {
+            self.cursor.line += 1;
+            self.cursor.col = 1;
+        } else {
+            self.cursor.col += 1;
+        }
+
+        Some(ch)
+    }
+
+    /// The character `n` places after the next one, without consuming it.
+    fn peek(&mut self, n: usize) -> Option<char> {
+        while self.lookahead.len() <= n {
+            let ch = self.chars.next()?;
+            self.lookahead.push_back(ch);
         }
 
-        Some(tokens)
+        self.lookahead.get(n).copied()
+    }
+
+    fn skip(&mut self, count: usize) {
+        for _ in 0..count {
+            self.next_char();
+        }
+    }
+
+    fn starts_with(&mut self, pattern: &str) -> bool {
+        pattern
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.peek(i) == Some(c))
+    }
+
+    fn starts_with_ignore_case(&mut self, pattern: &str) -> bool {
+        pattern.chars().enumerate().all(|(i, c)| {
+            self.peek(i).is_some_and(|n| n.eq_ignore_ascii_case(&c))
+        })
+    }
+
+    fn next_word(&mut self) -> String {
+        let mut value = String::new();
+        while let Some(next) = self.peek(0) {
+            if next.is_alphanumeric() || next == '-' {
+                value.push(next);
+                self.next_char();
+            } else {
+                break;
+            }
+        }
+
+        value
+    }
+
+    /// Reads everything up to the end tag of the current raw text element,
+    /// which is left in the input. Runs to the end of the input when it
+    /// never closes.
+    fn read_raw_text(&mut self) -> String {
+        let tag = self.current_tag.clone();
+        let mut text = String::new();
+        while !self.is_end_tag_of(&tag) {
+            match self.next_char() {
+                Some(c) => text.push(c),
+                None => break,
+            }
+        }
+
+        text
+    }
+
+    /// Whether the input continues with `</tag`, in any case, followed by
+    /// something that can not be part of the tag name.
+    fn is_end_tag_of(&mut self, tag: &str) -> bool {
+        if !self.starts_with("</") {
+            return false;
+        }
+
+        let name_matches = tag.chars().enumerate().all(|(i, c)| {
+            self.peek(i + 2).is_some_and(|n| n.eq_ignore_ascii_case(&c))
+        });
+
+        name_matches
+            && !self
+                .peek(tag.chars().count() + 2)
+                .is_some_and(|n| n.is_alphanumeric() || n == '-')
     }
 
     pub fn validate(tokens: &Vec<Token>) -> bool {
         let mut tags = VecDeque::new();
         for token in tokens {
             match token {
-                Token::TagBegin(tag) if !Lexer::is_tag_self_closing(tag) => tags.push_back(tag),
+                Token::TagBegin(tag) if !Lexer::is_tag_self_closing(tag) => {
+                    tags.push_back(tag)
+                }
+                Token::TagSelfClose(tag)
+                    if !Lexer::is_tag_self_closing(tag) =>
+                {
+                    tags.pop_back();
+                }
                 Token::TagEnd(tag) => {
                     if let Some(last_tag) = tags.pop_back() {
                         if last_tag != tag {
@@ -150,25 +482,24 @@ impl Lexer {
         true
     }
 
-    pub fn is_tag_self_closing(tag: &str) -> bool {
-        match tag.to_lowercase().as_str() {
-            "doctype" | "br" | "hr" | "img" | "input" | "meta" | "link" => true,
-            _ => false,
-        }
+    /// Whether the body of the element is kept as plain text instead of
+    /// being lexed as markup.
+    pub fn is_raw_text_tag(tag: &str) -> bool {
+        tag.eq_ignore_ascii_case("style") || tag.eq_ignore_ascii_case("script")
     }
-}
 
-fn get_next_word(iter: &mut Peekable<Iter<char>>) -> String {
-    let mut value = String::new();
-    while let Some(&&next) = iter.peek() {
-        if next.is_alphanumeric() || next == '-' {
-            value.push(iter.next().unwrap().clone());
-        } else {
-            break;
-        }
+    pub fn is_tag_self_closing(tag: &str) -> bool {
+        matches!(
+            tag.to_lowercase().as_str(),
+            "br" | "hr" | "img" | "input" | "meta" | "link"
+        )
     }
+}
 
-    value
+/// Whether a `<` followed by `next` starts a tag, an end tag, a comment or
+/// a doctype.
+fn opens_tag(next: Option<char>) -> bool {
+    next.is_some_and(|c| c.is_ascii_alphabetic() || c == '/' || c == '!')
 }
 
 #[cfg(test)]
@@ -185,7 +516,15 @@ mod tests {
         </html>
         "#;
         let mut lexer = Lexer::new(input);
-        let tokens = lexer.lex().expect("Failed to lex input");
+        let tokens: Vec<_> = lexer
+            .lex()
+            .expect("Failed to lex input")
+            .into_iter()
+            // leave out the indentation between the tags
+            .filter(|token| {
+                !matches!(token, Token::Content(text) if text.trim().is_empty())
+            })
+            .collect();
 
         // Expected tokens
         let expected_tokens = vec![
@@ -203,11 +542,335 @@ mod tests {
 
         assert_eq!(tokens.len(), expected_tokens.len());
 
-        for (i, (token, expected)) in tokens.iter().zip(expected_tokens.iter()).enumerate() {
+        for (i, (token, expected)) in
+            tokens.iter().zip(expected_tokens.iter()).enumerate()
+        {
             assert_eq!(token, expected, "Token mismatch at index {}", i);
         }
     }
 
+    #[test]
+    fn test_next_token_matches_lex() {
+        let input = "<!DOCTYPE html>\n<div class=\"a\"><!-- c --><br/>\
+                     <style>p > b {}</style>text</div>";
+        let batch = Lexer::new(input).lex().expect("Failed to lex input");
+
+        let mut lexer = Lexer::from_chars(input.chars());
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token() {
+            tokens.push(token.expect("Failed to lex input"));
+        }
+
+        assert_eq!(tokens, batch);
+        assert_eq!(tokens.last(), Some(&Token::EOF));
+        assert!(lexer.next_token().is_none());
+    }
+
+    #[test]
+    fn test_next_token_stops_after_error() {
+        let mut lexer = Lexer::new("<p>a</p><!-- never closed");
+        assert_eq!(
+            lexer.next_token(),
+            Some(Ok(Token::TagBegin("p".to_string())))
+        );
+        assert_eq!(lexer.token_span().col, 1);
+        lexer.next_token();
+        assert_eq!(lexer.token_span().col, 4);
+        lexer.next_token();
+
+        assert_eq!(
+            lexer.next_token(),
+            Some(Err(LexError::UnterminatedComment { position: 8 }))
+        );
+        assert!(lexer.next_token().is_none());
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let input = "<html>\n  <p class=\"a\">é</p>\n<div></div>";
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.lex().expect("Failed to lex input");
+        let spans = lexer.spans();
+        assert_eq!(spans.len(), tokens.len());
+
+        let span = |line, col, byte_offset| Span {
+            line,
+            col,
+            byte_offset,
+        };
+        assert_eq!(
+            tokens.iter().zip(spans).collect::<Vec<_>>(),
+            vec![
+                (&Token::TagBegin("html".to_string()), &span(1, 1, 0)),
+                (&Token::Content("\n  ".to_string()), &span(1, 7, 6)),
+                (&Token::TagBegin("p".to_string()), &span(2, 3, 9)),
+                (
+                    &Token::Attribute(("class".to_string(), "a".to_string())),
+                    &span(2, 6, 12)
+                ),
+                (&Token::Content("é".to_string()), &span(2, 16, 22)),
+                (&Token::TagEnd("p".to_string()), &span(2, 17, 24)),
+                (&Token::Content("\n".to_string()), &span(2, 21, 28)),
+                (&Token::TagBegin("div".to_string()), &span(3, 1, 29)),
+                (&Token::TagEnd("div".to_string()), &span(3, 6, 34)),
+                (&Token::EOF, &span(3, 12, 40)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_content_starting_with_punctuation() {
+        let mut lexer = Lexer::new("<p>(note)</p><p>> quoted & so on</p>");
+        assert_eq!(
+            lexer.lex(),
+            Ok(vec![
+                Token::TagBegin("p".to_string()),
+                Token::Content("(note)".to_string()),
+                Token::TagEnd("p".to_string()),
+                Token::TagBegin("p".to_string()),
+                Token::Content("> quoted & so on".to_string()),
+                Token::TagEnd("p".to_string()),
+                Token::EOF,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_whitespace_between_inline_elements() {
+        let mut lexer = Lexer::new("<b>A</b> B <i>C</i>");
+        assert_eq!(
+            lexer.lex(),
+            Ok(vec![
+                Token::TagBegin("b".to_string()),
+                Token::Content("A".to_string()),
+                Token::TagEnd("b".to_string()),
+                Token::Content(" B ".to_string()),
+                Token::TagBegin("i".to_string()),
+                Token::Content("C".to_string()),
+                Token::TagEnd("i".to_string()),
+                Token::EOF,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_lex_comment_between_elements() {
+        let input = "<p>a</p><!-- note --><p>b</p>";
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.lex().expect("Failed to lex input");
+
+        let expected_tokens = vec![
+            Token::TagBegin("p".to_string()),
+            Token::Content("a".to_string()),
+            Token::TagEnd("p".to_string()),
+            Token::Comment(" note ".to_string()),
+            Token::TagBegin("p".to_string()),
+            Token::Content("b".to_string()),
+            Token::TagEnd("p".to_string()),
+            Token::EOF,
+        ];
+
+        assert_eq!(tokens, expected_tokens);
+    }
+
+    #[test]
+    fn test_lex_comment_with_angle_brackets() {
+        let input = "<div><!-- <span>a > b</span> --></div>";
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.lex().expect("Failed to lex input");
+
+        let expected_tokens = vec![
+            Token::TagBegin("div".to_string()),
+            Token::Comment(" <span>a > b</span> ".to_string()),
+            Token::TagEnd("div".to_string()),
+            Token::EOF,
+        ];
+
+        assert_eq!(tokens, expected_tokens);
+    }
+
+    #[test]
+    fn test_lex_unterminated_comment() {
+        let mut lexer = Lexer::new("<p>a</p><!-- never closed");
+        assert_eq!(
+            lexer.lex(),
+            Err(LexError::UnterminatedComment { position: 8 })
+        );
+    }
+
+    #[test]
+    fn test_lex_unterminated_attribute() {
+        let mut lexer = Lexer::new("<p>é</p><div class=\"a\nb\"></div>");
+        assert_eq!(
+            lexer.lex(),
+            Err(LexError::UnterminatedAttribute { position: 14 })
+        );
+    }
+
+    #[test]
+    fn test_lex_attribute_quoting() {
+        let attributes = |input: &str| {
+            let mut lexer = Lexer::new(input);
+            lexer
+                .lex()
+                .expect("Failed to lex input")
+                .into_iter()
+                .filter_map(|token| match token {
+                    Token::Attribute(attribute) => Some(attribute),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let attribute =
+            |name: &str, value: &str| (name.to_string(), value.to_string());
+
+        assert_eq!(
+            attributes("<div id=main></div>"),
+            vec![attribute("id", "main")]
+        );
+        assert_eq!(
+            attributes("<div id='main' title='say \"hi\"'></div>"),
+            vec![attribute("id", "main"), attribute("title", "say \"hi\"")]
+        );
+        assert_eq!(
+            attributes("<input disabled type=text>"),
+            vec![attribute("disabled", ""), attribute("type", "text")]
+        );
+        assert_eq!(
+            attributes("<a href=/a?b=c class = \"x\">a</a>"),
+            vec![attribute("href", "/a?b=c"), attribute("class", "x")]
+        );
+    }
+
+    #[test]
+    fn test_lex_unexpected_eof() {
+        let mut lexer = Lexer::new("<div class=");
+        assert_eq!(lexer.lex(), Err(LexError::UnexpectedEof));
+
+        let mut lexer = Lexer::new("<div class=a");
+        assert_eq!(lexer.lex(), Err(LexError::UnexpectedEof));
+
+        // a `<` at the end can not open a tag and is text
+        let mut lexer = Lexer::new("<p>a</p><");
+        assert_eq!(
+            lexer.lex(),
+            Ok(vec![
+                Token::TagBegin("p".to_string()),
+                Token::Content("a".to_string()),
+                Token::TagEnd("p".to_string()),
+                Token::Content("<".to_string()),
+                Token::EOF,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_lex_doctype() {
+        let mut lexer = Lexer::new("<!DOCTYPE html>\n<p>a</p>");
+        let tokens = lexer.lex().expect("Failed to lex input");
+
+        // the keyword is not taken for a tag with an `html` attribute
+        let expected_tokens = vec![
+            Token::Doctype("html".to_string()),
+            Token::Content("\n".to_string()),
+            Token::TagBegin("p".to_string()),
+            Token::Content("a".to_string()),
+            Token::TagEnd("p".to_string()),
+            Token::EOF,
+        ];
+        assert_eq!(tokens, expected_tokens);
+
+        let mut lexer = Lexer::new("<!doctype html>");
+        assert_eq!(
+            lexer.lex(),
+            Ok(vec![Token::Doctype("html".to_string()), Token::EOF])
+        );
+
+        let mut lexer = Lexer::new("<!DOCTYPE html");
+        assert_eq!(lexer.lex(), Err(LexError::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_lex_self_closing_tags() {
+        let mut lexer = Lexer::new("<br/><div class=\"a\" /><span/>text");
+        let tokens = lexer.lex().expect("Failed to lex input");
+
+        let expected_tokens = vec![
+            Token::TagBegin("br".to_string()),
+            Token::TagSelfClose("br".to_string()),
+            Token::TagBegin("div".to_string()),
+            Token::Attribute(("class".to_string(), "a".to_string())),
+            Token::TagSelfClose("div".to_string()),
+            Token::TagBegin("span".to_string()),
+            Token::TagSelfClose("span".to_string()),
+            Token::Content("text".to_string()),
+            Token::EOF,
+        ];
+
+        assert_eq!(tokens, expected_tokens);
+        assert!(Lexer::validate(&tokens));
+    }
+
+    #[test]
+    fn test_style_body_is_raw_text() {
+        let input = "<style>p < b { color: red }</style><p>a</p>";
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.lex().expect("Failed to lex input");
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::TagBegin("style".to_string()),
+                Token::Content("p < b { color: red }".to_string()),
+                Token::TagEnd("style".to_string()),
+                Token::TagBegin("p".to_string()),
+                Token::Content("a".to_string()),
+                Token::TagEnd("p".to_string()),
+                Token::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_raw_text_with_markup_characters() {
+        let input = "<STYLE>a > b { color: red }</style >\
+                     <script>if (x<y && y>z) { s = \"</scripts>\"; }</SCRIPT>";
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.lex().expect("Failed to lex input");
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::TagBegin("STYLE".to_string()),
+                Token::Content("a > b { color: red }".to_string()),
+                Token::TagEnd("style".to_string()),
+                Token::TagBegin("script".to_string()),
+                Token::Content(
+                    "if (x<y && y>z) { s = \"</scripts>\"; }".to_string()
+                ),
+                Token::TagEnd("SCRIPT".to_string()),
+                Token::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_raw_text() {
+        let input = "<script type=\"module\">let a = 1 < 2;";
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.lex().expect("Failed to lex input");
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::TagBegin("script".to_string()),
+                Token::Attribute(("type".to_string(), "module".to_string())),
+                Token::Content("let a = 1 < 2;".to_string()),
+                Token::EOF,
+            ]
+        );
+    }
+
     #[test]
     fn test_validate_correctly_nested_tags() {
         let tokens = vec![
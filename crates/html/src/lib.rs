@@ -1,2 +1,11 @@
+//! HTML lexer, parser and serializer. Nothing in here depends on the
+//! renderer, and without the default `std` feature the crate only needs
+//! `alloc`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod entities;
 pub mod lexer;
 pub mod parser;
+pub mod serializer;
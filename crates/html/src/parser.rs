@@ -1,87 +1,427 @@
-use std::collections::HashMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
 
+use crate::entities::decode_entities;
 use crate::lexer::*;
 
+/// How deeply elements may be nested before parsing gives up, unless
+/// `Parser::set_max_depth` says otherwise.
+pub const DEFAULT_MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Lex(LexError),
+    /// An element was nested more than `max_depth` elements deep. `span` is
+    /// where its opening tag starts.
+    TooDeep {
+        max_depth: usize,
+        span: Span,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Lex(err) => err.fmt(f),
+            ParseError::TooDeep { max_depth, span } => write!(
+                f,
+                "elements nested more than {} deep at line {}, column {}",
+                max_depth, span.line, span.col
+            ),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Lex(err) => Some(err),
+            ParseError::TooDeep { .. } => None,
+        }
+    }
+}
+
+impl From<LexError> for ParseError {
+    fn from(err: LexError) -> Self {
+        ParseError::Lex(err)
+    }
+}
+
+/// Attributes of an element in source order. Names are matched ASCII
+/// case-insensitively and only the first occurrence of a name is kept.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Attributes {
+    entries: Vec<(String, String)>,
+}
+
+impl Attributes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an attribute unless one with the same name already exists.
+    /// Returns whether the attribute was added.
+    pub fn insert(&mut self, name: String, value: String) -> bool {
+        if self.get(&name).is_some() {
+            return false;
+        }
+
+        self.entries.push((name, value));
+        true
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
 #[derive(Debug)]
 pub enum Node {
+    /// Root of a parsed document, holding every top-level node in order.
+    Document {
+        children: Vec<Node>,
+    },
     Element {
         tag: String,
-        attributes: HashMap<String, String>,
+        attributes: Attributes,
         children: Vec<Node>,
+        /// Where the opening tag starts in the input.
+        span: Span,
     },
     Text(String),
+    /// Text of a `<!-- ... -->` comment, only kept when the parser was asked
+    /// to preserve comments.
+    Comment(String),
+}
+
+impl Node {
+    /// Calls `f` with this node and every node below it, parents before
+    /// their children. `f` also gets how deep each node is nested below
+    /// this one.
+    pub fn walk<'n, F: FnMut(&'n Node, usize)>(&'n self, f: &mut F) {
+        self.walk_at(0, f);
+    }
+
+    fn walk_at<'n, F: FnMut(&'n Node, usize)>(
+        &'n self,
+        depth: usize,
+        f: &mut F,
+    ) {
+        // nodes still to visit, the next one on top, so deep trees do not
+        // run out of stack
+        let mut pending = vec![(self, depth)];
+        while let Some((node, depth)) = pending.pop() {
+            f(node, depth);
+
+            let children = match node {
+                Node::Document { children }
+                | Node::Element { children, .. } => children,
+                Node::Text(_) | Node::Comment(_) => continue,
+            };
+            pending
+                .extend(children.iter().rev().map(|child| (child, depth + 1)));
+        }
+    }
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
+    spans: Vec<Span>,
+    well_formed: bool,
+    doctype: Option<String>,
+    preserve_comments: bool,
+    max_depth: usize,
 }
 
 impl Parser {
-    pub fn new(input: &str) -> Self {
+    pub fn new(input: &str) -> Result<Self, LexError> {
         let mut lexer = Lexer::new(input);
+        let tokens = lexer.lex()?;
+        let spans = lexer.spans().to_vec();
+
+        // badly nested documents are still parsed, missing end tags are
+        // implied the way browsers do
+        let well_formed = Lexer::validate(&tokens);
+
+        let doctype = tokens.iter().find_map(|token| match token {
+            Token::Doctype(doctype) => Some(doctype.clone()),
+            _ => None,
+        });
+
+        Ok(Self {
+            tokens,
+            spans,
+            well_formed,
+            doctype,
+            preserve_comments: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+        })
+    }
+
+    /// Lexes and parses `input` in one go into its top-level nodes, text
+    /// included like in `parse_document`. Any input gives a tree or an
+    /// error, it never panics.
+    pub fn try_parse(input: &str) -> Result<Vec<Node>, ParseError> {
+        Parser::new(input)?.parse_top_level()
+    }
+
+    /// Whether comments show up as `Node::Comment` in the parsed tree. They
+    /// are dropped by default.
+    pub fn preserve_comments(&mut self, preserve: bool) {
+        self.preserve_comments = preserve;
+    }
 
-        // TODO: if this fails print a good error message
-        let tokens = lexer.lex().unwrap();
+    /// How deeply elements may be nested. Parsing a document nested any
+    /// deeper fails with `ParseError::TooDeep` rather than running out of
+    /// stack.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
 
-        assert!(Lexer::validate(&tokens));
+    /// What the first `<!DOCTYPE ...>` of the input declared, like `html`.
+    /// Doctypes are not part of the parsed tree.
+    pub fn doctype(&self) -> Option<&str> {
+        self.doctype.as_deref()
+    }
 
-        Self { tokens }
+    /// Whether every end tag in the input matched the element it closed.
+    /// When it is `false` the tree was built by implying end tags.
+    pub fn is_well_formed(&self) -> bool {
+        self.well_formed
     }
 
-    pub fn parse(&self) -> Option<Vec<Node>> {
+    pub fn parse(&self) -> Result<Vec<Node>, ParseError> {
         let mut elements = Vec::new();
         let mut index = 0;
 
         while index < self.tokens.len() {
             match &self.tokens[index] {
-                Token::TagBegin(tag) => {
-                    elements.push(self.parse_element(tag.clone(), &mut index))
+                Token::TagBegin(tag) => elements.push(self.parse_element(
+                    tag.clone(),
+                    &mut index,
+                    &mut Vec::new(),
+                )?),
+                Token::EOF => break,
+                _ => index += 1,
+            }
+        }
+
+        Ok(elements)
+    }
+
+    /// Parses the input into a single `Node::Document` root. Unlike `parse`,
+    /// top-level text is kept alongside the elements.
+    pub fn parse_document(&self) -> Result<Node, ParseError> {
+        Ok(Node::Document {
+            children: self.parse_top_level()?,
+        })
+    }
+
+    /// The nodes `parse_document` puts below the document.
+    fn parse_top_level(&self) -> Result<Vec<Node>, ParseError> {
+        let mut children = Vec::new();
+        let mut index = 0;
+
+        while index < self.tokens.len() {
+            match &self.tokens[index] {
+                Token::TagBegin(tag) => children.push(self.parse_element(
+                    tag.clone(),
+                    &mut index,
+                    &mut Vec::new(),
+                )?),
+                Token::Content(content) => {
+                    children.push(Node::Text(decode_entities(content)));
+                    index += 1;
+                }
+                Token::Comment(comment) if self.preserve_comments => {
+                    children.push(Node::Comment(comment.clone()));
+                    index += 1;
                 }
                 Token::EOF => break,
                 _ => index += 1,
             }
         }
 
-        Some(elements)
+        trim_whitespace_text(&mut children);
+
+        Ok(children)
     }
 
-    fn parse_element(&self, tag: String, index: &mut usize) -> Node {
+    /// Parses the element starting at `index`. `open` holds the tags of the
+    /// elements it is nested in, so an end tag for one of them can close
+    /// this element too.
+    fn parse_element(
+        &self,
+        tag: String,
+        index: &mut usize,
+        open: &mut Vec<String>,
+    ) -> Result<Node, ParseError> {
         let mut children = Vec::new();
-        let mut attributes = HashMap::new();
+        let mut attributes = Attributes::new();
+        let span = self.spans[*index];
+
+        if open.len() >= self.max_depth {
+            return Err(ParseError::TooDeep {
+                max_depth: self.max_depth,
+                span,
+            });
+        }
+
+        // void elements like <br> never have children, so stop right after
+        // their attributes
+        let is_void = Lexer::is_tag_self_closing(tag.as_str());
+        // raw text like a stylesheet is kept exactly as written
+        let is_raw_text = Lexer::is_raw_text_tag(tag.as_str());
 
         *index += 1;
+        open.push(tag.clone());
 
         while *index < self.tokens.len() {
             match &self.tokens[*index] {
-                Token::TagBegin(child_tag) => {
-                    // do not add children if the tag is self contained
-                    if Lexer::is_tag_self_closing(tag.as_str()) {
-                        break;
-                    }
-
-                    children.push(self.parse_element(child_tag.clone(), index))
+                Token::Attribute(attribute) => {
+                    attributes.insert(
+                        attribute.0.clone(),
+                        decode_entities(&attribute.1),
+                    );
+                    *index += 1;
                 }
-                Token::TagEnd(_) => {
+                Token::TagSelfClose(_) => {
                     *index += 1;
                     break;
                 }
-                Token::Attribute(attribute) => {
-                    attributes.insert(attribute.0.clone(), attribute.1.clone());
+                _ if is_void => break,
+                // the new element ends this one, leave it to the parent
+                Token::TagBegin(child_tag)
+                    if is_implicitly_closed_by(&tag, child_tag) =>
+                {
+                    break
+                }
+                Token::TagBegin(child_tag) => children.push(
+                    self.parse_element(child_tag.clone(), index, open)?,
+                ),
+                Token::TagEnd(end_tag)
+                    if end_tag.eq_ignore_ascii_case(&tag) =>
+                {
                     *index += 1;
+                    break;
                 }
-                Token::Content(content) => {
+                // an end tag of an ancestor closes every element inside it
+                Token::TagEnd(end_tag)
+                    if open.iter().any(|open_tag| {
+                        open_tag.eq_ignore_ascii_case(end_tag)
+                    }) =>
+                {
+                    break
+                }
+                // stray end tags without an open element are dropped
+                Token::TagEnd(_) => *index += 1,
+                Token::Content(content) if is_raw_text => {
                     children.push(Node::Text(content.clone()));
                     *index += 1;
                 }
+                Token::Content(content) => {
+                    children.push(Node::Text(decode_entities(content)));
+                    *index += 1;
+                }
+                Token::Comment(comment) if self.preserve_comments => {
+                    children.push(Node::Comment(comment.clone()));
+                    *index += 1;
+                }
+                Token::Comment(_) | Token::Doctype(_) => *index += 1,
                 Token::EOF => break,
             }
         }
 
-        Node::Element {
+        open.pop();
+
+        if !is_raw_text {
+            trim_whitespace_text(&mut children);
+        }
+
+        Ok(Node::Element {
             tag,
             attributes,
             children,
-        }
+            span,
+        })
+    }
+}
+
+/// Drops text that is only whitespace at the start and end of `children`,
+/// which comes from indenting the markup. Whitespace between two siblings
+/// stays, it keeps inline elements like `<b>A</b> <i>B</i>` apart.
+fn trim_whitespace_text(children: &mut Vec<Node>) {
+    let is_whitespace = |node: &Node| matches!(node, Node::Text(text) if text.trim().is_empty());
+
+    while children.last().is_some_and(is_whitespace) {
+        children.pop();
+    }
+
+    let leading = children
+        .iter()
+        .take_while(|node| is_whitespace(node))
+        .count();
+    children.drain(..leading);
+}
+
+/// Whether an open `open_tag` element ends when a `next_tag` element starts,
+/// like a paragraph does when a block begins or a list item when the next
+/// item does.
+fn is_implicitly_closed_by(open_tag: &str, next_tag: &str) -> bool {
+    let open_tag = open_tag.to_lowercase();
+    let next_tag = next_tag.to_lowercase();
+
+    match open_tag.as_str() {
+        "p" => matches!(
+            next_tag.as_str(),
+            "address"
+                | "article"
+                | "aside"
+                | "blockquote"
+                | "div"
+                | "dl"
+                | "fieldset"
+                | "footer"
+                | "form"
+                | "h1"
+                | "h2"
+                | "h3"
+                | "h4"
+                | "h5"
+                | "h6"
+                | "header"
+                | "hr"
+                | "main"
+                | "nav"
+                | "ol"
+                | "p"
+                | "pre"
+                | "section"
+                | "table"
+                | "ul"
+        ),
+        "li" => next_tag == "li",
+        _ => false,
     }
 }
 
@@ -92,7 +432,7 @@ mod tests {
     #[test]
     fn test_parse_single_element() {
         let input = "<html></html>";
-        let parser = Parser::new(input);
+        let parser = Parser::new(input).expect("Lexing failed");
         let nodes = parser.parse().expect("Parsing failed");
 
         assert_eq!(nodes.len(), 1);
@@ -100,6 +440,7 @@ mod tests {
             tag,
             attributes,
             children,
+            ..
         } = &nodes[0]
         {
             assert_eq!(tag, "html");
@@ -113,7 +454,7 @@ mod tests {
     #[test]
     fn test_parse_element_with_text() {
         let input = "<h1>Hello, World!</h1>";
-        let parser = Parser::new(input);
+        let parser = Parser::new(input).expect("Lexing failed");
         let nodes = parser.parse().expect("Parsing failed");
 
         assert_eq!(nodes.len(), 1);
@@ -121,6 +462,7 @@ mod tests {
             tag,
             attributes,
             children,
+            ..
         } = &nodes[0]
         {
             assert_eq!(tag, "h1");
@@ -137,10 +479,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_decodes_entities() {
+        let input = r#"<p title="a &lt; b">AT&amp;T &#x26; more</p>"#;
+        let parser = Parser::new(input).expect("Lexing failed");
+        let nodes = parser.parse().expect("Parsing failed");
+
+        if let Node::Element {
+            attributes,
+            children,
+            ..
+        } = &nodes[0]
+        {
+            assert_eq!(attributes.get("title").unwrap(), "a < b");
+
+            if let Node::Text(content) = &children[0] {
+                assert_eq!(content, "AT&T & more");
+            } else {
+                panic!("Expected a Text node");
+            }
+        } else {
+            panic!("Expected an Element node");
+        }
+    }
+
     #[test]
     fn test_parse_nested_elements() {
         let input = "<div><p>Paragraph</p></div>";
-        let parser = Parser::new(input);
+        let parser = Parser::new(input).expect("Lexing failed");
         let nodes = parser.parse().expect("Parsing failed");
 
         assert_eq!(nodes.len(), 1);
@@ -173,7 +539,7 @@ mod tests {
     #[test]
     fn test_parse_element_with_attributes() {
         let input = r#"<img src="image.png" alt="An image"/>"#;
-        let parser = Parser::new(input);
+        let parser = Parser::new(input).expect("Lexing failed");
         let nodes = parser.parse().expect("Parsing failed");
 
         assert_eq!(nodes.len(), 1);
@@ -181,6 +547,7 @@ mod tests {
             tag,
             attributes,
             children,
+            ..
         } = &nodes[0]
         {
             assert_eq!(tag, "img");
@@ -197,10 +564,290 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_attributes_case_insensitive_lookup() {
+        let input = r#"<div class="container"></div>"#;
+        let parser = Parser::new(input).expect("Lexing failed");
+        let nodes = parser.parse().expect("Parsing failed");
+
+        if let Node::Element { attributes, .. } = &nodes[0] {
+            assert_eq!(attributes.get("CLASS"), Some("container"));
+            assert_eq!(attributes.get("Class"), Some("container"));
+            assert_eq!(attributes.get("id"), None);
+        } else {
+            panic!("Expected an Element node");
+        }
+    }
+
+    #[test]
+    fn test_attributes_keep_first_duplicate() {
+        let input = r#"<div id="first" ID="second" class="a"></div>"#;
+        let parser = Parser::new(input).expect("Lexing failed");
+        let nodes = parser.parse().expect("Parsing failed");
+
+        if let Node::Element { attributes, .. } = &nodes[0] {
+            assert_eq!(attributes.len(), 2);
+            assert_eq!(attributes.get("id"), Some("first"));
+            assert_eq!(
+                attributes.iter().collect::<Vec<_>>(),
+                vec![("id", "first"), ("class", "a")]
+            );
+        } else {
+            panic!("Expected an Element node");
+        }
+    }
+
+    #[test]
+    fn test_parse_self_closing_elements() {
+        let input = "<p><br/>a<i/><span/>text</p>";
+        let parser = Parser::new(input).expect("Lexing failed");
+        let nodes = parser.parse().expect("Parsing failed");
+
+        if let Node::Element { children, .. } = &nodes[0] {
+            assert_eq!(children.len(), 5);
+
+            let tags: Vec<_> = children
+                .iter()
+                .map(|child| match child {
+                    Node::Element { tag, children, .. } => {
+                        assert!(children.is_empty());
+                        tag.as_str()
+                    }
+                    Node::Text(text) => text.as_str(),
+                    Node::Document { .. } | Node::Comment(_) => {
+                        panic!("Unexpected node {:?}", child)
+                    }
+                })
+                .collect();
+            assert_eq!(tags, vec!["br", "a", "i", "span", "text"]);
+        } else {
+            panic!("Expected an Element node");
+        }
+    }
+
+    fn element_tags(nodes: &[Node]) -> Vec<&str> {
+        nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::Element { tag, .. } => Some(tag.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_implied_paragraph_end() {
+        let input = "<p>a<p>b</p><p>c<div>d</div>";
+        let parser = Parser::new(input).expect("Lexing failed");
+        let nodes = parser.parse().expect("Parsing failed");
+
+        assert_eq!(element_tags(&nodes), vec!["p", "p", "p", "div"]);
+        if let Node::Element { children, .. } = &nodes[0] {
+            assert_eq!(children.len(), 1);
+            assert!(matches!(&children[0], Node::Text(text) if text == "a"));
+        } else {
+            panic!("Expected an Element node");
+        }
+    }
+
+    #[test]
+    fn test_implied_list_item_end() {
+        let input = "<ul><li>a<li>b</ul>";
+        let parser = Parser::new(input).expect("Lexing failed");
+        let nodes = parser.parse().expect("Parsing failed");
+
+        if let Node::Element { children, .. } = &nodes[0] {
+            assert_eq!(element_tags(children), vec!["li", "li"]);
+        } else {
+            panic!("Expected an Element node");
+        }
+    }
+
+    #[test]
+    fn test_missing_end_tags() {
+        let input = "<html><body><p>a</p><b>bold</p>after</body>";
+        let parser = Parser::new(input).expect("Lexing failed");
+        assert!(!parser.is_well_formed());
+
+        let nodes = parser.parse().expect("Parsing failed");
+        assert_eq!(element_tags(&nodes), vec!["html"]);
+
+        if let Node::Element { children, .. } = &nodes[0] {
+            assert_eq!(element_tags(children), vec!["body"]);
+            if let Node::Element { children, .. } = &children[0] {
+                // the stray </p> is dropped and </body> closes the <b>
+                assert_eq!(element_tags(children), vec!["p", "b"]);
+                assert_eq!(children.len(), 2);
+            } else {
+                panic!("Expected an Element node");
+            }
+        } else {
+            panic!("Expected an Element node");
+        }
+    }
+
+    #[test]
+    fn test_parse_document_root() {
+        let input = "stray <html><body></body></html><!-- trailing --><p>b</p>";
+        let parser = Parser::new(input).expect("Lexing failed");
+
+        if let Node::Document { children } = parser.parse_document().unwrap() {
+            // comments are not kept in the tree
+            assert_eq!(children.len(), 3);
+            assert!(
+                matches!(&children[0], Node::Text(text) if text == "stray ")
+            );
+            assert!(
+                matches!(&children[1], Node::Element { tag, .. } if tag == "html")
+            );
+            assert!(
+                matches!(&children[2], Node::Element { tag, .. } if tag == "p")
+            );
+        } else {
+            panic!("Expected a Document node");
+        }
+    }
+
+    #[test]
+    fn test_doctype_is_not_an_element() {
+        let parser =
+            Parser::new("<!DOCTYPE html><html><body></body></html>").unwrap();
+        assert_eq!(parser.doctype(), Some("html"));
+
+        if let Node::Document { children } = parser.parse_document().unwrap() {
+            assert_eq!(children.len(), 1);
+            assert!(
+                matches!(&children[0], Node::Element { tag, .. } if tag == "html")
+            );
+        } else {
+            panic!("Expected a Document node");
+        }
+
+        let parser = Parser::new("<p>a</p>").unwrap();
+        assert_eq!(parser.doctype(), None);
+    }
+
+    #[test]
+    fn test_element_spans() {
+        let input = "<html>\n<body>\n  <p>a</p>\n</body>\n</html>";
+        let parser = Parser::new(input).expect("Lexing failed");
+        let nodes = parser.parse().expect("Parsing failed");
+
+        let Node::Element { children, span, .. } = &nodes[0] else {
+            panic!("Expected an Element node");
+        };
+        assert_eq!(
+            *span,
+            Span {
+                line: 1,
+                col: 1,
+                byte_offset: 0
+            }
+        );
+
+        let Node::Element { children, .. } = &children[0] else {
+            panic!("Expected an Element node");
+        };
+        let Node::Element { tag, span, .. } = &children[0] else {
+            panic!("Expected an Element node");
+        };
+        assert_eq!(tag, "p");
+        assert_eq!((span.line, span.col, span.byte_offset), (3, 3, 16));
+    }
+
+    #[test]
+    fn test_preserve_comments() {
+        let input = "<p>a</p><!-- note --><div><br><!--inner--><p>b</p></div>";
+
+        let parser = Parser::new(input).unwrap();
+        let Node::Document { children } = parser.parse_document().unwrap()
+        else {
+            panic!("Expected a Document node");
+        };
+        assert_eq!(children.len(), 2);
+
+        let mut parser = Parser::new(input).unwrap();
+        parser.preserve_comments(true);
+        let Node::Document { children } = parser.parse_document().unwrap()
+        else {
+            panic!("Expected a Document node");
+        };
+        assert_eq!(children.len(), 3);
+        assert!(
+            matches!(&children[1], Node::Comment(comment) if comment == " note ")
+        );
+
+        // the comment after the void <br> belongs to the <div>
+        let Node::Element {
+            children: div_children,
+            ..
+        } = &children[2]
+        else {
+            panic!("Expected an Element node");
+        };
+        assert_eq!(div_children.len(), 3);
+        assert!(
+            matches!(&div_children[1], Node::Comment(comment) if comment == "inner")
+        );
+        assert!(
+            matches!(&div_children[2], Node::Element { tag, .. } if tag == "p")
+        );
+    }
+
+    #[test]
+    fn test_walk() {
+        let parser = Parser::new("<div><p>a</p><br></div>text").unwrap();
+
+        let mut visited = Vec::new();
+        parser.parse_document().unwrap().walk(&mut |node, depth| {
+            let name = match node {
+                Node::Document { .. } => "#document".to_string(),
+                Node::Element { tag, .. } => tag.clone(),
+                Node::Text(text) => format!("{:?}", text),
+                Node::Comment(_) => "#comment".to_string(),
+            };
+            visited.push((name, depth));
+        });
+
+        let expected = [
+            ("#document", 0),
+            ("div", 1),
+            ("p", 2),
+            ("\"a\"", 3),
+            ("br", 2),
+            ("\"text\"", 1),
+        ];
+        assert_eq!(
+            visited,
+            expected.map(|(name, depth)| (name.to_string(), depth))
+        );
+    }
+
+    #[test]
+    fn test_whitespace_between_inline_elements() {
+        let parser = Parser::new("<p>\n  <b>A</b> <i>B</i>\n</p>").unwrap();
+
+        let mut text = String::new();
+        let mut children = 0;
+        parser.parse_document().unwrap().walk(&mut |node, depth| {
+            if let Node::Text(content) = node {
+                text.push_str(content);
+            }
+            if depth == 2 {
+                children += 1;
+            }
+        });
+
+        // the indentation inside <p> is dropped, the space between the
+        // words is not
+        assert_eq!(text, "A B");
+        assert_eq!(children, 3);
+    }
+
     #[test]
     fn test_parse_multiple_elements() {
         let input = "<html><body><h1>Title</h1><p>Paragraph</p></body></html>";
-        let parser = Parser::new(input);
+        let parser = Parser::new(input).expect("Lexing failed");
         let nodes = parser.parse().expect("Parsing failed");
 
         assert_eq!(nodes.len(), 1);
@@ -259,4 +906,80 @@ mod tests {
             panic!("Expected an html Element node");
         }
     }
+
+    #[test]
+    fn test_deep_nesting_is_an_error() {
+        let input = "<div>".repeat(10_000) + &"</div>".repeat(10_000);
+        let parser = Parser::new(&input).expect("Lexing failed");
+
+        let err = parser.parse_document().unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::TooDeep {
+                max_depth: DEFAULT_MAX_DEPTH,
+                span: parser.spans[DEFAULT_MAX_DEPTH],
+            }
+        );
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut parser =
+            Parser::new("<div><p><b>a</b></p></div><i></i>").unwrap();
+
+        parser.set_max_depth(3);
+        let mut depths = Vec::new();
+        parser
+            .parse_document()
+            .unwrap()
+            .walk(&mut |_, depth| depths.push(depth));
+        assert_eq!(depths, vec![0, 1, 2, 3, 4, 1]);
+
+        parser.set_max_depth(2);
+        assert!(matches!(
+            parser.parse_document(),
+            Err(ParseError::TooDeep { max_depth: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_try_parse_malformed_input() {
+        let malformed = [
+            "<div",
+            "<p class=\"a",
+            "<a href='x>",
+            "<!-- never closed",
+            "<!doctype",
+        ];
+        for input in malformed {
+            assert!(
+                matches!(Parser::try_parse(input), Err(ParseError::Lex(_))),
+                "{:?} should not parse",
+                input
+            );
+        }
+
+        // stray end tags and lone `>` are dropped like browsers do
+        for input in [">", "</div></p>", "</", "<<>>", "\0\u{ff}<\u{1}>"] {
+            assert!(Parser::try_parse(input).is_ok(), "{:?}", input);
+        }
+
+        // a `<` that does not open a tag is text
+        let nodes = Parser::try_parse("a < b").unwrap();
+        assert!(matches!(&nodes[..], [Node::Text(text)] if text == "a < b"));
+        let nodes = Parser::try_parse("<p>1 <2</p><").unwrap();
+        let [Node::Element { children, .. }, Node::Text(end)] = &nodes[..]
+        else {
+            panic!("unexpected nodes {:?}", nodes);
+        };
+        assert!(matches!(&children[..], [Node::Text(text)] if text == "1 <2"));
+        assert_eq!(end, "<");
+
+        let nested = "<b>".repeat(DEFAULT_MAX_DEPTH + 1);
+        assert!(matches!(
+            Parser::try_parse(&nested),
+            Err(ParseError::TooDeep { .. })
+        ));
+    }
 }
@@ -0,0 +1,125 @@
+use alloc::format;
+use alloc::string::String;
+
+use crate::entities::{escape_attribute, escape_text};
+use crate::lexer::Lexer;
+use crate::parser::Node;
+
+/// Writes `node` back out as html. Parsing the result gives the same tree,
+/// with end tags that were implied in the source written out.
+pub fn serialize(node: &Node) -> String {
+    let mut output = String::new();
+    write_node(node, false, &mut output);
+    output
+}
+
+/// Appends `node` to `output`. Text inside a raw text element like
+/// `<style>` is not decoded by the parser, so it is written as it is.
+fn write_node(node: &Node, raw_text: bool, output: &mut String) {
+    match node {
+        Node::Document { children } => {
+            for child in children {
+                write_node(child, false, output);
+            }
+        }
+        Node::Element {
+            tag,
+            attributes,
+            children,
+            ..
+        } => {
+            output.push('<');
+            output.push_str(tag);
+            for (name, value) in attributes.iter() {
+                output.push_str(&format!(
+                    " {}=\"{}\"",
+                    name,
+                    escape_attribute(value)
+                ));
+            }
+
+            // void elements can not have children or an end tag
+            if Lexer::is_tag_self_closing(tag) {
+                output.push_str("/>");
+                return;
+            }
+            output.push('>');
+
+            let raw_text = Lexer::is_raw_text_tag(tag);
+            for child in children {
+                write_node(child, raw_text, output);
+            }
+
+            output.push_str("</");
+            output.push_str(tag);
+            output.push('>');
+        }
+        Node::Text(text) if raw_text => output.push_str(text),
+        Node::Text(text) => output.push_str(&escape_text(text)),
+        Node::Comment(comment) => {
+            output.push_str("<!--");
+            output.push_str(comment);
+            output.push_str("-->");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn parse(input: &str) -> Node {
+        let mut parser = Parser::new(input).expect("Lexing failed");
+        parser.preserve_comments(true);
+        parser.parse_document().expect("Parsing failed")
+    }
+
+    /// One line per node with its depth, leaving out where it was in the
+    /// source.
+    fn outline(node: &Node) -> Vec<String> {
+        let mut lines = Vec::new();
+        node.walk(&mut |node, depth| {
+            let line = match node {
+                Node::Document { .. } => "#document".to_string(),
+                Node::Element {
+                    tag, attributes, ..
+                } => format!("<{}> {:?}", tag, attributes),
+                Node::Text(text) => format!("{:?}", text),
+                Node::Comment(comment) => format!("<!--{}-->", comment),
+            };
+            lines.push(format!("{}{}", depth, line));
+        });
+        lines
+    }
+
+    #[test]
+    fn test_serialize() {
+        let input = r#"<div class="a"><p>hi &amp; bye</p></div>"#;
+        let document = parse(input);
+
+        assert_eq!(serialize(&document), input);
+        assert_eq!(outline(&parse(&serialize(&document))), outline(&document));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let inputs = [
+            "<ul><li>one<li>two</ul><p>a<br>b<hr>",
+            r#"<img src="a.png" alt="say &quot;hi&quot;"><p title="x > y">a &lt; b</p>"#,
+            "<style>p > b { color: red }</style><!-- note --><p>c</p>",
+        ];
+
+        for input in inputs {
+            let document = parse(input);
+            let serialized = serialize(&document);
+            assert_eq!(
+                outline(&parse(&serialized)),
+                outline(&document),
+                "{} was serialized as {}",
+                input,
+                serialized
+            );
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! Uses the crate the way a program that only analyzes html would, through
+//! its public API and without the renderer.
+
+use html::lexer::{Lexer, Token};
+use html::parser::{Node, Parser};
+use html::serializer::serialize;
+
+#[test]
+fn test_lex_and_parse_document() {
+    let input =
+        r#"<!DOCTYPE html><ul id="list"><li>one &amp; two<li>three</ul>"#;
+
+    let tokens = Lexer::new(input).lex().unwrap();
+    assert_eq!(tokens[0], Token::Doctype("html".to_string()));
+
+    let parser = Parser::new(input).unwrap();
+    assert_eq!(parser.doctype(), Some("html"));
+    let document = parser.parse_document().unwrap();
+
+    let mut items = Vec::new();
+    document.walk(&mut |node, depth| {
+        if let Node::Element { tag, children, .. } = node {
+            if tag == "li" {
+                if let [Node::Text(text)] = children.as_slice() {
+                    items.push((text.clone(), depth));
+                }
+            }
+        }
+    });
+    assert_eq!(
+        items,
+        [("one & two".to_string(), 2), ("three".to_string(), 2)]
+    );
+
+    assert_eq!(
+        serialize(&document),
+        r#"<ul id="list"><li>one &amp; two</li><li>three</li></ul>"#
+    );
+}
+
+#[test]
+fn test_malformed_input_is_an_error() {
+    assert!(Parser::try_parse("<p class=").is_err());
+    assert!(Parser::try_parse("<p>fine</p>").is_ok());
+}
@@ -0,0 +1,54 @@
+use glium::glutin::event_loop::ControlFlow;
+
+/// State of the browser window that outlives the page shown in it.
+#[derive(Default)]
+pub struct App {
+    on_quit: Option<Box<dyn FnMut()>>,
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets what runs right before the window closes, like saving state
+    /// the embedder wants to keep.
+    pub fn set_on_quit(&mut self, on_quit: impl FnMut() + 'static) {
+        self.on_quit = Some(Box::new(on_quit));
+    }
+
+    /// Runs the quit hook and stops the event loop, the window closes once
+    /// it is dropped. Escape and the close button both end up here.
+    pub fn quit(&mut self, control_flow: &mut ControlFlow) {
+        if let Some(on_quit) = &mut self.on_quit {
+            on_quit();
+        }
+        *control_flow = ControlFlow::Exit;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn test_quit_runs_the_hook() {
+        let mut control_flow = ControlFlow::Wait;
+        App::new().quit(&mut control_flow);
+        assert_eq!(control_flow, ControlFlow::Exit);
+
+        let quits = Rc::new(Cell::new(0));
+        let mut app = App::new();
+        app.set_on_quit({
+            let quits = quits.clone();
+            move || quits.set(quits.get() + 1)
+        });
+        let mut control_flow = ControlFlow::Wait;
+        app.quit(&mut control_flow);
+        assert_eq!(quits.get(), 1);
+        assert_eq!(control_flow, ControlFlow::Exit);
+    }
+}
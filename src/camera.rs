@@ -1,27 +1,51 @@
 use glm::Mat4;
 
+/// Smallest zoom factor a camera accepts.
+pub const MIN_ZOOM: f32 = 0.25;
+/// Largest zoom factor a camera accepts.
+pub const MAX_ZOOM: f32 = 5.0;
+
 pub struct Camera {
     pub position: [f32; 2],
     pub screen_size: (u32, u32),
+    // how much larger than one pixel a unit is drawn, see `set_zoom`
+    zoom: f32,
     pub near_clip: f32,
     pub far_clip: f32,
 }
 
 impl Camera {
+    /// Maps pixel coordinates, with the origin at the top left corner, to
+    /// normalized device coordinates. One unit is one pixel on both axes, so
+    /// quads keep their size on windows of any aspect ratio. The zoom
+    /// scales everything around the center of the screen.
     pub fn get_projection(&self) -> Mat4 {
-        glm::ortho(
+        let projection = glm::ortho(
             0.0,
-            self.screen_size.0 as f32 * 0.5,
+            self.screen_size.0 as f32,
             self.screen_size.1 as f32,
             0.0,
             self.near_clip,
             self.far_clip,
-        )
+        );
+
+        glm::scaling(&glm::vec3(self.zoom, self.zoom, 1.0)) * projection
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Sets the zoom factor, 2 draws everything twice as large. It is
+    /// clamped between `MIN_ZOOM` and `MAX_ZOOM`.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
     }
 
+    /// Moves the world opposite to the camera, so the point at `position`
+    /// ends up at the origin of the view.
     pub fn get_view(&self) -> Mat4 {
-        let transform = Mat4::identity();
-        transform.prepend_translation(&glm::vec3(
+        let transform = Mat4::identity().prepend_translation(&glm::vec3(
             self.position[0],
             self.position[1],
             0.0,
@@ -29,15 +53,185 @@ impl Camera {
 
         transform.try_inverse().unwrap()
     }
+
+    /// Moves the camera down by `delta` pixels, or up for negative values,
+    /// keeping the viewport inside content of the given height. Returns
+    /// whether the camera moved.
+    pub fn scroll_clamped(
+        &mut self,
+        delta: f32,
+        content_height: f32,
+        viewport_height: f32,
+    ) -> bool {
+        scroll_axis(
+            &mut self.position[1],
+            delta,
+            content_height,
+            viewport_height,
+        )
+    }
+
+    /// Like `scroll_clamped`, but moves the camera right by `delta` pixels
+    /// inside content of the given width.
+    pub fn scroll_x_clamped(
+        &mut self,
+        delta: f32,
+        content_width: f32,
+        viewport_width: f32,
+    ) -> bool {
+        scroll_axis(&mut self.position[0], delta, content_width, viewport_width)
+    }
+}
+
+/// Moves `position` by `delta` along one axis, so that the viewport stays
+/// inside the content. Returns whether it moved.
+fn scroll_axis(
+    position: &mut f32,
+    delta: f32,
+    content_size: f32,
+    viewport_size: f32,
+) -> bool {
+    let max_scroll = (content_size - viewport_size).max(0.0);
+    let moved_to = (*position + delta).clamp(0.0, max_scroll);
+
+    let moved = moved_to != *position;
+    *position = moved_to;
+    moved
 }
 
 impl Default for Camera {
     fn default() -> Self {
         Self {
             position: [0.0, 0.0],
-            screen_size: (0, 0).into(),
+            screen_size: (0, 0),
+            zoom: 1.0,
             near_clip: -1.0,
             far_clip: 1.0,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(camera: &Camera, x: f32, y: f32) -> (f32, f32) {
+        let ndc = camera.get_projection()
+            * camera.get_view()
+            * glm::vec4(x, y, 0.0, 1.0);
+        (ndc.x, ndc.y)
+    }
+
+    #[test]
+    fn test_projection_keeps_pixel_size() {
+        let camera = Camera {
+            screen_size: (800, 600),
+            ..Default::default()
+        };
+
+        assert_eq!(project(&camera, 0.0, 0.0), (-1.0, 1.0));
+        assert_eq!(project(&camera, 800.0, 600.0), (1.0, -1.0));
+
+        // a 100x100 quad covers 100 of the 800 and 100 of the 600 pixels
+        let (left, top) = project(&camera, 100.0, 100.0);
+        let (right, bottom) = project(&camera, 200.0, 200.0);
+        assert!((right - left - 2.0 * 100.0 / 800.0).abs() < 1e-6);
+        assert!((top - bottom - 2.0 * 100.0 / 600.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_projection_spans_full_width() {
+        let camera = Camera {
+            screen_size: (1280, 720),
+            ..Default::default()
+        };
+
+        assert_eq!(project(&camera, 1280.0, 0.0).0, 1.0);
+        assert_eq!(project(&camera, 640.0, 0.0).0, 0.0);
+    }
+
+    #[test]
+    fn test_zoom_scales_around_center() {
+        let mut camera = Camera {
+            screen_size: (800, 600),
+            ..Default::default()
+        };
+        camera.set_zoom(2.0);
+
+        // only the middle half of the screen is visible on each axis
+        assert_eq!(project(&camera, 200.0, 150.0), (-1.0, 1.0));
+        assert_eq!(project(&camera, 600.0, 450.0), (1.0, -1.0));
+        assert_eq!(project(&camera, 400.0, 300.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_zoom_is_clamped() {
+        let mut camera = Camera::default();
+
+        camera.set_zoom(1.5);
+        assert_eq!(camera.zoom(), 1.5);
+        camera.set_zoom(100.0);
+        assert_eq!(camera.zoom(), MAX_ZOOM);
+        camera.set_zoom(0.0);
+        assert_eq!(camera.zoom(), MIN_ZOOM);
+    }
+
+    #[test]
+    fn test_view_translates_by_position() {
+        let camera = Camera {
+            position: [30.0, 250.0],
+            ..Default::default()
+        };
+
+        let view = camera.get_view();
+        assert_eq!(
+            view.column(3).into_owned(),
+            glm::vec4(-30.0, -250.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_scroll_clamped() {
+        let mut camera = Camera::default();
+
+        // already at the top
+        assert!(!camera.scroll_clamped(-10.0, 1000.0, 600.0));
+        assert_eq!(camera.position[1], 0.0);
+
+        assert!(camera.scroll_clamped(250.0, 1000.0, 600.0));
+        assert_eq!(camera.position[1], 250.0);
+
+        // stops with the bottom of the content at the bottom of the viewport
+        assert!(camera.scroll_clamped(f32::INFINITY, 1000.0, 600.0));
+        assert_eq!(camera.position[1], 400.0);
+        assert!(!camera.scroll_clamped(1.0, 1000.0, 600.0));
+
+        assert!(camera.scroll_clamped(f32::NEG_INFINITY, 1000.0, 600.0));
+        assert_eq!(camera.position[1], 0.0);
+
+        // content shorter than the viewport can not be scrolled
+        assert!(!camera.scroll_clamped(10.0, 300.0, 600.0));
+        assert_eq!(camera.position[1], 0.0);
+    }
+
+    #[test]
+    fn test_scroll_x_clamped() {
+        let mut camera = Camera::default();
+
+        assert!(camera.scroll_x_clamped(150.0, 1200.0, 800.0));
+        assert_eq!(camera.position, [150.0, 0.0]);
+
+        // stops with the right edge of the content at the right edge of the
+        // viewport
+        assert!(camera.scroll_x_clamped(1000.0, 1200.0, 800.0));
+        assert_eq!(camera.position[0], 400.0);
+        assert!(!camera.scroll_x_clamped(1.0, 1200.0, 800.0));
+
+        assert!(camera.scroll_x_clamped(-1000.0, 1200.0, 800.0));
+        assert_eq!(camera.position[0], 0.0);
+
+        // content that fits the viewport can not be scrolled sideways
+        assert!(!camera.scroll_x_clamped(10.0, 800.0, 800.0));
+        assert_eq!(camera.position[0], 0.0);
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use html::parser::Attributes;
+
+/// Simple selector deciding which elements a rule applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    Tag(String),
+    Class(String),
+    Id(String),
+}
+
+impl Selector {
+    fn parse(selector: &str) -> Option<Self> {
+        let selector = selector.trim();
+        let is_name = |name: &str| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        };
+
+        if let Some(class) = selector.strip_prefix('.') {
+            is_name(class).then(|| Selector::Class(class.to_string()))
+        } else if let Some(id) = selector.strip_prefix('#') {
+            is_name(id).then(|| Selector::Id(id.to_string()))
+        } else {
+            is_name(selector).then(|| Selector::Tag(selector.to_lowercase()))
+        }
+    }
+
+    fn matches(&self, tag: &str, attributes: &Attributes) -> bool {
+        match self {
+            Selector::Tag(name) => name.eq_ignore_ascii_case(tag),
+            Selector::Class(class) => {
+                attributes.get("class").is_some_and(|classes| {
+                    classes.split_ascii_whitespace().any(|c| c == class)
+                })
+            }
+            Selector::Id(id) => attributes.get("id") == Some(id.as_str()),
+        }
+    }
+
+    /// Ids beat classes and classes beat tag names.
+    fn specificity(&self) -> u8 {
+        match self {
+            Selector::Tag(_) => 0,
+            Selector::Class(_) => 1,
+            Selector::Id(_) => 2,
+        }
+    }
+}
+
+/// A selector list with the declarations it applies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub selectors: Vec<Selector>,
+    pub declarations: Vec<(String, String)>,
+}
+
+/// Rules collected from the `<style>` elements of a page.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stylesheet {
+    pub rules: Vec<Rule>,
+}
+
+impl Stylesheet {
+    /// Parses rules like `h1, .warn { color: red }`. Rules with a selector
+    /// that is not a plain tag, class or id are skipped.
+    pub fn parse(source: &str) -> Self {
+        let source = strip_comments(source);
+        let mut rules = Vec::new();
+
+        let mut rest = source.as_str();
+        while let Some((selectors, after)) = rest.split_once('{') {
+            let (block, after) = after.split_once('}').unwrap_or((after, ""));
+            rest = after;
+
+            let selectors = selectors
+                .split(',')
+                .map(Selector::parse)
+                .collect::<Option<Vec<_>>>();
+
+            if let Some(selectors) = selectors {
+                rules.push(Rule {
+                    selectors,
+                    declarations: parse_declarations(block),
+                });
+            }
+        }
+
+        Self { rules }
+    }
+
+    /// Adds the rules of `other` after the existing ones, so they win over
+    /// rules of the same specificity.
+    pub fn extend(&mut self, other: Stylesheet) {
+        self.rules.extend(other.rules);
+    }
+
+    /// Value of `property` for an element. Among the matching rules the most
+    /// specific one wins and ties go to the rule written last.
+    pub fn value(
+        &self,
+        tag: &str,
+        attributes: &Attributes,
+        property: &str,
+    ) -> Option<&str> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter_map(|(order, rule)| {
+                let specificity = rule
+                    .selectors
+                    .iter()
+                    .filter(|selector| selector.matches(tag, attributes))
+                    .map(Selector::specificity)
+                    .max()?;
+
+                // later declarations in a block override earlier ones
+                let value = rule
+                    .declarations
+                    .iter()
+                    .rev()
+                    .find(|(name, _)| name == property)
+                    .map(|(_, value)| value.as_str())?;
+
+                Some(((specificity, order), value))
+            })
+            .max_by_key(|(priority, _)| *priority)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Parses the declarations of a `style` attribute like
+/// `color: red; background: url(a:b)`, keyed by their lowercase name. When a
+/// property is declared twice the later value wins.
+pub fn parse_inline_styles(style: &str) -> HashMap<String, String> {
+    parse_declarations(style).into_iter().collect()
+}
+
+/// Parses the `name: value` pairs of a declaration block.
+fn parse_declarations(block: &str) -> Vec<(String, String)> {
+    block
+        .split(';')
+        .filter_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            let (name, value) = (name.trim(), value.trim());
+
+            (!name.is_empty() && !value.is_empty())
+                .then(|| (name.to_lowercase(), value.to_string()))
+        })
+        .collect()
+}
+
+fn strip_comments(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("/*") {
+        output.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(pairs: &[(&str, &str)]) -> Attributes {
+        let mut attributes = Attributes::new();
+        for (name, value) in pairs {
+            attributes.insert(name.to_string(), value.to_string());
+        }
+        attributes
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let sheet = Stylesheet::parse(
+            "/* headings */ h1, .warn { color: red; font-size: 20px }\n\
+             #main{background-color:#fff;} div > p { color: blue }",
+        );
+
+        assert_eq!(
+            sheet.rules,
+            vec![
+                Rule {
+                    selectors: vec![
+                        Selector::Tag("h1".to_string()),
+                        Selector::Class("warn".to_string()),
+                    ],
+                    declarations: vec![
+                        ("color".to_string(), "red".to_string()),
+                        ("font-size".to_string(), "20px".to_string()),
+                    ],
+                },
+                Rule {
+                    selectors: vec![Selector::Id("main".to_string())],
+                    declarations: vec![(
+                        "background-color".to_string(),
+                        "#fff".to_string()
+                    )],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_inline_styles() {
+        let styles = parse_inline_styles(
+            "color: red;; Background-Image: url(http://a/b.png) ; \
+             color: blue; margin:0;",
+        );
+
+        let mut pairs: Vec<_> = styles
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec![
+                ("background-image", "url(http://a/b.png)"),
+                ("color", "blue"),
+                ("margin", "0"),
+            ]
+        );
+
+        assert!(parse_inline_styles(" ; ").is_empty());
+        assert!(parse_inline_styles("color:").is_empty());
+    }
+
+    #[test]
+    fn test_most_specific_rule_wins() {
+        let sheet = Stylesheet::parse(
+            "#main { color: green } .warn { color: red } p { color: blue } \
+             .warn { color: orange }",
+        );
+
+        let plain = attributes(&[]);
+        let warn = attributes(&[("class", "big warn")]);
+        let main = attributes(&[("class", "warn"), ("id", "main")]);
+
+        assert_eq!(sheet.value("p", &plain, "color"), Some("blue"));
+        assert_eq!(sheet.value("P", &warn, "color"), Some("orange"));
+        assert_eq!(sheet.value("p", &main, "color"), Some("green"));
+        assert_eq!(sheet.value("div", &plain, "color"), None);
+        assert_eq!(sheet.value("p", &plain, "font-size"), None);
+    }
+}
@@ -0,0 +1,230 @@
+use std::error::Error;
+use std::fmt;
+
+/// The contents of a `data:` URL, like
+/// `data:text/html,<p>Hi</p>` or `data:image/png;base64,iVBORw0KGgo=`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataUrl {
+    /// Media type without its parameters, in lower case. `text/plain` when
+    /// the URL leaves it out.
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataUrlError {
+    /// The URL does not start with `data:`.
+    NotDataUrl,
+    /// There is no `,` between the media type and the data.
+    MissingComma,
+    InvalidBase64,
+    /// The data is of a type that can not be shown where it is used.
+    UnsupportedMediaType(String),
+}
+
+impl fmt::Display for DataUrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataUrlError::NotDataUrl => write!(f, "not a data: URL"),
+            DataUrlError::MissingComma => {
+                write!(f, "data: URL has no ',' before its data")
+            }
+            DataUrlError::InvalidBase64 => {
+                write!(f, "data: URL has invalid base64 data")
+            }
+            DataUrlError::UnsupportedMediaType(media_type) => {
+                write!(f, "unsupported media type {}", media_type)
+            }
+        }
+    }
+}
+
+impl Error for DataUrlError {}
+
+impl DataUrl {
+    /// Fails with `UnsupportedMediaType` unless the data is one of
+    /// `media_types`.
+    pub fn expect_media_type(
+        &self,
+        media_types: &[&str],
+    ) -> Result<(), DataUrlError> {
+        if media_types.contains(&self.media_type.as_str()) {
+            Ok(())
+        } else {
+            Err(DataUrlError::UnsupportedMediaType(self.media_type.clone()))
+        }
+    }
+}
+
+pub fn is_data_url(url: &str) -> bool {
+    url.get(..5)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:"))
+}
+
+/// Decodes a `data:` URL. Its data is percent-encoded, or base64 encoded
+/// when the media type ends in `;base64`. A `#fragment` at the end is not
+/// part of the data.
+pub fn parse_data_url(url: &str) -> Result<DataUrl, DataUrlError> {
+    if !is_data_url(url) {
+        return Err(DataUrlError::NotDataUrl);
+    }
+
+    let url = &url[5..];
+    let url = url.split_once('#').map_or(url, |(url, _)| url);
+    let (header, data) =
+        url.split_once(',').ok_or(DataUrlError::MissingComma)?;
+
+    let header = header.trim();
+    let (header, base64) = match header.rsplit_once(';') {
+        Some((rest, flag)) if flag.trim().eq_ignore_ascii_case("base64") => {
+            (rest, true)
+        }
+        _ => (header, false),
+    };
+
+    // parameters like the charset are not needed to tell what the data is
+    let media_type = header.split(';').next().unwrap_or("").trim();
+    let media_type = if media_type.is_empty() {
+        "text/plain".to_string()
+    } else {
+        media_type.to_ascii_lowercase()
+    };
+
+    let data = percent_decode(data);
+    let data = if base64 {
+        decode_base64(&data).ok_or(DataUrlError::InvalidBase64)?
+    } else {
+        data
+    };
+
+    Ok(DataUrl { media_type, data })
+}
+
+/// Replaces every `%XX` escape with the byte it stands for. Escapes that
+/// are not followed by two hex digits are kept as they are.
+fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    decoded
+}
+
+/// Decodes standard base64, ignoring whitespace. Padding is optional, but
+/// nothing may follow it.
+fn decode_base64(encoded: &[u8]) -> Option<Vec<u8>> {
+    let sextet = |byte: u8| match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+
+    let encoded: Vec<u8> = encoded
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    let unpadded = encoded
+        .iter()
+        .position(|&byte| byte == b'=')
+        .map_or(&encoded[..], |end| &encoded[..end]);
+    let padding = &encoded[unpadded.len()..];
+    if padding.len() > 2
+        || padding.iter().any(|&byte| byte != b'=')
+        || unpadded.len() % 4 == 1
+    {
+        return None;
+    }
+
+    let mut decoded = Vec::with_capacity(unpadded.len() * 3 / 4);
+    for chunk in unpadded.chunks(4) {
+        let mut bits = 0u32;
+        for &byte in chunk {
+            bits = bits << 6 | u32::from(sextet(byte)?);
+        }
+        // a chunk of n sextets holds n - 1 whole bytes
+        bits <<= 6 * (4 - chunk.len());
+        let bytes = bits.to_be_bytes();
+        decoded.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_encoded_html() {
+        let url = parse_data_url("data:text/html,%3Cp%3EHello%20world%3C/p%3E")
+            .unwrap();
+        assert_eq!(url.media_type, "text/html");
+        assert_eq!(url.data, b"<p>Hello world</p>");
+
+        let plain = parse_data_url("data:,50%25#top").unwrap();
+        assert_eq!(plain.media_type, "text/plain");
+        assert_eq!(plain.data, b"50%");
+    }
+
+    #[test]
+    fn test_base64_image() {
+        let url = parse_data_url("data:image/PNG;base64,iVBORw0KGgo=").unwrap();
+        assert_eq!(url.media_type, "image/png");
+        assert_eq!(url.data, b"\x89PNG\r\n\x1a\n");
+        assert!(url.expect_media_type(&["image/png"]).is_ok());
+        assert_eq!(
+            url.expect_media_type(&["text/html"]),
+            Err(DataUrlError::UnsupportedMediaType("image/png".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64(b"TWFu").unwrap(), b"Man");
+        assert_eq!(decode_base64(b"TWE=").unwrap(), b"Ma");
+        assert_eq!(decode_base64(b"TQ").unwrap(), b"M");
+        assert_eq!(decode_base64(b"TW\nFu").unwrap(), b"Man");
+        assert_eq!(decode_base64(b"").unwrap(), b"");
+        assert_eq!(decode_base64(b"T"), None);
+        assert_eq!(decode_base64(b"TQ==TQ=="), None);
+        assert_eq!(decode_base64(b"T!=="), None);
+    }
+
+    #[test]
+    fn test_invalid_data_urls() {
+        assert_eq!(
+            parse_data_url("https://example.com"),
+            Err(DataUrlError::NotDataUrl)
+        );
+        assert_eq!(
+            parse_data_url("data:text/html"),
+            Err(DataUrlError::MissingComma)
+        );
+        assert_eq!(
+            parse_data_url("data:text/plain;base64,*"),
+            Err(DataUrlError::InvalidBase64)
+        );
+    }
+}
@@ -0,0 +1,119 @@
+/// Pages visited in a window, oldest first, with the one shown marked by a
+/// cursor. Going back and forth only moves the cursor, visiting a page
+/// drops everything after it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct History {
+    entries: Vec<String>,
+    // index of the page shown, `None` before the first visit
+    current: Option<usize>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a visit to `target` after the page shown. Pages that could
+    /// be gone forward to are forgotten.
+    pub fn push(&mut self, target: &str) {
+        let next = self.current.map_or(0, |current| current + 1);
+        self.entries.truncate(next);
+        self.entries.push(target.to_string());
+        self.current = Some(next);
+    }
+
+    /// Moves to the page before the shown one and returns it.
+    pub fn back(&mut self) -> Option<&str> {
+        let previous = self.previous_index()?;
+        self.current = Some(previous);
+        Some(&self.entries[previous])
+    }
+
+    /// Moves to the page after the shown one and returns it.
+    pub fn forward(&mut self) -> Option<&str> {
+        let next = self.next_index()?;
+        self.current = Some(next);
+        Some(&self.entries[next])
+    }
+
+    /// The page `back` would move to, without moving.
+    pub fn peek_back(&self) -> Option<&str> {
+        self.previous_index()
+            .map(|previous| self.entries[previous].as_str())
+    }
+
+    /// The page `forward` would move to, without moving.
+    pub fn peek_forward(&self) -> Option<&str> {
+        self.next_index().map(|next| self.entries[next].as_str())
+    }
+
+    fn previous_index(&self) -> Option<usize> {
+        self.current?.checked_sub(1)
+    }
+
+    fn next_index(&self) -> Option<usize> {
+        let next = self.current? + 1;
+        (next < self.entries.len()).then_some(next)
+    }
+
+    /// The page shown.
+    pub fn current(&self) -> Option<&str> {
+        self.current.map(|current| self.entries[current].as_str())
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.current.is_some_and(|current| current > 0)
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.current
+            .is_some_and(|current| current + 1 < self.entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut history = History::new();
+        assert_eq!(history.back(), None);
+        assert_eq!(history.current(), None);
+
+        history.push("a.html");
+        history.push("b.html");
+        history.push("c.html");
+        assert_eq!(history.current(), Some("c.html"));
+        assert!(!history.can_go_forward());
+
+        assert_eq!(history.peek_back(), Some("b.html"));
+        assert_eq!(history.current(), Some("c.html"));
+        assert_eq!(history.back(), Some("b.html"));
+        assert_eq!(history.back(), Some("a.html"));
+        assert_eq!(history.peek_back(), None);
+        assert_eq!(history.back(), None);
+        assert_eq!(history.current(), Some("a.html"));
+
+        assert_eq!(history.peek_forward(), Some("b.html"));
+        assert_eq!(history.forward(), Some("b.html"));
+        assert!(history.can_go_back());
+        assert!(history.can_go_forward());
+    }
+
+    #[test]
+    fn test_push_drops_forward_entries() {
+        let mut history = History::new();
+        history.push("a.html");
+        history.push("b.html");
+        history.push("c.html");
+        history.back();
+        history.back();
+
+        history.push("d.html");
+        assert_eq!(history.current(), Some("d.html"));
+        assert_eq!(history.forward(), None);
+        assert_eq!(history.back(), Some("a.html"));
+        assert_eq!(history.forward(), Some("d.html"));
+    }
+}
@@ -1,14 +1,42 @@
-use glium::Display;
-use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+#[cfg(feature = "net")]
+use std::io::Read;
+use std::mem;
+use std::ops::Range;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use glium::Frame;
 
 use glyph_brush::{HorizontalAlign, VerticalAlign};
-use html::parser::{Node, Parser};
+use html::lexer::{LexError, Span};
+use html::parser::{Attributes, Node, ParseError, Parser};
 
-use crate::renderer::{get_line_height_of_text, Renderer, TextDrawConfig};
+use crate::camera::Camera;
+use crate::css::{parse_inline_styles, Stylesheet};
+use crate::data_url::{is_data_url, parse_data_url, DataUrlError};
+use crate::history::History;
+use crate::lalg::Rect;
+use crate::layout;
+use crate::renderer::{
+    border_rects, Canvas, FontStyle, FrameCanvas, Renderer, TextDrawConfig,
+    DEFAULT_CLEAR_COLOR, DEFAULT_LINE_HEIGHT,
+};
+use crate::selection::{
+    caret_at, highlight_rects, match_ranges, selected_text, GlyphBox, Selection,
+};
+use crate::ua_styles::UserAgentStyleSheet;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum HtmlElement {
     Html,
+    Head,
     Header,
     Body,
     Div,
@@ -16,36 +44,493 @@ pub enum HtmlElement {
     H1,
     H2,
     H3,
+    H4,
+    H5,
+    H6,
     Paragraph,
+    Blockquote,
+    Code,
+    Break,
+    HorizontalRule,
+    Bold,
+    Strong,
+    Italic,
+    Emphasis,
+    Underline,
+    Span,
+    Anchor,
+    Pre,
+    UnorderedList,
+    OrderedList,
+    ListItem,
+    Table,
+    TableRow,
+    TableCell,
     Content,
+    /// Text next to sibling elements, which gets a box of its own like an
+    /// anonymous block in CSS.
+    Text,
+    Script,
+    Style,
+    /// Content kept for later use, parsed but never laid out or drawn.
+    Template,
     Unknown,
 }
 
+impl HtmlElement {
+    /// Whether the element shows up on the page. Metadata, scripts and
+    /// styles are skipped together with their children. Line breaks have
+    /// no box of their own, they end a line of their parent's text.
+    fn is_rendered(&self) -> bool {
+        !matches!(
+            self,
+            HtmlElement::Head
+                | HtmlElement::Script
+                | HtmlElement::Style
+                | HtmlElement::Break
+        )
+    }
+
+    /// Whether the element flows along a line next to its inline siblings
+    /// instead of starting a block of its own.
+    pub(crate) fn is_inline(&self) -> bool {
+        matches!(
+            self,
+            HtmlElement::Img
+                | HtmlElement::Code
+                | HtmlElement::Bold
+                | HtmlElement::Strong
+                | HtmlElement::Italic
+                | HtmlElement::Emphasis
+                | HtmlElement::Underline
+                | HtmlElement::Span
+                | HtmlElement::Anchor
+                | HtmlElement::Text
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct HtmlElementLayout {
-    h_align: HorizontalAlign,
-    v_align: VerticalAlign,
+    pub(crate) h_align: HorizontalAlign,
+    // applied by the layout, which moves the content box
+    pub(crate) v_align: VerticalAlign,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RenderNode {
-    position: (u32, u32), // row, column
-    element: HtmlElement,
+    position: (u32, u32), // column, row
+    pub(crate) element: HtmlElement,
     content: Option<String>,
+    id: Option<String>,
+    // names in the `class` attribute, in order
+    classes: Vec<String>,
+    href: Option<String>,
+    title: Option<String>,
+    // image source of an <img>
+    pub(crate) src: Option<String>,
+    list_marker: Option<String>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_color"))]
+    fg_color: [f32; 4],
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_color"))]
+    bg_color: [f32; 4],
+    // already applied to the colors, kept for images
+    opacity: f32,
+    pub(crate) font_style: FontStyle,
+    pub(crate) font_size: f32,
+    // pixels from one line of text to the next
+    pub(crate) line_height: f32,
+    text_decoration: TextDecoration,
+    pub(crate) width: Option<Length>,
+    pub(crate) max_width: Option<Length>,
+    pub(crate) height: Option<Length>,
+    pub(crate) margin: BoxEdges,
+    pub(crate) padding: BoxEdges,
+    pub(crate) borders: Borders,
+    // how far the corners of the background are rounded
+    border_radius: f32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) layout: HtmlElementLayout,
+    pub(crate) children: Vec<RenderNode>,
+    // children of a <template>, which are not part of the page
+    template_content: Vec<RenderNode>,
+    // boxes assigned by the layout pass, in page coordinates
+    pub(crate) rect: Rect,
+    pub(crate) content_rect: Rect,
+}
+
+/// A line drawn with text, set by `text-decoration`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TextDecoration {
+    #[default]
+    None,
+    Underline,
+    LineThrough,
+}
+
+/// A CSS `line-height`. Multiples are inherited as they are and so follow
+/// the font size of every element, pixels stay the same.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineHeight {
+    Multiple(f32),
+    Px(f32),
+}
+
+impl LineHeight {
+    /// Line height in pixels for text of `font_size`.
+    pub fn resolve(self, font_size: f32) -> f32 {
+        match self {
+            LineHeight::Multiple(multiple) => multiple * font_size,
+            LineHeight::Px(px) => px,
+        }
+    }
+}
+
+impl Default for LineHeight {
+    fn default() -> Self {
+        LineHeight::Multiple(DEFAULT_LINE_HEIGHT)
+    }
+}
+
+/// A CSS length that may depend on the size of the containing box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Length {
+    Px(f32),
+    Percent(f32),
+    /// Percent of the viewport's width.
+    Vw(f32),
+    /// Percent of the viewport's height.
+    Vh(f32),
+    /// Left for the layout to decide.
+    Auto,
+}
+
+/// The sizes a `Length` can be relative to where it is used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthContext {
+    /// Width and height of the area the page is laid out in.
+    pub viewport: (f32, f32),
+    /// Size of the containing box along the axis of the length, `None`
+    /// when it is not known yet.
+    pub containing: Option<f32>,
+}
+
+impl Length {
+    /// Length in pixels in the given context. Percentages of a box whose
+    /// size is not known and `auto` can not be resolved.
+    pub fn resolve(self, ctx: LengthContext) -> Option<f32> {
+        let percent_of = |size: f32, percent: f32| size * percent / 100.0;
+        match self {
+            Length::Px(px) => Some(px),
+            Length::Percent(percent) => {
+                ctx.containing.map(|size| percent_of(size, percent))
+            }
+            Length::Vw(percent) => Some(percent_of(ctx.viewport.0, percent)),
+            Length::Vh(percent) => Some(percent_of(ctx.viewport.1, percent)),
+            Length::Auto => None,
+        }
+    }
+}
+
+/// Sizes of the four sides of a box, used for margins and padding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BoxEdges {
+    pub top: Length,
+    pub right: Length,
+    pub bottom: Length,
+    pub left: Length,
+}
+
+impl Default for BoxEdges {
+    fn default() -> Self {
+        Self {
+            top: Length::Px(0.0),
+            right: Length::Px(0.0),
+            bottom: Length::Px(0.0),
+            left: Length::Px(0.0),
+        }
+    }
+}
+
+/// Color of the box shown for images that can not be drawn.
+const IMAGE_PLACEHOLDER_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+
+/// One side of the border around the padding of an element, drawn as a
+/// solid line. Sides without a border have no width.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Border {
+    pub width: f32,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_color"))]
+    pub color: [f32; 4],
+}
+
+/// Borders of the four sides of a box.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Borders {
+    pub top: Border,
+    pub right: Border,
+    pub bottom: Border,
+    pub left: Border,
+}
+
+impl Borders {
+    /// The same borders with their colors faded by `opacity`.
+    fn with_opacity(mut self, opacity: f32) -> Self {
+        for side in [
+            &mut self.top,
+            &mut self.right,
+            &mut self.bottom,
+            &mut self.left,
+        ] {
+            side.color = with_opacity(side.color, opacity);
+        }
+        self
+    }
+
+    /// Widths of the top, right, bottom and left sides.
+    pub fn widths(&self) -> [f32; 4] {
+        [self.top, self.right, self.bottom, self.left].map(|side| side.width)
+    }
+
+    /// The border shared by all four sides, if they look the same.
+    pub fn uniform(&self) -> Option<Border> {
+        let sides = [self.right, self.bottom, self.left];
+        sides
+            .iter()
+            .all(|side| *side == self.top)
+            .then_some(self.top)
+    }
+}
+
+/// Default text color of links, the usual browser blue.
+const LINK_COLOR: [f32; 4] = [0.0, 0.0, 238.0 / 255.0, 1.0];
+
+/// Default color of the line drawn for `<hr>`.
+const RULE_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+/// How far down the glyph boxes of a line the line of a `text-decoration`
+/// is drawn. Underlines sit just below the baseline, lines through the text
+/// cross lower case letters in their middle.
+const UNDERLINE_POSITION: f32 = 0.85;
+const LINE_THROUGH_POSITION: f32 = 0.55;
+
+/// Color drawn behind selected text.
+const SELECTION_COLOR: [f32; 4] = [0.2, 0.45, 1.0, 0.35];
+
+/// Colors drawn behind the text found by `find`, the match navigated to
+/// stands out from the others.
+const FIND_MATCH_COLOR: [f32; 4] = [1.0, 0.9, 0.0, 0.5];
+const CURRENT_FIND_MATCH_COLOR: [f32; 4] = [1.0, 0.6, 0.0, 0.6];
+
+/// Screen area covered by a link, recorded while rendering.
+#[derive(Debug)]
+struct AnchorRegion {
+    rect: Rect,
+    href: String,
+    // nesting depth, used to prefer the innermost link
+    depth: u32,
+}
+
+/// Screen area of an element with a `title`, recorded while rendering.
+#[derive(Debug)]
+struct TitleRegion {
+    rect: Rect,
+    title: String,
+    // nesting depth, used to prefer the innermost title
+    depth: u32,
+}
+
+/// Title shown in a box next to the mouse once it rested on an element.
+#[derive(Debug)]
+struct Tooltip {
+    title: String,
+    // where the mouse was on the canvas when it came to rest and when that
+    // was
+    position: (f32, f32),
+    since: Instant,
+    shown: bool,
+}
+
+/// How far in pixels the mouse may move between pressing and releasing the
+/// button for it to count as a click rather than a drag.
+const CLICK_DISTANCE: f32 = 4.0;
+
+/// How long the mouse has to rest on an element before its title shows.
+pub const TOOLTIP_DELAY: Duration = Duration::from_millis(500);
+const TOOLTIP_FONT_SIZE: f32 = 14.0;
+const TOOLTIP_PADDING: f32 = 4.0;
+/// Offset of the tooltip from the mouse, clear of the cursor image.
+const TOOLTIP_OFFSET: (f32, f32) = (12.0, 18.0);
+const TOOLTIP_BG_COLOR: [f32; 4] = [1.0, 1.0, 0.88, 1.0];
+const TOOLTIP_BORDER_COLOR: [f32; 4] = [0.4, 0.4, 0.4, 1.0];
+/// Distance of the search status from the bottom right corner of the screen.
+const FIND_STATUS_MARGIN: f32 = 8.0;
+
+/// Space around the page, like the default margin of `<body>` in browsers.
+pub const DEFAULT_PAGE_MARGIN: f32 = 8.0;
+
+/// Font size of text outside of any element that sets one.
+const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+/// Styles a node passes down to its children.
+#[derive(Debug, Clone, Copy)]
+struct InheritedStyle {
     fg_color: [f32; 4],
+    // only used for an explicit `background-color: inherit`
     bg_color: [f32; 4],
-    layout: HtmlElementLayout,
-    children: Vec<RenderNode>,
+    font_style: FontStyle,
+    font_size: f32,
+    line_height: LineHeight,
+    // drawn under the text of every descendant as well
+    text_decoration: TextDecoration,
+    preserve_whitespace: bool,
+    opacity: f32,
+}
+
+impl Default for InheritedStyle {
+    fn default() -> Self {
+        Self {
+            fg_color: [0.0, 0.0, 0.0, 1.0],
+            bg_color: [0.0, 0.0, 0.0, 0.0],
+            font_style: FontStyle::Regular,
+            font_size: DEFAULT_FONT_SIZE,
+            line_height: LineHeight::default(),
+            text_decoration: TextDecoration::None,
+            preserve_whitespace: false,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl RenderNode {
+    /// The `(column, row)` of this node, where the column is its nesting
+    /// depth below the top-level element and the row its document order.
+    pub fn position(&self) -> (u32, u32) {
+        self.position
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn classes(&self) -> &[String] {
+        &self.classes
+    }
+
+    /// Whether `name` is one of the names in the `class` attribute.
+    pub fn has_class(&self, name: &str) -> bool {
+        self.classes.iter().any(|class| class == name)
+    }
+
+    /// All text of this node and its descendants in document order, like
+    /// `textContent` in the DOM. Inline text is joined as it is written and
+    /// the text of blocks is separated by a space. Scripts, styles and the
+    /// content of templates are not part of it.
+    pub fn text_content(&self) -> String {
+        let mut text = String::new();
+        push_text_content(self, &mut text, &mut false);
+        text
+    }
+
+    /// What a `<template>` holds. The nodes are not laid out or drawn, and
+    /// every other element has none.
+    pub fn template_content(&self) -> &[RenderNode] {
+        &self.template_content
+    }
+}
+
+/// Something in the document the renderer could not fully handle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// An element the renderer does not know. It is rendered as a plain
+    /// block so its children still show up.
+    UnknownTag { tag: String, span: Span },
+    /// End tags did not match the elements they closed, so the tree was
+    /// built by implying the missing ones.
+    MismatchedTags,
+    /// The input could not be split into tokens, nothing is rendered.
+    LexFailed(LexError),
+    /// The tokens could not be built into a tree, nothing is rendered.
+    ParseFailed(ParseError),
 }
 
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::UnknownTag { tag, span } => write!(
+                f,
+                "unknown tag <{}> at line {}, column {}",
+                tag, span.line, span.col
+            ),
+            Diagnostic::MismatchedTags => {
+                write!(f, "mismatched end tags, implied the missing ones")
+            }
+            Diagnostic::LexFailed(err)
+            | Diagnostic::ParseFailed(ParseError::Lex(err)) => {
+                write!(f, "failed to read html: {}", err)
+            }
+            Diagnostic::ParseFailed(err) => {
+                write!(f, "failed to parse html: {}", err)
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct HtmlRenderGraph {
     pub nodes: Vec<RenderNode>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    diagnostics: Vec<Diagnostic>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    content_width: f32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    content_height: f32,
+    // path to the element with each id, the index in `nodes` followed by
+    // the index among the children at every level below
+    #[cfg_attr(feature = "serde", serde(skip))]
+    ids: HashMap<String, Vec<usize>>,
 }
 
 impl HtmlRenderGraph {
     pub fn new(input: &str) -> Self {
-        let parser = Parser::new(input);
-        let nodes = parser.parse().unwrap_or(Vec::new());
+        Self::with_ua_styles(input, &UserAgentStyleSheet::default())
+    }
+
+    /// Builds the render tree with `ua_styles` as the tag defaults.
+    pub fn with_ua_styles(
+        input: &str,
+        ua_styles: &UserAgentStyleSheet,
+    ) -> Self {
+        let mut diagnostics = Vec::new();
+        let nodes = match Parser::new(input) {
+            Ok(parser) => {
+                if !parser.is_well_formed() {
+                    diagnostics.push(Diagnostic::MismatchedTags);
+                }
+
+                match parser.parse_document() {
+                    Ok(Node::Document { children }) => children,
+                    Ok(_) => Vec::new(),
+                    Err(err) => {
+                        diagnostics.push(Diagnostic::ParseFailed(err));
+                        Vec::new()
+                    }
+                }
+            }
+            Err(err) => {
+                diagnostics.push(Diagnostic::LexFailed(err));
+                Vec::new()
+            }
+        };
+
+        let mut stylesheet = Stylesheet::default();
+        collect_stylesheets(&nodes, &mut stylesheet);
 
         // parse attributes and build render tree
         let mut render_nodes = Vec::with_capacity(nodes.len());
@@ -54,30 +539,115 @@ impl HtmlRenderGraph {
             if let Some(render_node) = HtmlRenderGraph::parse_node(
                 &node,
                 (0, last_line),
+                InheritedStyle::default(),
+                &stylesheet,
+                ua_styles,
                 &mut last_line,
+                &mut diagnostics,
             ) {
                 render_nodes.push(render_node);
             }
         }
+        trim_inline_spaces(&mut render_nodes);
+
+        let mut ids = HashMap::new();
+        index_ids(&render_nodes, &mut Vec::new(), &mut ids);
 
         Self {
             nodes: render_nodes,
+            diagnostics,
+            content_width: 0.0,
+            content_height: 0.0,
+            ids,
+        }
+    }
+
+    /// The element with the given `id` attribute. When several elements
+    /// share an id the first one in the document wins.
+    pub fn get_element_by_id(&self, id: &str) -> Option<&RenderNode> {
+        let (first, rest) = self.ids.get(id)?.split_first()?;
+        rest.iter()
+            .try_fold(self.nodes.get(*first)?, |node, &index| {
+                node.children.get(index)
+            })
+    }
+
+    /// Lays the nodes out for `viewport`, see `layout::layout`.
+    pub fn layout(&mut self, canvas: &mut impl Canvas, viewport: Rect) {
+        (self.content_width, self.content_height) =
+            layout::layout(&mut self.nodes, canvas, viewport);
+    }
+
+    /// Width of the page as of the last `layout`. It is only wider than
+    /// the viewport when an element sets a larger width.
+    pub fn content_width(&self) -> f32 {
+        self.content_width
+    }
+
+    /// Height of the page as of the last `layout`, margins included. It
+    /// changes with the viewport width as text wraps differently.
+    pub fn content_height(&self) -> f32 {
+        self.content_height
+    }
+
+    /// Background of the whole page, taken from `<html>` or else `<body>`
+    /// like browsers do. `None` when neither sets a background.
+    pub fn background_color(&self) -> Option<[f32; 4]> {
+        let (mut html, mut body) = (None, None);
+        for node in &self.nodes {
+            visit(node, 0, &mut |node, _| match node.element {
+                HtmlElement::Html if html.is_none() => {
+                    html = Some(node.bg_color)
+                }
+                HtmlElement::Body if body.is_none() => {
+                    body = Some(node.bg_color)
+                }
+                _ => {}
+            });
+        }
+
+        [html, body]
+            .into_iter()
+            .flatten()
+            .find(|color| color[3] > 0.0)
+    }
+
+    /// Problems found while building the render tree, in document order.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Dumps the render tree with one line per node, children indented
+    /// below their parent. Meant for inspecting and snapshotting pages.
+    pub fn to_debug_string(&self) -> String {
+        let mut output = String::new();
+        for node in &self.nodes {
+            visit(node, 0, &mut |node, depth| {
+                write_debug_line(node, depth, &mut output)
+            });
         }
+        output
     }
 
     fn parse_node(
         node: &Node,
-        _parent_position: (u32, u32),
+        parent_position: (u32, u32),
+        inherited: InheritedStyle,
+        stylesheet: &Stylesheet,
+        ua_styles: &UserAgentStyleSheet,
         last_line: &mut u32,
+        diagnostics: &mut Vec<Diagnostic>,
     ) -> Option<RenderNode> {
         match &node {
             Node::Element {
                 tag,
                 attributes,
                 children,
+                span,
             } => {
                 let element = match tag.to_lowercase().as_str() {
                     "html" => HtmlElement::Html,
+                    "head" => HtmlElement::Head,
                     "header" => HtmlElement::Header,
                     "body" => HtmlElement::Body,
                     "div" => HtmlElement::Div,
@@ -85,64 +655,292 @@ impl HtmlRenderGraph {
                     "h1" => HtmlElement::H1,
                     "h2" => HtmlElement::H2,
                     "h3" => HtmlElement::H3,
+                    "h4" => HtmlElement::H4,
+                    "h5" => HtmlElement::H5,
+                    "h6" => HtmlElement::H6,
                     "p" => HtmlElement::Paragraph,
+                    "blockquote" => HtmlElement::Blockquote,
+                    "code" => HtmlElement::Code,
+                    "br" => HtmlElement::Break,
+                    "hr" => HtmlElement::HorizontalRule,
+                    "b" => HtmlElement::Bold,
+                    "strong" => HtmlElement::Strong,
+                    "i" => HtmlElement::Italic,
+                    "em" => HtmlElement::Emphasis,
+                    "u" => HtmlElement::Underline,
+                    "span" => HtmlElement::Span,
+                    "a" => HtmlElement::Anchor,
+                    "pre" => HtmlElement::Pre,
+                    "ul" => HtmlElement::UnorderedList,
+                    "ol" => HtmlElement::OrderedList,
+                    "li" => HtmlElement::ListItem,
+                    "table" => HtmlElement::Table,
+                    "tr" => HtmlElement::TableRow,
+                    "td" => HtmlElement::TableCell,
                     "content" => HtmlElement::Content,
+                    ANONYMOUS_TEXT_TAG => HtmlElement::Text,
+                    "script" => HtmlElement::Script,
+                    "style" => HtmlElement::Style,
+                    "template" => HtmlElement::Template,
                     _ => HtmlElement::Unknown,
                 };
 
-                if element == HtmlElement::Unknown {
+                if !element.is_rendered() {
                     return None;
                 }
 
-                let content = if let [Node::Text(text)] = &children[..] {
-                    Some(text.clone())
-                } else {
-                    None
+                // style = "color: #ffaa00", parsed once for every property
+                let inline_styles = attributes
+                    .get("style")
+                    .map(parse_inline_styles)
+                    .unwrap_or_default();
+                // inline styles win over the rules of the page's stylesheet
+                let style_value = |property: &str| {
+                    inline_styles.get(property).cloned().or_else(|| {
+                        stylesheet
+                            .value(tag, attributes, property)
+                            .map(str::to_string)
+                    })
                 };
 
-                // style = "color: #ffaa00
-                let style = attributes.get("style");
+                // whitespace from the source formatting is collapsed like
+                // browsers do, except inside <pre> or where `white-space`
+                // asks to keep it
+                let preserve_whitespace = style_value("white-space")
+                    .and_then(|value| parse_white_space(&value))
+                    .unwrap_or(
+                        element == HtmlElement::Pre
+                            || inherited.preserve_whitespace,
+                    );
 
-                // TODO: bg color should persist between childs
-                let fg_color = if let Some(style) = style {
-                    parse_style(style, "color").as_deref().and_then(hex_to_rgba)
-                } else {
-                    None
-                };
-                let bg_color = if let Some(style) = style {
-                    parse_style(style, "background-color")
-                        .as_deref()
-                        .and_then(hex_to_rgba)
-                } else {
-                    None
-                };
+                let content = text_lines(children).and_then(|lines| {
+                    if preserve_whitespace {
+                        let text = lines.join("\n");
+                        // like in browsers a newline right after <pre> is
+                        // not part of the text
+                        let text = match element {
+                            HtmlElement::Pre => text
+                                .strip_prefix("\r\n")
+                                .or_else(|| text.strip_prefix('\n'))
+                                .unwrap_or(&text),
+                            _ => &text,
+                        };
+                        Some(expand_tabs(text))
+                    } else {
+                        let text = lines
+                            .iter()
+                            .map(|line| collapse_whitespace(line))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        if text.is_empty() {
+                            return None;
+                        }
+
+                        // inline text keeps a space at its edges, which
+                        // separates it from its neighbors on the line
+                        let raw = lines.concat();
+                        let space = |edge: Option<char>| match edge {
+                            Some(ch)
+                                if ch.is_ascii_whitespace()
+                                    && element.is_inline() =>
+                            {
+                                " "
+                            }
+                            _ => "",
+                        };
+                        Some(format!(
+                            "{}{}{}",
+                            space(raw.chars().next()),
+                            text,
+                            space(raw.chars().next_back())
+                        ))
+                    }
+                });
+                // the text of a template belongs to its content, which is
+                // never shown
+                let content =
+                    content.filter(|_| element != HtmlElement::Template);
+
+                let tag_style = ua_styles.get(element);
 
-                // position for childs
-                let position = (0, *last_line);
+                // text color is inherited unless the element sets its own,
+                // links and rules default to their own color like in
+                // browsers
+                let fg_color = style_value("color")
+                    .and_then(|value| resolve_color(&value, inherited.fg_color))
+                    .unwrap_or(match element {
+                        HtmlElement::Anchor => LINK_COLOR,
+                        HtmlElement::HorizontalRule => RULE_COLOR,
+                        _ => inherited.fg_color,
+                    });
+                // backgrounds are not inherited, the parent's box already
+                // shows through a transparent child unless asked to inherit
+                let bg_color = style_value("background-color")
+                    .and_then(|value| resolve_color(&value, inherited.bg_color))
+                    .or(tag_style.bg_color)
+                    .unwrap_or([0.0, 0.0, 0.0, 0.0]);
+
+                // columns are relative to the parent, rows keep counting
+                // through the whole document
+                let position = (parent_position.0, *last_line);
                 *last_line += 1;
 
+                if element == HtmlElement::Unknown {
+                    diagnostics.push(Diagnostic::UnknownTag {
+                        tag: tag.clone(),
+                        span: *span,
+                    });
+                }
+
+                // hidden elements are dropped with their children, so they
+                // take up no space on the page
+                let hidden = attributes.get("hidden").is_some()
+                    || style_value("display")
+                        .is_some_and(|value| is_display_none(&value));
+                if hidden {
+                    return None;
+                }
+
+                // children are nested one column deeper than this element
+                let child_position = (position.0 + 1, position.1);
+
+                let font_style =
+                    tag_style.font_style.unwrap_or(inherited.font_style);
+                // relative sizes and tag defaults scale the parent's size
+                let font_size = style_value("font-size")
+                    .and_then(|value| {
+                        parse_font_size(&value, inherited.font_size)
+                    })
+                    .or_else(|| {
+                        tag_style
+                            .font_scale
+                            .map(|scale| scale * inherited.font_size)
+                    })
+                    .unwrap_or(inherited.font_size);
+                let line_height = style_value("line-height")
+                    .and_then(|value| parse_line_height(&value))
+                    .unwrap_or(inherited.line_height);
+                let text_decoration = style_value("text-decoration")
+                    .and_then(|value| parse_text_decoration(&value))
+                    .or(tag_style.text_decoration)
+                    .unwrap_or(inherited.text_decoration);
+                // a child can not be more opaque than its parent, the
+                // colors passed down stay unfaded so it is applied once
+                let opacity = inherited.opacity
+                    * style_value("opacity")
+                        .and_then(|value| parse_opacity(&value))
+                        .unwrap_or(1.0);
+
+                let child_style = InheritedStyle {
+                    fg_color,
+                    bg_color,
+                    font_style,
+                    font_size,
+                    line_height,
+                    text_decoration,
+                    preserve_whitespace,
+                    opacity,
+                };
+
+                // the content of a template counts its rows on its own, so
+                // the elements after it are numbered as if it were empty
+                let mut template_line = *last_line;
+                let children_line = match element {
+                    HtmlElement::Template => &mut template_line,
+                    _ => &mut *last_line,
+                };
+
                 let mut render_children: Vec<RenderNode> = Vec::new();
-                for child in children {
-                    if let Some(render_node) =
-                        HtmlRenderGraph::parse_node(child, position, last_line)
-                    {
+                for_each_child_box(children, *span, |child| {
+                    if let Some(render_node) = HtmlRenderGraph::parse_node(
+                        child,
+                        child_position,
+                        child_style,
+                        stylesheet,
+                        ua_styles,
+                        children_line,
+                        diagnostics,
+                    ) {
                         render_children.push(render_node);
                     }
+                });
+                trim_inline_spaces(&mut render_children);
+
+                // number the direct items of this list, nested lists count
+                // their own items
+                let items = render_children
+                    .iter_mut()
+                    .filter(|child| child.element == HtmlElement::ListItem);
+                for (index, item) in items.enumerate() {
+                    item.list_marker = match element {
+                        HtmlElement::UnorderedList => Some("• ".to_string()),
+                        HtmlElement::OrderedList => {
+                            Some(format!("{}. ", index + 1))
+                        }
+                        _ => None,
+                    };
                 }
 
+                let (render_children, template_content) = match element {
+                    HtmlElement::Template => (Vec::new(), render_children),
+                    _ => (render_children, Vec::new()),
+                };
+
                 let render_node = RenderNode {
-                    // TODO: make child positions relative to their parents using
-                    // parent_position
                     position,
                     element,
                     content,
-                    fg_color: fg_color.unwrap_or([0.0, 0.0, 0.0, 1.0]),
-                    bg_color: bg_color.unwrap_or([0.0, 0.0, 0.0, 0.0]),
+                    id: attributes.get("id").map(str::to_string),
+                    classes: attributes
+                        .get("class")
+                        .map(parse_classes)
+                        .unwrap_or_default(),
+                    href: attributes.get("href").map(str::to_string),
+                    title: attributes.get("title").map(str::to_string),
+                    src: attributes
+                        .get("src")
+                        .filter(|_| element == HtmlElement::Img)
+                        .map(str::to_string),
+                    list_marker: None,
+                    fg_color: with_opacity(fg_color, opacity),
+                    bg_color: with_opacity(bg_color, opacity),
+                    opacity,
+                    font_style,
+                    font_size,
+                    line_height: line_height.resolve(font_size),
+                    text_decoration,
+                    width: style_value("width")
+                        .and_then(|value| parse_length(&value))
+                        .or_else(|| dimension_attribute(attributes, "width")),
+                    max_width: style_value("max-width")
+                        .and_then(|value| parse_length(&value)),
+                    height: style_value("height")
+                        .and_then(|value| parse_length(&value))
+                        .or_else(|| dimension_attribute(attributes, "height")),
+                    margin: style_value("margin")
+                        .and_then(|value| parse_box_edges(&value))
+                        .or(tag_style.margin)
+                        .unwrap_or_default(),
+                    padding: style_value("padding")
+                        .and_then(|value| parse_box_edges(&value))
+                        .unwrap_or_default(),
+                    borders: parse_borders(style_value, fg_color)
+                        .with_opacity(opacity),
+                    border_radius: style_value("border-radius")
+                        .and_then(|value| parse_border_radius(&value))
+                        .unwrap_or(0.0),
+                    rect: Rect::default(),
+                    content_rect: Rect::default(),
                     layout: HtmlElementLayout {
-                        h_align: HorizontalAlign::Left,
-                        v_align: VerticalAlign::Top,
+                        h_align: style_value("text-align")
+                            .and_then(|value| parse_text_align(&value))
+                            .unwrap_or(HorizontalAlign::Left),
+                        v_align: style_value("vertical-align")
+                            .and_then(|value| parse_vertical_align(&value))
+                            .unwrap_or(VerticalAlign::Top),
                     },
                     children: render_children,
+                    template_content,
                 };
 
                 Some(render_node)
@@ -152,112 +950,4150 @@ impl HtmlRenderGraph {
     }
 }
 
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    DataUrl(DataUrlError),
+    #[cfg(feature = "net")]
+    Http(Box<ureq::Error>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "failed to read page: {}", err),
+            LoadError::DataUrl(err) => {
+                write!(f, "failed to decode page: {}", err)
+            }
+            #[cfg(feature = "net")]
+            LoadError::Http(err) => write!(f, "failed to fetch page: {}", err),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(err) => Some(err),
+            LoadError::DataUrl(err) => Some(err),
+            #[cfg(feature = "net")]
+            LoadError::Http(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+impl From<DataUrlError> for LoadError {
+    fn from(err: DataUrlError) -> Self {
+        LoadError::DataUrl(err)
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+/// Called with the href of a clicked link.
+pub type LinkClickHandler = Box<dyn FnMut(&str)>;
+
+/// Identifies a tab of an `HtmlRenderer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TabId(u32);
+
+/// A page kept aside while another tab is shown.
+#[derive(Default)]
+struct Tab {
+    render_graph: Option<HtmlRenderGraph>,
+    source: Option<String>,
+    history: History,
+    base: Option<String>,
+    scroll: [f32; 2],
+    pending_fragment: Option<String>,
+}
+
 pub struct HtmlRenderer {
     render_graph: Option<HtmlRenderGraph>,
+    // source of the loaded page, kept to rebuild it with new tag defaults
+    source: Option<String>,
+    ua_styles: UserAgentStyleSheet,
+    anchor_regions: Vec<AnchorRegion>,
+    // text of the page in document order as it was last painted, what
+    // `selection` indexes into
+    glyphs: Vec<GlyphBox>,
+    // the glyphs of every element with text, as ranges into `glyphs`
+    glyph_runs: Vec<Range<usize>>,
+    // how far the glyphs were moved from their place on the page when
+    // they were painted
+    painted_scroll: (f32, f32),
+    selection: Option<Selection>,
+    // text searched for with `find`, its matches are ranges into `glyphs`
+    find_query: String,
+    find_matches: Vec<Range<usize>>,
+    current_find_match: Option<usize>,
+    // href of the link under the mouse
+    hovered_link: Option<String>,
+    title_regions: Vec<TitleRegion>,
+    tooltip: Option<Tooltip>,
+    // where the left button went down, while it is held
+    pressed_at: Option<(f32, f32)>,
+    on_link_click: Option<LinkClickHandler>,
+    // pages loaded from files or urls, html loaded directly is not in it
+    history: History,
+    // path or url the page was loaded from, relative links are resolved
+    // against it
+    base: Option<String>,
+    // scrolled down by its y position, sized like the last painted canvas
+    camera: Camera,
+    // id of the element to scroll to once the loaded page is laid out
+    pending_fragment: Option<String>,
+    // set when the page changed since it was last painted
+    needs_redraw: bool,
+    // space left empty around the page on every side
+    page_margin: f32,
+    // every tab in the order they were opened. The page of the active one
+    // is in the fields above, the others wait in `background_tabs`
+    tabs: Vec<TabId>,
+    active_tab: TabId,
+    background_tabs: HashMap<TabId, Tab>,
+    next_tab_id: u32,
+}
+
+impl Default for HtmlRenderer {
+    fn default() -> Self {
+        Self {
+            render_graph: None,
+            source: None,
+            ua_styles: UserAgentStyleSheet::default(),
+            anchor_regions: Vec::new(),
+            glyphs: Vec::new(),
+            glyph_runs: Vec::new(),
+            painted_scroll: (0.0, 0.0),
+            selection: None,
+            find_query: String::new(),
+            find_matches: Vec::new(),
+            current_find_match: None,
+            hovered_link: None,
+            title_regions: Vec::new(),
+            tooltip: None,
+            pressed_at: None,
+            on_link_click: None,
+            history: History::new(),
+            base: None,
+            camera: Camera::default(),
+            pending_fragment: None,
+            needs_redraw: false,
+            page_margin: DEFAULT_PAGE_MARGIN,
+            tabs: vec![TabId(0)],
+            active_tab: TabId(0),
+            background_tabs: HashMap::new(),
+            next_tab_id: 1,
+        }
+    }
 }
 
 impl HtmlRenderer {
     pub fn new() -> Self {
-        Self { render_graph: None }
+        Self::default()
+    }
+
+    /// Sets the space left empty around the page, `DEFAULT_PAGE_MARGIN` by
+    /// default. Top-level elements start that far from the top left corner.
+    pub fn set_page_margin(&mut self, margin: f32) {
+        let margin = margin.max(0.0);
+        if margin != self.page_margin && self.render_graph.is_some() {
+            self.needs_redraw = true;
+        }
+        self.page_margin = margin;
+    }
+
+    pub fn page_margin(&self) -> f32 {
+        self.page_margin
     }
 
     pub fn load_html(&mut self, html: &str) {
-        self.render_graph = Some(HtmlRenderGraph::new(html));
+        self.render_graph =
+            Some(HtmlRenderGraph::with_ua_styles(html, &self.ua_styles));
+        self.source = Some(html.to_string());
+        self.base = None;
+        self.camera.position = [0.0, 0.0];
+        self.pending_fragment = None;
+        self.selection = None;
+        self.clear_find();
+        self.tooltip = None;
+        self.needs_redraw = true;
     }
 
-    pub fn render(&self, renderer: &mut Renderer, display: &mut Display) {
-        if let Some(render_graph) = &self.render_graph {
-            let mut line_height: f32 = 0.0;
-            for node in &render_graph.nodes {
-                self.render_node(node, renderer, display, &mut line_height);
+    /// Parses the loaded page again, keeping the scroll position. Does
+    /// nothing when no page is loaded.
+    pub fn reload(&mut self) {
+        if let Some(source) = self.source.clone() {
+            self.reload_html(&source);
+        }
+    }
+
+    /// Replaces the loaded page with `html` like `load_html`, but keeps the
+    /// scroll position, which suits showing a page while it is edited. A
+    /// page that got shorter is scrolled to its new bottom once painted.
+    pub fn reload_html(&mut self, html: &str) {
+        self.render_graph =
+            Some(HtmlRenderGraph::with_ua_styles(html, &self.ua_styles));
+        self.source = Some(html.to_string());
+        // the text may have moved, so the old selection means nothing.
+        // Matches of a search are found again once the page is painted
+        self.selection = None;
+        self.tooltip = None;
+        self.needs_redraw = true;
+    }
+
+    /// Replaces the default styles of tags. The pages of every tab are
+    /// built again with the new defaults.
+    pub fn set_ua_styles(&mut self, ua_styles: UserAgentStyleSheet) {
+        self.ua_styles = ua_styles;
+        self.reload();
+
+        for tab in self.background_tabs.values_mut() {
+            if let Some(source) = &tab.source {
+                tab.render_graph = Some(HtmlRenderGraph::with_ua_styles(
+                    source,
+                    &self.ua_styles,
+                ));
             }
         }
     }
 
-    fn render_node(
-        &self,
-        node: &RenderNode,
-        renderer: &mut Renderer,
-        display: &mut Display,
-        line_height: &mut f32,
-    ) {
-        // TODO: only draw background if there is background color
-        // draw the element if is there a content
-        if let Some(content) = &node.content {
-            let font_size = match node.element {
-                HtmlElement::H1 => 32.0,
-                HtmlElement::H2 => 28.0,
-                HtmlElement::H3 => 24.0,
-                HtmlElement::Paragraph | HtmlElement::Content => 16.0,
-                _ => 14.0,
-            };
+    pub fn ua_styles(&self) -> &UserAgentStyleSheet {
+        &self.ua_styles
+    }
 
-            // Draw the text with provided styles and layout
-            renderer.draw_text(
-                display,
-                content,
-                font_size,
-                TextDrawConfig {
-                    screen_pos: (0.0, *line_height),
-                    fg_color: node.fg_color,
-                    bg_color: node.bg_color,
-                    h_align: node.layout.h_align,
-                    v_align: node.layout.v_align,
-                    ..Default::default()
-                },
-            );
+    /// Opens `html` in a new tab behind the active one, which stays shown
+    /// until `switch_tab` is called. The new tab is scrolled to the top.
+    pub fn new_tab(&mut self, html: &str) -> TabId {
+        let id = TabId(self.next_tab_id);
+        self.next_tab_id += 1;
+
+        let tab = Tab {
+            render_graph: Some(HtmlRenderGraph::with_ua_styles(
+                html,
+                &self.ua_styles,
+            )),
+            source: Some(html.to_string()),
+            ..Tab::default()
+        };
+        self.tabs.push(id);
+        self.background_tabs.insert(id, tab);
+        id
+    }
+
+    /// Shows the page of tab `id` where it was scrolled to when it was
+    /// left. Returns `false` when there is no such tab.
+    pub fn switch_tab(&mut self, id: TabId) -> bool {
+        let Some(tab) = self.background_tabs.remove(&id) else {
+            return id == self.active_tab;
+        };
+
+        let active = Tab {
+            render_graph: self.render_graph.take(),
+            source: self.source.take(),
+            history: mem::take(&mut self.history),
+            base: self.base.take(),
+            scroll: self.camera.position,
+            pending_fragment: self.pending_fragment.take(),
+        };
+        self.background_tabs.insert(self.active_tab, active);
+
+        self.render_graph = tab.render_graph;
+        self.source = tab.source;
+        self.history = tab.history;
+        self.base = tab.base;
+        self.camera.position = tab.scroll;
+        self.pending_fragment = tab.pending_fragment;
+        self.active_tab = id;
 
-            *line_height += get_line_height_of_text(content, font_size);
+        // what was painted and pointed at belongs to the other page
+        self.anchor_regions.clear();
+        self.title_regions.clear();
+        self.glyphs.clear();
+        self.glyph_runs.clear();
+        self.selection = None;
+        self.clear_find();
+        self.hovered_link = None;
+        self.tooltip = None;
+        self.pressed_at = None;
+        self.needs_redraw = true;
+        true
+    }
+
+    /// Closes tab `id`. Closing the active tab shows the one after it, or
+    /// the one before when it was the last. Returns `false` when there is
+    /// no such tab or it is the only one, which can not be closed.
+    pub fn close_tab(&mut self, id: TabId) -> bool {
+        let Some(index) = self.tabs.iter().position(|&tab| tab == id) else {
+            return false;
+        };
+        if self.tabs.len() == 1 {
+            return false;
         }
 
-        for child in &node.children {
-            self.render_node(child, renderer, display, line_height);
+        if id == self.active_tab {
+            let neighbor = self
+                .tabs
+                .get(index + 1)
+                .copied()
+                .unwrap_or(self.tabs[index - 1]);
+            self.switch_tab(neighbor);
         }
+
+        self.tabs.remove(index);
+        self.background_tabs.remove(&id);
+        true
     }
-}
 
-pub fn parse_style(style: &str, property: &str) -> Option<String> {
-    let pattern =
-        format!(r"(^|\s*;\s*){}\s*:\s*([^;]+)", regex::escape(property));
-    let re = Regex::new(&pattern).unwrap();
+    /// Every open tab in the order they were opened.
+    pub fn tabs(&self) -> &[TabId] {
+        &self.tabs
+    }
 
-    re.captures(style)
-        .and_then(|cap| cap.get(2).map(|m| m.as_str().trim().to_string()))
-}
+    /// The tab whose page is shown.
+    pub fn active_tab(&self) -> TabId {
+        self.active_tab
+    }
 
-pub fn hex_to_rgba(hex: &str) -> Option<[f32; 4]> {
-    let hex = if hex.starts_with('#') { &hex[1..] } else { hex };
+    /// Reads and loads a local html file. Invalid UTF-8 sequences are
+    /// replaced rather than rejected. A path like `page.html#intro` that
+    /// does not exist as written opens `page.html` scrolled to the element
+    /// with the id `intro`. The file is added to the history.
+    pub fn load_file(&mut self, path: &Path) -> io::Result<()> {
+        self.read_file(path)?;
+        self.history.push(&path.to_string_lossy());
 
-    if hex.len() == 6 {
-        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-        Some([r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0]) // Alpha set to 1.0
-    } else {
-        None
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn read_file(&mut self, path: &Path) -> io::Result<()> {
+        let (path, fragment) = match path.to_str().and_then(split_fragment) {
+            Some((file, fragment)) if !path.exists() => {
+                (Path::new(file), Some(fragment))
+            }
+            _ => (path, None),
+        };
 
-    #[test]
-    fn test_style_parsing() {
-        let style = "my-style: 15; my-other-style: 'hello'";
+        let bytes = fs::read(path)?;
+        self.load_html(&String::from_utf8_lossy(&bytes));
+        self.base = Some(path.to_string_lossy().into_owned());
+        self.pending_fragment = fragment.map(str::to_string);
 
-        assert_eq!(parse_style(style, "my-style").unwrap(), "15");
-        assert_eq!(parse_style(style, "my-other-style").unwrap(), "'hello'");
+        Ok(())
+    }
 
-        let style2 = "background-color: #ff0000";
+    /// Fetches `url` over HTTP with a blocking request and loads the
+    /// response body. Invalid UTF-8 sequences are replaced rather than
+    /// rejected. The url is added to the history.
+    #[cfg(feature = "net")]
+    pub fn load_url(&mut self, url: &str) -> Result<(), LoadError> {
+        self.fetch_url(url)?;
+        self.history.push(url);
 
-        assert_eq!(parse_style(style2, "background-color").unwrap(), "#ff0000");
-        assert_eq!(parse_style(style2, "color").is_none(), true);
+        Ok(())
     }
 
-    #[test]
-    fn test_hex_to_rgba() {
-        let color = "#ffaa00";
-        assert_eq!(hex_to_rgba(color).unwrap(), [1.0, 0.6666667, 0.0, 1.0]);
+    #[cfg(feature = "net")]
+    fn fetch_url(&mut self, url: &str) -> Result<(), LoadError> {
+        if is_data_url(url) {
+            return self.decode_data_url(url);
+        }
+
+        // the fragment is not sent, it names the element to scroll to
+        let (url, fragment) = match split_fragment(url) {
+            Some((url, fragment)) => (url, Some(fragment)),
+            None => (url, None),
+        };
+
+        let response = ureq::get(url)
+            .call()
+            .map_err(|err| LoadError::Http(Box::new(err)))?;
+
+        let mut bytes = Vec::new();
+        response.into_reader().read_to_end(&mut bytes)?;
+        self.load_html(&String::from_utf8_lossy(&bytes));
+        self.base = Some(url.to_string());
+        self.pending_fragment = fragment.map(str::to_string);
+
+        Ok(())
+    }
+
+    /// Loads the html page held by a `data:text/html` URL, percent-encoded
+    /// or base64 encoded. The url is added to the history.
+    pub fn load_data_url(&mut self, url: &str) -> Result<(), LoadError> {
+        self.decode_data_url(url)?;
+        self.history.push(url);
+
+        Ok(())
+    }
+
+    fn decode_data_url(&mut self, url: &str) -> Result<(), LoadError> {
+        let data_url = parse_data_url(url)?;
+        data_url.expect_media_type(&["text/html"])?;
+
+        self.load_html(&String::from_utf8_lossy(&data_url.data));
+        self.pending_fragment = url
+            .split_once('#')
+            .map(|(_, fragment)| fragment.to_string())
+            .filter(|fragment| !fragment.is_empty());
+
+        Ok(())
+    }
+
+    /// Loads the page before the current one in the history. Returns
+    /// whether there was one. When it fails to load, the history stays at
+    /// the page shown.
+    pub fn go_back(&mut self) -> Result<bool, LoadError> {
+        let Some(target) = self.history.peek_back().map(str::to_string) else {
+            return Ok(false);
+        };
+
+        self.load_history_entry(&target)?;
+        self.history.back();
+        Ok(true)
+    }
+
+    /// Loads the page after the current one in the history, the one last
+    /// gone back from. Returns whether there was one. When it fails to
+    /// load, the history stays at the page shown.
+    pub fn go_forward(&mut self) -> Result<bool, LoadError> {
+        let Some(target) = self.history.peek_forward().map(str::to_string)
+        else {
+            return Ok(false);
+        };
+
+        self.load_history_entry(&target)?;
+        self.history.forward();
+        Ok(true)
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Loads a page of the history again without adding it once more.
+    fn load_history_entry(&mut self, target: &str) -> Result<(), LoadError> {
+        if is_data_url(target) {
+            return self.decode_data_url(target);
+        }
+
+        #[cfg(feature = "net")]
+        if target.starts_with("http://") || target.starts_with("https://") {
+            return self.fetch_url(target);
+        }
+
+        Ok(self.read_file(Path::new(target))?)
+    }
+
+    pub fn render(&mut self, renderer: &mut Renderer, target: &mut Frame) {
+        self.paint(&mut FrameCanvas { renderer, target });
+    }
+
+    /// Renders the page off-screen at the given size and returns the pixels
+    /// as RGBA bytes, top row first. Useful for screenshots and tests.
+    pub fn render_to_buffer(
+        &mut self,
+        renderer: &mut Renderer,
+        size: (u32, u32),
+    ) -> Vec<u8> {
+        renderer.render_to_buffer(size, |renderer, target| {
+            self.paint(&mut FrameCanvas { renderer, target })
+        })
+    }
+
+    /// Whether the page has to be painted again, because it was loaded or
+    /// scrolled since the last `paint`.
+    pub fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    pub fn scroll_x(&self) -> f32 {
+        self.camera.position[0]
+    }
+
+    pub fn scroll_y(&self) -> f32 {
+        self.camera.position[1]
+    }
+
+    /// Width of the area the page is shown in, as of the last `paint`.
+    pub fn viewport_width(&self) -> f32 {
+        self.camera.screen_size.0 as f32 / self.camera.zoom()
+    }
+
+    /// Height of the area the page is shown in, as of the last `paint`.
+    /// Zooming in shows less of the page.
+    pub fn viewport_height(&self) -> f32 {
+        self.camera.screen_size.1 as f32 / self.camera.zoom()
+    }
+
+    /// Height of the loaded page as of the last `paint` with the page
+    /// margin above and below it, nothing before it was painted.
+    pub fn content_height(&self) -> f32 {
+        self.render_graph.as_ref().map_or(0.0, |render_graph| {
+            render_graph.content_height() + 2.0 * self.page_margin
+        })
+    }
+
+    /// Width of the loaded page as of the last `paint` with the page margin
+    /// on both sides, nothing before it was painted.
+    pub fn content_width(&self) -> f32 {
+        self.render_graph.as_ref().map_or(0.0, |render_graph| {
+            render_graph.content_width() + 2.0 * self.page_margin
+        })
+    }
+
+    /// Scrolls so the top of the element with the given id is at the top of
+    /// the viewport, or as close as the page allows. Returns whether the
+    /// element was found. Positions are from the last `paint`.
+    pub fn scroll_to_element(&mut self, id: &str) -> bool {
+        let Some(render_graph) = &self.render_graph else {
+            return false;
+        };
+        let Some(node) = render_graph.get_element_by_id(id) else {
+            return false;
+        };
+
+        let delta = node.rect.y - self.camera.position[1];
+        let content_height = self.content_height();
+        let viewport_height = self.viewport_height();
+        if self
+            .camera
+            .scroll_clamped(delta, content_height, viewport_height)
+        {
+            self.needs_redraw = true;
+        }
+
+        true
+    }
+
+    /// Scrolls the page down by `delta` pixels, or up for negative values.
+    /// Scrolling stops at the top and at the bottom of the page.
+    pub fn scroll_by(&mut self, delta: f32) {
+        let content_height = self.content_height();
+        let viewport_height = self.viewport_height();
+        if self
+            .camera
+            .scroll_clamped(delta, content_height, viewport_height)
+        {
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Scrolls the page right by `delta` pixels, or left for negative
+    /// values. Only pages wider than the viewport can be scrolled sideways.
+    pub fn scroll_x_by(&mut self, delta: f32) {
+        let content_width = self.content_width();
+        let viewport_width = self.viewport_width();
+        if self
+            .camera
+            .scroll_x_clamped(delta, content_width, viewport_width)
+        {
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Lays the loaded page out for the size of `canvas` and draws it.
+    pub fn paint(&mut self, canvas: &mut impl Canvas) {
+        // pages without a background of their own are drawn on white
+        let background = self
+            .render_graph
+            .as_ref()
+            .and_then(HtmlRenderGraph::background_color);
+        canvas.set_clear_color(background.unwrap_or(DEFAULT_CLEAR_COLOR));
+
+        let mut anchor_regions = Vec::new();
+        let mut title_regions = Vec::new();
+        let mut glyphs = Vec::new();
+        let mut glyph_runs = Vec::new();
+        let margin = self.page_margin;
+        if let Some(render_graph) = &mut self.render_graph {
+            let (screen_w, screen_h) = canvas.screen_size();
+            let viewport = Rect::new(
+                margin,
+                margin,
+                (screen_w as f32 - 2.0 * margin).max(0.0),
+                (screen_h as f32 - 2.0 * margin).max(0.0),
+            );
+            render_graph.layout(canvas, viewport);
+
+            // the element a fragment points to has no position before the
+            // page is laid out, the clamping below keeps it in bounds
+            let fragment = self.pending_fragment.take();
+            if let Some(node) =
+                fragment.and_then(|id| render_graph.get_element_by_id(&id))
+            {
+                self.camera.position[1] = node.rect.y;
+            }
+
+            // the page may have become smaller than the scroll position
+            self.camera.screen_size = (screen_w, screen_h);
+            self.camera.set_zoom(canvas.zoom());
+            let viewport_width = screen_w as f32 / self.camera.zoom();
+            let viewport_height = screen_h as f32 / self.camera.zoom();
+            self.camera.scroll_x_clamped(
+                0.0,
+                render_graph.content_width() + 2.0 * margin,
+                viewport_width,
+            );
+            self.camera.scroll_clamped(
+                0.0,
+                render_graph.content_height() + 2.0 * margin,
+                viewport_height,
+            );
+
+            // the canvas zooms around the center of the screen, the page is
+            // moved so the scroll position stays at the top left of what is
+            // visible
+            let visible = visible_rect(&self.camera);
+            let scroll = (
+                self.camera.position[0] - visible.x,
+                self.camera.position[1] - visible.y,
+            );
+            for node in &render_graph.nodes {
+                render_node(
+                    node,
+                    canvas,
+                    scroll,
+                    &mut anchor_regions,
+                    &mut title_regions,
+                    &mut glyphs,
+                    &mut glyph_runs,
+                );
+            }
+            self.painted_scroll = scroll;
+        }
+
+        // the text searched for may have moved or changed since it was found
+        if !self.find_query.is_empty() {
+            self.find_matches =
+                match_ranges(&glyphs, &glyph_runs, &self.find_query);
+            self.current_find_match = self
+                .current_find_match
+                .filter(|&current| current < self.find_matches.len());
+        }
+        for (index, range) in self.find_matches.iter().enumerate() {
+            let color = if self.current_find_match == Some(index) {
+                CURRENT_FIND_MATCH_COLOR
+            } else {
+                FIND_MATCH_COLOR
+            };
+            let found = Selection {
+                start: range.start,
+                end: range.end,
+            };
+            for rect in highlight_rects(&glyphs, found) {
+                canvas.draw_quad(
+                    (rect.x, rect.y),
+                    (rect.width, rect.height),
+                    color,
+                );
+            }
+        }
+
+        // text is drawn over every quad, so the highlight can go last and
+        // still lie behind the selected text
+        if let Some(selection) = self.selection {
+            for rect in highlight_rects(&glyphs, selection) {
+                canvas.draw_quad(
+                    (rect.x, rect.y),
+                    (rect.width, rect.height),
+                    SELECTION_COLOR,
+                );
+            }
+        }
+
+        // the search status and the tooltip go over the whole page
+        if let Some(status) = self.find_status() {
+            draw_find_status(canvas, &status, visible_rect(&self.camera));
+        }
+        if let Some(tooltip) = &mut self.tooltip {
+            if tooltip.since.elapsed() >= TOOLTIP_DELAY {
+                let visible = visible_rect(&self.camera);
+                draw_tooltip(canvas, &tooltip.title, tooltip.position, visible);
+                tooltip.shown = true;
+            }
+        }
+
+        self.anchor_regions = anchor_regions;
+        self.title_regions = title_regions;
+        self.glyphs = glyphs;
+        self.glyph_runs = glyph_runs;
+        self.needs_redraw = false;
+    }
+
+    /// Starts selecting text at the given screen position, nothing is
+    /// selected until the selection is extended.
+    pub fn start_selection(&mut self, x: f32, y: f32) {
+        let (x, y) = self.screen_to_canvas(x, y);
+        let selection = caret_at(&self.glyphs, x, y).map(|caret| Selection {
+            start: caret,
+            end: caret,
+        });
+
+        if self.selection.is_some_and(|old| !old.is_empty()) {
+            self.needs_redraw = true;
+        }
+        self.selection = selection;
+    }
+
+    /// Moves the end of the selection to the given screen position, like
+    /// dragging the mouse does.
+    pub fn extend_selection(&mut self, x: f32, y: f32) {
+        let (x, y) = self.screen_to_canvas(x, y);
+        let Some(selection) = &mut self.selection else {
+            return;
+        };
+
+        if let Some(caret) = caret_at(&self.glyphs, x, y) {
+            if caret != selection.end {
+                selection.end = caret;
+                self.needs_redraw = true;
+            }
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        if self.selection.take().is_some() {
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    /// The text of the selection as it was last painted, `None` when
+    /// nothing is selected.
+    pub fn selected_text(&self) -> Option<String> {
+        self.selection
+            .filter(|selection| !selection.is_empty())
+            .map(|selection| selected_text(&self.glyphs, selection))
+    }
+
+    /// Searches the page for `query`, ignoring case, highlights every match
+    /// and scrolls to the first one. Returns the number of matches. The
+    /// text is searched as it was last painted and again whenever the page
+    /// is painted, an empty query ends the search.
+    pub fn find(&mut self, query: &str) -> usize {
+        let matches = match_ranges(&self.glyphs, &self.glyph_runs, query);
+        // the status of the search changes along with the highlights
+        if self.find_query != query
+            || !self.find_matches.is_empty()
+            || !matches.is_empty()
+        {
+            self.needs_redraw = true;
+        }
+
+        self.find_query = query.to_string();
+        self.find_matches = matches;
+        self.current_find_match = None;
+        self.find_next();
+        self.find_matches.len()
+    }
+
+    /// Moves on to the next match of the search, wrapping around to the
+    /// first one, and scrolls it into view. Returns its index, `None` when
+    /// nothing was found.
+    pub fn find_next(&mut self) -> Option<usize> {
+        let count = self.find_matches.len();
+        let next = match self.current_find_match {
+            _ if count == 0 => return None,
+            Some(current) => (current + 1) % count,
+            None => 0,
+        };
+        self.go_to_find_match(next);
+        Some(next)
+    }
+
+    /// Moves back to the previous match of the search, wrapping around to
+    /// the last one, and scrolls it into view.
+    pub fn find_previous(&mut self) -> Option<usize> {
+        let count = self.find_matches.len();
+        let previous = match self.current_find_match {
+            _ if count == 0 => return None,
+            Some(current) => (current + count - 1) % count,
+            None => count - 1,
+        };
+        self.go_to_find_match(previous);
+        Some(previous)
+    }
+
+    /// Ends the search and removes its highlights.
+    pub fn clear_find(&mut self) {
+        if !self.find_query.is_empty() {
+            self.needs_redraw = true;
+        }
+        self.find_query.clear();
+        self.find_matches.clear();
+        self.current_find_match = None;
+    }
+
+    /// Glyph ranges of the text found by `find`, as of the last `paint`.
+    pub fn find_matches(&self) -> &[Range<usize>] {
+        &self.find_matches
+    }
+
+    /// Index of the match navigated to in `find_matches`.
+    pub fn current_find_match(&self) -> Option<usize> {
+        self.current_find_match
+    }
+
+    /// How the search went, shown in the corner of the screen while
+    /// searching: which match is the current one or that nothing was found.
+    pub fn find_status(&self) -> Option<String> {
+        if self.find_query.is_empty() {
+            return None;
+        }
+
+        Some(match self.current_find_match {
+            Some(current) => format!(
+                "{} of {} matches",
+                current + 1,
+                self.find_matches.len()
+            ),
+            None => format!("No matches for \"{}\"", self.find_query),
+        })
+    }
+
+    /// Makes `index` the current match and scrolls just enough to center
+    /// it when any part of it is out of view.
+    fn go_to_find_match(&mut self, index: usize) {
+        self.current_find_match = Some(index);
+        self.needs_redraw = true;
+
+        let range = self.find_matches[index].clone();
+        let found = Selection {
+            start: range.start,
+            end: range.end,
+        };
+        let Some(rect) = highlight_rects(&self.glyphs, found).first().copied()
+        else {
+            return;
+        };
+
+        // the position of the match on the page, relative to what is shown
+        let left = rect.x + self.painted_scroll.0 - self.camera.position[0];
+        let top = rect.y + self.painted_scroll.1 - self.camera.position[1];
+        let viewport_width = self.viewport_width();
+        let viewport_height = self.viewport_height();
+        if left < 0.0 || left + rect.width > viewport_width {
+            self.scroll_x_by(left + (rect.width - viewport_width) / 2.0);
+        }
+        if top < 0.0 || top + rect.height > viewport_height {
+            self.scroll_by(top + (rect.height - viewport_height) / 2.0);
+        }
+    }
+
+    /// Where the link `href` on the loaded page points to. Relative links
+    /// are resolved against the file or url the page was loaded from, like
+    /// `b.html` on `/pages/a.html` to `/pages/b.html`. Links with a scheme
+    /// and links on pages loaded from html directly are returned as is.
+    pub fn resolve_link(&self, href: &str) -> String {
+        match &self.base {
+            Some(base) if !has_scheme(href) => resolve_href(base, href),
+            _ => href.to_string(),
+        }
+    }
+
+    /// Sets what happens when a link is clicked, usually loading the page
+    /// it points to. The closure gets the link's href as written, see
+    /// `resolve_link`.
+    pub fn set_on_link_click(
+        &mut self,
+        on_link_click: impl FnMut(&str) + 'static,
+    ) {
+        self.on_link_click = Some(Box::new(on_link_click));
+    }
+
+    /// The left button went down at the given screen position, which
+    /// starts selecting text.
+    pub fn mouse_down(&mut self, x: f32, y: f32) {
+        self.pressed_at = Some((x, y));
+        self.start_selection(x, y);
+    }
+
+    /// The left button was released at the given screen position. When the
+    /// mouse barely moved on the screen since it went down this is a click,
+    /// and a link under it is followed, whatever the zoom. Returns whether
+    /// a link was clicked.
+    pub fn mouse_up(&mut self, x: f32, y: f32) -> bool {
+        let Some((pressed_x, pressed_y)) = self.pressed_at.take() else {
+            return false;
+        };
+
+        let moved = (x - pressed_x).hypot(y - pressed_y);
+        if moved > CLICK_DISTANCE {
+            return false;
+        }
+
+        let Some(href) = self.hit_test(x, y).map(str::to_string) else {
+            return false;
+        };
+        if let Some(on_link_click) = &mut self.on_link_click {
+            on_link_click(&href);
+        }
+
+        true
+    }
+
+    /// Moves the mouse to the given screen position. Returns whether it
+    /// entered or left a link, so the cursor has to change.
+    pub fn update_hover(&mut self, x: f32, y: f32) -> bool {
+        // the tooltip follows the mouse until it shows, and goes away once
+        // the mouse leaves the element
+        let mouse = self.screen_to_canvas(x, y);
+        let title = self
+            .title_regions
+            .iter()
+            .filter(|region| region.rect.contains(mouse.0, mouse.1))
+            .max_by_key(|region| region.depth)
+            .map(|region| &region.title);
+        match (&mut self.tooltip, title) {
+            (Some(tooltip), Some(title)) if tooltip.title == *title => {
+                if !tooltip.shown {
+                    tooltip.position = mouse;
+                }
+            }
+            (tooltip, title) => {
+                if tooltip.as_ref().is_some_and(|tooltip| tooltip.shown) {
+                    self.needs_redraw = true;
+                }
+                *tooltip = title.map(|title| Tooltip {
+                    title: title.clone(),
+                    position: mouse,
+                    since: Instant::now(),
+                    shown: false,
+                });
+            }
+        }
+
+        let hovered = self.hit_test(x, y).map(str::to_string);
+        if hovered == self.hovered_link {
+            return false;
+        }
+
+        self.hovered_link = hovered;
+        true
+    }
+
+    /// When the title under the mouse is due to show, `None` when there is
+    /// none or it is already shown. The page has to be painted again then.
+    pub fn tooltip_due(&self) -> Option<Instant> {
+        self.tooltip
+            .as_ref()
+            .filter(|tooltip| !tooltip.shown)
+            .map(|tooltip| tooltip.since + TOOLTIP_DELAY)
+    }
+
+    /// The title of the element the mouse is resting on.
+    pub fn hovered_title(&self) -> Option<&str> {
+        self.tooltip.as_ref().map(|tooltip| tooltip.title.as_str())
+    }
+
+    /// The href of the link the mouse was last over.
+    pub fn hovered_link(&self) -> Option<&str> {
+        self.hovered_link.as_deref()
+    }
+
+    /// Returns the href of the link under the given screen position. When
+    /// links are nested the innermost one wins.
+    pub fn hit_test(&self, x: f32, y: f32) -> Option<&str> {
+        let (x, y) = self.screen_to_canvas(x, y);
+        self.anchor_regions
+            .iter()
+            .filter(|region| region.rect.contains(x, y))
+            .max_by_key(|region| region.depth)
+            .map(|region| region.href.as_str())
+    }
+
+    /// Converts a position on the screen, like the mouse's, to the canvas
+    /// coordinates links, titles and text were painted at.
+    fn screen_to_canvas(&self, x: f32, y: f32) -> (f32, f32) {
+        let visible = visible_rect(&self.camera);
+        let zoom = self.camera.zoom();
+        (visible.x + x / zoom, visible.y + y / zoom)
+    }
+}
+
+/// The part of the canvas that shows on the screen of `camera`. Zooming in
+/// scales the canvas around the center of the screen, so less of it is
+/// visible.
+fn visible_rect(camera: &Camera) -> Rect {
+    let (screen_w, screen_h) = camera.screen_size;
+    let width = screen_w as f32 / camera.zoom();
+    let height = screen_h as f32 / camera.zoom();
+    Rect::new(
+        (screen_w as f32 - width) / 2.0,
+        (screen_h as f32 - height) / 2.0,
+        width,
+        height,
+    )
+}
+
+/// Splits `page#fragment` into the page and the fragment. There is no
+/// fragment when nothing follows the `#`.
+fn split_fragment(target: &str) -> Option<(&str, &str)> {
+    target
+        .split_once('#')
+        .filter(|(_, fragment)| !fragment.is_empty())
+}
+
+/// Whether `target` starts with a scheme like `https:` or `data:`. Single
+/// letters are drive letters of Windows paths.
+fn has_scheme(target: &str) -> bool {
+    target.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    })
+}
+
+/// Resolves the relative link `href` against the path or http(s) url
+/// `base` of the page it is on.
+fn resolve_href(base: &str, href: &str) -> String {
+    let Some((scheme, rest)) = base
+        .split_once("://")
+        .filter(|(scheme, _)| matches!(*scheme, "http" | "https"))
+    else {
+        let dir = Path::new(base).parent().unwrap_or(Path::new(""));
+        return dir.join(href).to_string_lossy().into_owned();
+    };
+
+    let (host, path) = match rest.find(['/', '?', '#']) {
+        Some(end) => (&rest[..end], &rest[end..]),
+        None => (rest, "/"),
+    };
+    let path = &path[..path.find(['?', '#']).unwrap_or(path.len())];
+    let path = if path.is_empty() { "/" } else { path };
+
+    if href.starts_with("//") {
+        return format!("{}:{}", scheme, href);
+    }
+    let (href_path, suffix) =
+        href.split_at(href.find(['?', '#']).unwrap_or(href.len()));
+    let joined = if href_path.starts_with('/') {
+        href_path.to_string()
+    } else if href_path.is_empty() {
+        path.to_string()
+    } else {
+        let dir = &path[..path.rfind('/').map_or(0, |slash| slash + 1)];
+        format!("{}{}", dir, href_path)
+    };
+
+    format!(
+        "{}://{}{}{}",
+        scheme,
+        host,
+        remove_dot_segments(&joined),
+        suffix
+    )
+}
+
+/// Drops the `.` and `..` segments of the absolute url path `path`, like
+/// `/a/./b/../c` to `/a/c`.
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut parts = path.trim_start_matches('/').split('/').peekable();
+    while let Some(part) = parts.next() {
+        match part {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(part),
+        }
+        // a path ending in a dot segment names a directory
+        if parts.peek().is_none() && matches!(part, "." | "..") {
+            segments.push("");
+        }
+    }
+
+    format!("/{}", segments.join("/"))
+}
+
+/// Calls `f` with `root` and every node below it, parents before their
+/// children. `f` also gets the nesting depth of each node, counting from
+/// `depth` for `root`.
+pub fn visit<'n, F: FnMut(&'n RenderNode, usize)>(
+    root: &'n RenderNode,
+    depth: usize,
+    f: &mut F,
+) {
+    f(root, depth);
+    for child in &root.children {
+        visit(child, depth + 1, f);
+    }
+}
+
+/// Like `visit`, but `f` can change the nodes.
+pub fn visit_mut<F: FnMut(&mut RenderNode, usize)>(
+    root: &mut RenderNode,
+    depth: usize,
+    f: &mut F,
+) {
+    f(root, depth);
+    for child in &mut root.children {
+        visit_mut(child, depth + 1, f);
+    }
+}
+
+/// Records the path to every element with an id below `nodes`, which are
+/// at `path`. The first element in the document with an id keeps it.
+fn index_ids(
+    nodes: &[RenderNode],
+    path: &mut Vec<usize>,
+    ids: &mut HashMap<String, Vec<usize>>,
+) {
+    for (index, node) in nodes.iter().enumerate() {
+        path.push(index);
+        if let Some(id) = &node.id {
+            ids.entry(id.clone()).or_insert_with(|| path.clone());
+        }
+        index_ids(&node.children, path, ids);
+        path.pop();
+    }
+}
+
+/// Appends the text of `node` and its descendants to `text`. `separate` is
+/// set at the edges of blocks, the next text then starts after a space.
+fn push_text_content(
+    node: &RenderNode,
+    text: &mut String,
+    separate: &mut bool,
+) {
+    let block = !node.element.is_inline();
+    *separate |= block;
+
+    if let Some(content) = node.content.as_deref().filter(|c| !c.is_empty()) {
+        if *separate
+            && !text.is_empty()
+            && !text.ends_with(char::is_whitespace)
+            && !content.starts_with(char::is_whitespace)
+        {
+            text.push(' ');
+        }
+        text.push_str(content);
+        *separate = false;
+    }
+    for child in &node.children {
+        push_text_content(child, text, separate);
+    }
+
+    *separate |= block;
+}
+
+/// Adds the rules of every `<style>` element under `nodes` to `stylesheet`
+/// in document order.
+fn collect_stylesheets(nodes: &[Node], stylesheet: &mut Stylesheet) {
+    for node in nodes {
+        node.walk(&mut |node, _| {
+            if let Node::Element { tag, children, .. } = node {
+                if tag.eq_ignore_ascii_case("style") {
+                    for child in children {
+                        if let Node::Text(source) = child {
+                            stylesheet.extend(Stylesheet::parse(source));
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Draws `node` and its children at the boxes the layout pass gave them,
+/// moved left and up by `scroll`.
+fn render_node(
+    node: &RenderNode,
+    canvas: &mut impl Canvas,
+    scroll: (f32, f32),
+    anchor_regions: &mut Vec<AnchorRegion>,
+    title_regions: &mut Vec<TitleRegion>,
+    glyphs: &mut Vec<GlyphBox>,
+    glyph_runs: &mut Vec<Range<usize>>,
+) {
+    if node.element == HtmlElement::Template {
+        return;
+    }
+
+    let rect = Rect {
+        x: node.rect.x - scroll.0,
+        y: node.rect.y - scroll.1,
+        ..node.rect
+    };
+    let content_rect = Rect {
+        x: node.content_rect.x - scroll.0,
+        y: node.content_rect.y - scroll.1,
+        ..node.content_rect
+    };
+
+    // the background covers the whole element box and goes below the text
+    // and children, so it is drawn first
+    if node.bg_color[3] != 0.0 && rect.height > 0.0 {
+        let bg_rect = match node.element {
+            HtmlElement::Span | HtmlElement::Code => {
+                inline_background_rect(node, canvas, rect, content_rect)
+            }
+            _ => rect,
+        };
+        canvas.draw_rounded_quad(
+            (bg_rect.x, bg_rect.y),
+            (bg_rect.width, bg_rect.height),
+            node.border_radius,
+            node.bg_color,
+        );
+    }
+
+    // uniform borders go through the canvas primitive, mixed ones are
+    // drawn side by side
+    match node.borders.uniform() {
+        Some(border) => canvas.draw_border(rect, border.width, border.color),
+        None => {
+            let borders = &node.borders;
+            let sides =
+                [borders.top, borders.right, borders.bottom, borders.left];
+            let rects = border_rects(rect, borders.widths());
+            for (side, side_rect) in sides.iter().zip(rects) {
+                if side.width > 0.0 {
+                    canvas.draw_quad(
+                        (side_rect.x, side_rect.y),
+                        (side_rect.width, side_rect.height),
+                        side.color,
+                    );
+                }
+            }
+        }
+    }
+
+    if node.element == HtmlElement::Img {
+        let screen_pos = (content_rect.x, content_rect.y);
+        let bounds = (content_rect.width, content_rect.height);
+        let drawn = node.src.as_deref().is_some_and(|src| {
+            canvas.draw_image(src, screen_pos, bounds, node.opacity)
+        });
+
+        if !drawn {
+            canvas.draw_quad(
+                screen_pos,
+                bounds,
+                with_opacity(IMAGE_PLACEHOLDER_COLOR, node.opacity),
+            );
+        }
+    }
+
+    // a rule is a line in its text color filling the content box
+    if node.element == HtmlElement::HorizontalRule {
+        canvas.draw_quad(
+            (content_rect.x, content_rect.y),
+            (content_rect.width, content_rect.height),
+            node.fg_color,
+        );
+    }
+
+    // draw the element if is there a content
+    if let Some(content) = node_text(node) {
+        // Draw the text with provided styles and layout, wrapping at the
+        // right edge of the content box
+        let cfg = TextDrawConfig {
+            screen_pos: (content_rect.x, content_rect.y),
+            bounds: (content_rect.width, f32::INFINITY),
+            fg_color: node.fg_color,
+            bg_color: [0.0, 0.0, 0.0, 0.0],
+            h_align: node.layout.h_align,
+            // the layout already placed the content box
+            v_align: VerticalAlign::Top,
+            style: node.font_style,
+        };
+        let boxes = canvas.glyph_boxes(&content, node.font_size, &cfg);
+        draw_text_decoration(
+            canvas,
+            &boxes,
+            node.text_decoration,
+            node.font_size,
+            node.fg_color,
+        );
+        let start = glyphs.len();
+        glyphs.extend(boxes);
+        glyph_runs.push(start..glyphs.len());
+        canvas.draw_text(&content, node.font_size, cfg);
+    }
+
+    for child in &node.children {
+        render_node(
+            child,
+            canvas,
+            scroll,
+            anchor_regions,
+            title_regions,
+            glyphs,
+            glyph_runs,
+        );
+    }
+
+    // links cover every line they and their children took up
+    if let Some(href) = &node.href {
+        if node.element == HtmlElement::Anchor {
+            anchor_regions.push(AnchorRegion {
+                rect,
+                href: href.clone(),
+                depth: node.position.0,
+            });
+        }
+    }
+
+    if let Some(title) = &node.title {
+        title_regions.push(TitleRegion {
+            rect,
+            title: title.clone(),
+            depth: node.position.0,
+        });
+    }
+}
+
+/// The part of the box of an inline element its background covers, only
+/// as wide as its text plus padding and borders, where the text is aligned.
+fn inline_background_rect(
+    node: &RenderNode,
+    canvas: &impl Canvas,
+    rect: Rect,
+    content_rect: Rect,
+) -> Rect {
+    let (text_width, _) = node_text(node)
+        .map(|text| {
+            canvas.measure_text(
+                &text,
+                node.font_size,
+                node.font_style,
+                Some(content_rect.width),
+                node.line_height,
+            )
+        })
+        .unwrap_or((0.0, 0.0));
+    let text_width = text_width.min(content_rect.width);
+
+    let free = content_rect.width - text_width;
+    let text_x = content_rect.x
+        + match node.layout.h_align {
+            HorizontalAlign::Left => 0.0,
+            HorizontalAlign::Center => free / 2.0,
+            HorizontalAlign::Right => free,
+        };
+    let inset_left = content_rect.x - rect.x;
+    let inset_right =
+        rect.x + rect.width - (content_rect.x + content_rect.width);
+
+    Rect {
+        x: text_x - inset_left,
+        width: inset_left + text_width + inset_right,
+        ..rect
+    }
+}
+
+/// Draws the line of `decoration` under or through every line of `glyphs`,
+/// from its first to its last character that is not whitespace.
+fn draw_text_decoration(
+    canvas: &mut impl Canvas,
+    glyphs: &[GlyphBox],
+    decoration: TextDecoration,
+    font_size: f32,
+    color: [f32; 4],
+) {
+    let position = match decoration {
+        TextDecoration::None => return,
+        TextDecoration::Underline => UNDERLINE_POSITION,
+        TextDecoration::LineThrough => LINE_THROUGH_POSITION,
+    };
+    let thickness = (font_size / DEFAULT_FONT_SIZE).max(1.0);
+
+    // the glyphs of a line share the top of their boxes
+    for line in glyphs.chunk_by(|a, b| a.rect.y == b.rect.y) {
+        let mut visible = line.iter().filter(|glyph| !glyph.ch.is_whitespace());
+        let Some(first) = visible.next() else {
+            continue;
+        };
+        let last = visible.next_back().unwrap_or(first);
+
+        let left = first.rect.x;
+        let right = last.rect.x + last.rect.width;
+        let y = first.rect.y + first.rect.height * position;
+        canvas.draw_quad((left, y), (right - left, thickness), color);
+    }
+}
+
+/// Draws `title` in a box next to the mouse at `mouse`. The box flips to
+/// the other side of the mouse where it would leave `visible`, the part of
+/// the canvas on the screen.
+fn draw_tooltip(
+    canvas: &mut impl Canvas,
+    title: &str,
+    mouse: (f32, f32),
+    visible: Rect,
+) {
+    let (text_width, text_height) = canvas.measure_text(
+        title,
+        TOOLTIP_FONT_SIZE,
+        FontStyle::Regular,
+        None,
+        TOOLTIP_FONT_SIZE * DEFAULT_LINE_HEIGHT,
+    );
+    let size = (
+        text_width + 2.0 * TOOLTIP_PADDING,
+        text_height + 2.0 * TOOLTIP_PADDING,
+    );
+    let rect = tooltip_rect(mouse, size, visible);
+
+    canvas.draw_quad(
+        (rect.x, rect.y),
+        (rect.width, rect.height),
+        TOOLTIP_BG_COLOR,
+    );
+    canvas.draw_border(rect, 1.0, TOOLTIP_BORDER_COLOR);
+    canvas.draw_text(
+        title,
+        TOOLTIP_FONT_SIZE,
+        TextDrawConfig {
+            screen_pos: (rect.x + TOOLTIP_PADDING, rect.y + TOOLTIP_PADDING),
+            ..Default::default()
+        },
+    );
+}
+
+/// Draws the status of the search in the bottom right corner of `visible`,
+/// in a box like a tooltip's.
+fn draw_find_status(canvas: &mut impl Canvas, status: &str, visible: Rect) {
+    let (text_width, text_height) = canvas.measure_text(
+        status,
+        TOOLTIP_FONT_SIZE,
+        FontStyle::Regular,
+        None,
+        TOOLTIP_FONT_SIZE * DEFAULT_LINE_HEIGHT,
+    );
+    let width = text_width + 2.0 * TOOLTIP_PADDING;
+    let height = text_height + 2.0 * TOOLTIP_PADDING;
+    let rect = Rect::new(
+        visible.x + visible.width - width - FIND_STATUS_MARGIN,
+        visible.y + visible.height - height - FIND_STATUS_MARGIN,
+        width,
+        height,
+    );
+
+    canvas.draw_quad(
+        (rect.x, rect.y),
+        (rect.width, rect.height),
+        TOOLTIP_BG_COLOR,
+    );
+    canvas.draw_border(rect, 1.0, TOOLTIP_BORDER_COLOR);
+    canvas.draw_text(
+        status,
+        TOOLTIP_FONT_SIZE,
+        TextDrawConfig {
+            screen_pos: (rect.x + TOOLTIP_PADDING, rect.y + TOOLTIP_PADDING),
+            ..Default::default()
+        },
+    );
+}
+
+/// Where a tooltip of the given size goes for the mouse at `mouse`: below
+/// and right of it, or above and left of it where that does not fit in
+/// `visible`.
+fn tooltip_rect(
+    mouse: (f32, f32),
+    (width, height): (f32, f32),
+    visible: Rect,
+) -> Rect {
+    let place = |mouse: f32, offset: f32, size: f32, start: f32, end: f32| {
+        let after = mouse + offset;
+        if after + size <= end {
+            after
+        } else {
+            (mouse - size).max(start)
+        }
+    };
+
+    Rect::new(
+        place(
+            mouse.0,
+            TOOLTIP_OFFSET.0,
+            width,
+            visible.x,
+            visible.x + visible.width,
+        ),
+        place(
+            mouse.1,
+            TOOLTIP_OFFSET.1,
+            height,
+            visible.y,
+            visible.y + visible.height,
+        ),
+        width,
+        height,
+    )
+}
+
+/// Appends the line `to_debug_string` shows for `node`, indented by its
+/// depth.
+fn write_debug_line(node: &RenderNode, depth: usize, output: &mut String) {
+    use std::fmt::Write;
+
+    let _ = write!(
+        output,
+        "{:indent$}{:?} ({}, {}) fg={} bg={}",
+        "",
+        node.element,
+        node.position.0,
+        node.position.1,
+        color_to_hex(node.fg_color),
+        color_to_hex(node.bg_color),
+        indent = depth * 2,
+    );
+    if let Some(content) = &node.content {
+        let _ = write!(output, " {:?}", content);
+    }
+    output.push('\n');
+}
+
+/// Formats a color as `#rrggbbaa`.
+pub fn color_to_hex(color: [f32; 4]) -> String {
+    color.iter().fold(String::from("#"), |mut hex, channel| {
+        let byte = (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+        hex.push_str(&format!("{:02x}", byte));
+        hex
+    })
+}
+
+#[cfg(feature = "serde")]
+fn serialize_color<S: serde::Serializer>(
+    color: &[f32; 4],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&color_to_hex(*color))
+}
+
+/// Text drawn for the node itself. List items draw their marker in front of
+/// the content.
+pub(crate) fn node_text(node: &RenderNode) -> Option<Cow<'_, str>> {
+    match (&node.list_marker, &node.content) {
+        (Some(marker), Some(content)) => {
+            Some(Cow::Owned(format!("{}{}", marker, content)))
+        }
+        (Some(marker), None) => Some(Cow::Borrowed(marker)),
+        (None, content) => content.as_deref().map(Cow::Borrowed),
+    }
+}
+
+/// Splits a `class` attribute into its names, which are separated by any
+/// amount of whitespace.
+fn parse_classes(value: &str) -> Vec<String> {
+    value.split_ascii_whitespace().map(str::to_string).collect()
+}
+
+fn is_display_none(value: &str) -> bool {
+    value.trim().eq_ignore_ascii_case("none")
+}
+
+/// Reads a `width` or `height` attribute, given in pixels without a unit.
+fn dimension_attribute(attributes: &Attributes, name: &str) -> Option<Length> {
+    attributes
+        .get(name)
+        .and_then(|value| value.trim().parse::<f32>().ok())
+        .filter(|value| *value >= 0.0)
+        .map(Length::Px)
+}
+
+/// Value of `property` in a `style` attribute, see `parse_inline_styles`.
+pub fn parse_style(style: &str, property: &str) -> Option<String> {
+    parse_inline_styles(style).remove(&property.to_ascii_lowercase())
+}
+
+/// Whether a `white-space` value keeps spaces and line breaks. Unknown
+/// values give `None`.
+fn parse_white_space(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "pre" | "pre-wrap" | "break-spaces" => Some(true),
+        "normal" | "nowrap" | "pre-line" => Some(false),
+        _ => None,
+    }
+}
+
+/// Columns between tab stops in preformatted text.
+const TAB_WIDTH: usize = 8;
+
+/// Replaces tabs with spaces up to the next tab stop.
+pub fn expand_tabs(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut column = 0;
+
+    for c in text.chars() {
+        match c {
+            '\t' => {
+                let spaces = TAB_WIDTH - column % TAB_WIDTH;
+                expanded.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            '\n' => {
+                expanded.push(c);
+                column = 0;
+            }
+            _ => {
+                expanded.push(c);
+                column += 1;
+            }
+        }
+    }
+
+    expanded
+}
+
+/// The text of an element whose children are only text and `<br>`
+/// elements, split into the lines the breaks end. `None` when there is no
+/// text or there are other children.
+fn text_lines(children: &[Node]) -> Option<Vec<String>> {
+    if !children.iter().any(|child| matches!(child, Node::Text(_))) {
+        return None;
+    }
+
+    let mut lines = vec![String::new()];
+    for child in children {
+        match child {
+            Node::Text(text) => lines.last_mut().unwrap().push_str(text),
+            Node::Comment(_) => {}
+            Node::Element { tag, .. } if tag.eq_ignore_ascii_case("br") => {
+                lines.push(String::new())
+            }
+            _ => return None,
+        }
+    }
+
+    Some(lines)
+}
+
+/// Drops the spaces kept at the edges of inline text where they start or
+/// end a run of inline siblings, or follow another space. Like in browsers
+/// the whitespace between two elements then shows as a single space.
+fn trim_inline_spaces(siblings: &mut [RenderNode]) {
+    // whether a space would be at the start of a line or follow another
+    let mut after_space = true;
+    // the last inline sibling with text, which may end a run
+    let mut last_text: Option<usize> = None;
+    for index in 0..siblings.len() {
+        let sibling = &mut siblings[index];
+        if !sibling.element.is_inline() {
+            if let Some(last) = last_text.take() {
+                trim_content_end(&mut siblings[last]);
+            }
+            after_space = true;
+            continue;
+        }
+
+        match &mut sibling.content {
+            Some(content) => {
+                if after_space && content.starts_with(' ') {
+                    content.remove(0);
+                }
+                after_space = content.ends_with(' ');
+                last_text = Some(index);
+            }
+            // an image or an element of its own children separates the
+            // spaces around it
+            None => {
+                after_space = false;
+                last_text = None;
+            }
+        }
+    }
+
+    if let Some(last) = last_text {
+        trim_content_end(&mut siblings[last]);
+    }
+}
+
+fn trim_content_end(node: &mut RenderNode) {
+    if let Some(content) = &mut node.content {
+        if content.ends_with(' ') {
+            content.pop();
+        }
+    }
+}
+
+/// Tag of the elements `for_each_child_box` puts text into, named like text
+/// nodes in the DOM so no page can use it.
+const ANONYMOUS_TEXT_TAG: &str = "#text";
+
+/// Calls `f` with every child element of an element, putting each run of
+/// text and line breaks between two of them into an element of its own so
+/// none of the text is lost when text and elements are mixed. Runs of
+/// whitespace only are dropped. Children that are all text are passed on
+/// as they are.
+fn for_each_child_box(children: &[Node], span: Span, mut f: impl FnMut(&Node)) {
+    let is_text = |node: &Node| match node {
+        Node::Text(_) | Node::Comment(_) => true,
+        Node::Element { tag, .. } => tag.eq_ignore_ascii_case("br"),
+        Node::Document { .. } => false,
+    };
+    let mixed = children.iter().any(|child| matches!(child, Node::Text(_)))
+        && !children.iter().all(is_text);
+    if !mixed {
+        children.iter().for_each(f);
+        return;
+    }
+
+    let mut run = Vec::new();
+    for child in children {
+        if is_text(child) {
+            run.push(child);
+            continue;
+        }
+
+        if let Some(text) = anonymous_text_element(&run, span) {
+            f(&text);
+        }
+        run.clear();
+        f(child);
+    }
+    if let Some(text) = anonymous_text_element(&run, span) {
+        f(&text);
+    }
+}
+
+/// An element holding a copy of the text and line breaks in `run`, unless
+/// it is whitespace only.
+fn anonymous_text_element(run: &[&Node], span: Span) -> Option<Node> {
+    let has_text = run.iter().any(
+        |node| matches!(node, Node::Text(text) if !text.trim().is_empty()),
+    );
+    if !has_text {
+        return None;
+    }
+
+    let children = run
+        .iter()
+        .filter_map(|node| match node {
+            Node::Text(text) => Some(Node::Text(text.clone())),
+            Node::Element { tag, span, .. } => Some(Node::Element {
+                tag: tag.clone(),
+                attributes: Attributes::new(),
+                children: Vec::new(),
+                span: *span,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    Some(Node::Element {
+        tag: ANONYMOUS_TEXT_TAG.to_string(),
+        attributes: Attributes::new(),
+        children,
+        span,
+    })
+}
+
+/// Collapses every run of ASCII whitespace into a single space and trims
+/// both ends.
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Parses an absolute length in pixels like `40px`.
+pub fn parse_px(value: &str) -> Option<f32> {
+    let px = value
+        .trim()
+        .strip_suffix("px")?
+        .trim()
+        .parse::<f32>()
+        .ok()?;
+
+    if px.is_finite() && px >= 0.0 {
+        Some(px)
+    } else {
+        None
+    }
+}
+
+/// Parses a box length given in pixels (`200px`), as a percentage of the
+/// containing box (`50%`), of the viewport's width (`50vw`) or height
+/// (`10vh`), or `auto`. A zero does not need a unit.
+pub fn parse_length(value: &str) -> Option<Length> {
+    let value = value.trim();
+    match value {
+        "auto" => return Some(Length::Auto),
+        "0" => return Some(Length::Px(0.0)),
+        _ => {}
+    }
+
+    let (percent, length): (&str, fn(f32) -> Length) =
+        if let Some(percent) = value.strip_suffix('%') {
+            (percent, Length::Percent)
+        } else if let Some(percent) = value.strip_suffix("vw") {
+            (percent, Length::Vw)
+        } else if let Some(percent) = value.strip_suffix("vh") {
+            (percent, Length::Vh)
+        } else {
+            return parse_px(value).map(Length::Px);
+        };
+
+    let percent = percent.trim().parse::<f32>().ok()?;
+    (percent.is_finite() && percent >= 0.0).then(|| length(percent))
+}
+
+/// Parses a `border-radius` in pixels, the same for every corner.
+/// Percentages are not supported.
+pub fn parse_border_radius(value: &str) -> Option<f32> {
+    match parse_length(value)? {
+        Length::Px(px) => Some(px),
+        _ => None,
+    }
+}
+
+/// Width of a border that only sets its style, like CSS `medium`.
+const DEFAULT_BORDER_WIDTH: f32 = 3.0;
+
+/// A border side while its properties are being resolved. Like in CSS it
+/// is only drawn once it got a visible style.
+#[derive(Debug, Clone, Copy)]
+struct BorderSide {
+    width: f32,
+    color: [f32; 4],
+    visible: bool,
+}
+
+impl BorderSide {
+    fn initial(fg_color: [f32; 4]) -> Self {
+        Self {
+            width: DEFAULT_BORDER_WIDTH,
+            color: fg_color,
+            visible: false,
+        }
+    }
+
+    fn resolve(self) -> Border {
+        if self.visible {
+            Border {
+                width: self.width,
+                color: self.color,
+            }
+        } else {
+            Border::default()
+        }
+    }
+}
+
+/// Resolves the border properties of an element, read through
+/// `style_value`. The `border` shorthand sets every side, `border-width`,
+/// `border-color` and `border-style` override it and the shorthands of
+/// single sides like `border-left` win over both. Every visible style is
+/// drawn as a solid line and colors default to the text color.
+pub fn parse_borders(
+    style_value: impl Fn(&str) -> Option<String>,
+    fg_color: [f32; 4],
+) -> Borders {
+    let mut sides = [BorderSide::initial(fg_color); 4];
+
+    if let Some(side) = style_value("border")
+        .and_then(|value| parse_border_side(&value, fg_color))
+    {
+        sides = [side; 4];
+    }
+
+    if let Some(widths) = style_value("border-width")
+        .and_then(|value| parse_sides(&value, parse_border_width))
+    {
+        for (side, width) in sides.iter_mut().zip(widths) {
+            side.width = width;
+        }
+    }
+
+    if let Some(colors) = style_value("border-color")
+        .and_then(|value| parse_sides(&value, parse_color))
+    {
+        for (side, color) in sides.iter_mut().zip(colors) {
+            side.color = color;
+        }
+    }
+
+    if let Some(styles) = style_value("border-style")
+        .and_then(|value| parse_sides(&value, parse_border_style))
+    {
+        for (side, visible) in sides.iter_mut().zip(styles) {
+            side.visible = visible;
+        }
+    }
+
+    let names = ["border-top", "border-right", "border-bottom", "border-left"];
+    for (side, name) in sides.iter_mut().zip(names) {
+        if let Some(value) = style_value(name)
+            .and_then(|value| parse_border_side(&value, fg_color))
+        {
+            *side = value;
+        }
+    }
+
+    let [top, right, bottom, left] = sides.map(BorderSide::resolve);
+    Borders {
+        top,
+        right,
+        bottom,
+        left,
+    }
+}
+
+/// Parses a border shorthand like `1px solid #000`. Values left out get
+/// their initial value, so a border without a style is not drawn.
+fn parse_border_side(value: &str, fg_color: [f32; 4]) -> Option<BorderSide> {
+    let mut side = BorderSide::initial(fg_color);
+
+    for part in split_values(value) {
+        if let Some(visible) = parse_border_style(part) {
+            side.visible = visible;
+        } else if let Some(width) = parse_border_width(part) {
+            side.width = width;
+        } else {
+            side.color = parse_color(part)?;
+        }
+    }
+
+    Some(side)
+}
+
+/// Whether a border style is drawn. Unknown styles give `None`.
+fn parse_border_style(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "none" | "hidden" => Some(false),
+        "solid" | "dashed" | "dotted" | "double" | "groove" | "ridge"
+        | "inset" | "outset" => Some(true),
+        _ => None,
+    }
+}
+
+fn parse_border_width(value: &str) -> Option<f32> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "0" => Some(0.0),
+        "thin" => Some(1.0),
+        "medium" => Some(DEFAULT_BORDER_WIDTH),
+        "thick" => Some(5.0),
+        value => parse_px(value),
+    }
+}
+
+/// Parses one to four values of a per-side property into top, right,
+/// bottom and left values, following the same rules as `margin`.
+fn parse_sides<T: Copy>(
+    value: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Option<[T; 4]> {
+    let values = split_values(value)
+        .into_iter()
+        .map(parse)
+        .collect::<Option<Vec<_>>>()?;
+
+    expand_sides(&values)
+}
+
+/// Spreads one to four values clockwise over the sides of a box, starting
+/// at the top.
+fn expand_sides<T: Copy>(values: &[T]) -> Option<[T; 4]> {
+    match *values {
+        [all] => Some([all; 4]),
+        [vertical, horizontal] => {
+            Some([vertical, horizontal, vertical, horizontal])
+        }
+        [top, horizontal, bottom] => {
+            Some([top, horizontal, bottom, horizontal])
+        }
+        [top, right, bottom, left] => Some([top, right, bottom, left]),
+        _ => None,
+    }
+}
+
+/// Splits a shorthand into its values at whitespace, keeping functions like
+/// `rgb(0, 0, 0)` together.
+fn split_values(value: &str) -> Vec<&str> {
+    let mut values = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+
+    for (index, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(start) = start.take() {
+                    values.push(&value[start..index]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        start.get_or_insert(index);
+    }
+
+    if let Some(start) = start {
+        values.push(&value[start..]);
+    }
+
+    values
+}
+
+/// Parses the `margin` and `padding` shorthands. Like in CSS one value sets
+/// every side, two set vertical and horizontal sides, three set top,
+/// horizontal and bottom and four go clockwise from the top.
+pub fn parse_box_edges(value: &str) -> Option<BoxEdges> {
+    let [top, right, bottom, left] = parse_sides(value, parse_length)?;
+
+    Some(BoxEdges {
+        top,
+        right,
+        bottom,
+        left,
+    })
+}
+
+/// Resolves a CSS `font-size` value. `px` values are absolute while `em` and
+/// `%` are relative to the parent's font size.
+pub fn parse_font_size(value: &str, parent_size: f32) -> Option<f32> {
+    let value = value.trim();
+
+    let size = if let Some(px) = value.strip_suffix("px") {
+        px.trim().parse::<f32>().ok()?
+    } else if let Some(em) = value.strip_suffix("em") {
+        em.trim().parse::<f32>().ok()? * parent_size
+    } else if let Some(percent) = value.strip_suffix('%') {
+        percent.trim().parse::<f32>().ok()? / 100.0 * parent_size
+    } else {
+        return None;
+    };
+
+    if size.is_finite() && size >= 0.0 {
+        Some(size)
+    } else {
+        None
+    }
+}
+
+/// Parses a `line-height` given as a multiple of the font size, like `1.5`,
+/// or in pixels.
+pub fn parse_line_height(value: &str) -> Option<LineHeight> {
+    let value = value.trim();
+    let line_height = match value.strip_suffix("px") {
+        Some(px) => LineHeight::Px(px.trim().parse::<f32>().ok()?),
+        None => LineHeight::Multiple(value.parse::<f32>().ok()?),
+    };
+
+    match line_height {
+        LineHeight::Multiple(n) | LineHeight::Px(n)
+            if n.is_finite() && n >= 0.0 =>
+        {
+            Some(line_height)
+        }
+        _ => None,
+    }
+}
+
+/// Parses an `opacity` value given as a number or a percentage, clamped
+/// between fully transparent and fully opaque.
+pub fn parse_opacity(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let opacity = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().ok()? / 100.0,
+        None => value.parse::<f32>().ok()?,
+    };
+
+    opacity.is_finite().then(|| opacity.clamp(0.0, 1.0))
+}
+
+/// `color` with its alpha multiplied by `opacity`.
+fn with_opacity(color: [f32; 4], opacity: f32) -> [f32; 4] {
+    let [r, g, b, a] = color;
+    [r, g, b, a * opacity]
+}
+
+pub fn parse_text_align(value: &str) -> Option<HorizontalAlign> {
+    match value.trim().to_lowercase().as_str() {
+        "left" | "start" => Some(HorizontalAlign::Left),
+        "center" => Some(HorizontalAlign::Center),
+        "right" | "end" => Some(HorizontalAlign::Right),
+        _ => None,
+    }
+}
+
+/// Parses a `text-decoration` value. Only the kind of line is used, a
+/// color or style given with it is ignored.
+pub fn parse_text_decoration(value: &str) -> Option<TextDecoration> {
+    value.split_ascii_whitespace().find_map(|word| {
+        match word.to_lowercase().as_str() {
+            "none" => Some(TextDecoration::None),
+            "underline" => Some(TextDecoration::Underline),
+            "line-through" => Some(TextDecoration::LineThrough),
+            _ => None,
+        }
+    })
+}
+
+/// Parses a `vertical-align` value. Boxes that line up next to each other
+/// share no baseline, so `baseline` lines up their tops.
+pub fn parse_vertical_align(value: &str) -> Option<VerticalAlign> {
+    match value.trim().to_lowercase().as_str() {
+        "top" | "baseline" => Some(VerticalAlign::Top),
+        "middle" => Some(VerticalAlign::Center),
+        "bottom" => Some(VerticalAlign::Bottom),
+        _ => None,
+    }
+}
+
+/// Resolves a color property value, where `inherit` takes the parent's color.
+fn resolve_color(value: &str, inherited: [f32; 4]) -> Option<[f32; 4]> {
+    if value.trim().eq_ignore_ascii_case("inherit") {
+        Some(inherited)
+    } else {
+        parse_color(value)
+    }
+}
+
+/// Parses a CSS color: `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`,
+/// `rgb(r, g, b)`, `rgba(r, g, b, a)` or one of the basic named colors.
+pub fn parse_color(value: &str) -> Option<[f32; 4]> {
+    let value = value.trim();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        return parse_hex_color(hex);
+    }
+
+    let value = value.to_ascii_lowercase();
+    let args = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+        .and_then(|args| args.strip_suffix(')'));
+    if let Some(args) = args {
+        return parse_rgb_function(args);
+    }
+
+    named_color(&value)
+}
+
+/// Parses a hex color with or without the leading `#`.
+pub fn hex_to_rgba(hex: &str) -> Option<[f32; 4]> {
+    parse_hex_color(hex.strip_prefix('#').unwrap_or(hex))
+}
+
+fn parse_hex_color(hex: &str) -> Option<[f32; 4]> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    let channels = match hex.len() {
+        // shorthand digits are doubled, `f` meaning `ff`
+        3 | 4 => hex
+            .char_indices()
+            .map(|(i, _)| digit(i).map(|d| d * 17))
+            .collect::<Option<Vec<_>>>()?,
+        6 | 8 => (0..hex.len())
+            .step_by(2)
+            .map(pair)
+            .collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+
+    let alpha = channels.get(3).copied().unwrap_or(255);
+    Some([
+        channels[0] as f32 / 255.0,
+        channels[1] as f32 / 255.0,
+        channels[2] as f32 / 255.0,
+        alpha as f32 / 255.0,
+    ])
+}
+
+/// Parses the arguments of `rgb()`/`rgba()`. Channels are `0-255` or
+/// percentages, alpha is `0-1`.
+fn parse_rgb_function(args: &str) -> Option<[f32; 4]> {
+    let parts: Vec<&str> = args.split(',').map(str::trim).collect();
+    if parts.len() != 3 && parts.len() != 4 {
+        return None;
+    }
+
+    let channel = |part: &str| -> Option<f32> {
+        let value = match part.strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f32>().ok()? / 100.0,
+            None => part.parse::<f32>().ok()? / 255.0,
+        };
+        Some(value.clamp(0.0, 1.0))
+    };
+
+    let alpha = match parts.get(3) {
+        Some(alpha) => alpha.parse::<f32>().ok()?.clamp(0.0, 1.0),
+        None => 1.0,
+    };
+
+    Some([
+        channel(parts[0])?,
+        channel(parts[1])?,
+        channel(parts[2])?,
+        alpha,
+    ])
+}
+
+fn named_color(name: &str) -> Option<[f32; 4]> {
+    let hex = match name {
+        "transparent" => return Some([0.0, 0.0, 0.0, 0.0]),
+        "black" => "000000",
+        "white" => "ffffff",
+        "red" => "ff0000",
+        "green" => "008000",
+        "lime" => "00ff00",
+        "blue" => "0000ff",
+        "yellow" => "ffff00",
+        "cyan" | "aqua" => "00ffff",
+        "magenta" | "fuchsia" => "ff00ff",
+        "gray" | "grey" => "808080",
+        "silver" => "c0c0c0",
+        "maroon" => "800000",
+        "olive" => "808000",
+        "navy" => "000080",
+        "purple" => "800080",
+        "teal" => "008080",
+        "orange" => "ffa500",
+        _ => return None,
+    };
+
+    parse_hex_color(hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use glyph_brush::FontId;
+
+    use super::*;
+    use crate::renderer::get_line_height_of_text;
+    use crate::ua_styles::{TagStyle, CODE_BG_COLOR};
+
+    #[test]
+    fn test_style_parsing() {
+        let style = "my-style: 15; my-other-style: 'hello'";
+
+        assert_eq!(parse_style(style, "my-style").unwrap(), "15");
+        assert_eq!(parse_style(style, "my-other-style").unwrap(), "'hello'");
+
+        let style2 = "background-color: #ff0000";
+
+        assert_eq!(parse_style(style2, "background-color").unwrap(), "#ff0000");
+        assert!(parse_style(style2, "color").is_none());
+
+        // trailing semicolons and colons inside of values
+        let style3 = "background: url(http://a/b.png);color:red;";
+        assert_eq!(
+            parse_style(style3, "background").unwrap(),
+            "url(http://a/b.png)"
+        );
+        assert_eq!(parse_style(style3, "COLOR").unwrap(), "red");
+    }
+
+    #[test]
+    fn test_child_position_relative_to_parent() {
+        let graph = HtmlRenderGraph::new("<h1>a</h1><div><p>b</p></div>");
+
+        let div = &graph.nodes[1];
+        assert_eq!(div.position(), (0, 1));
+
+        let p = &div.children[0];
+        assert_eq!(p.position(), (div.position().0 + 1, 2));
+    }
+
+    #[test]
+    fn test_classes() {
+        let graph = HtmlRenderGraph::new(
+            r#"<p class="a  b c">x</p><p class=" ">y</p><p>z</p>"#,
+        );
+
+        let p = &graph.nodes[0];
+        assert_eq!(p.classes(), ["a", "b", "c"]);
+        assert!(p.has_class("b"));
+        assert!(!p.has_class("a  b"));
+        assert!(!p.has_class(""));
+
+        assert!(graph.nodes[1].classes().is_empty());
+        assert!(graph.nodes[2].classes().is_empty());
+    }
+
+    #[test]
+    fn test_span_background_covers_its_text() {
+        let canvas = paint(
+            r#"<span style="background-color: yellow">word</span><span style="background-color: yellow; padding: 2px">ab</span><p style="background-color: yellow">word</p>"#,
+        );
+        let line_height = get_line_height_of_text("a", 16.0);
+
+        let rects: Vec<_> =
+            canvas.quads.iter().map(|(rect, _)| *rect).collect();
+        assert_eq!(
+            rects,
+            [
+                Rect::new(0.0, 0.0, 40.0, line_height),
+                // padding on both sides of the text, on the same line
+                Rect::new(40.0, 0.0, 24.0, line_height + 4.0),
+                // blocks still fill the width of the page
+                Rect::new(0.0, line_height + 4.0, 800.0, line_height),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_content() {
+        let graph = HtmlRenderGraph::new(
+            "<div>Hello <b>world</b></div><div><p>a</p><script>b</script><template>c</template><ul><li>d</li></ul>e</div>",
+        );
+
+        assert_eq!(graph.nodes[0].text_content(), "Hello world");
+        // list markers are not text of the page
+        assert_eq!(graph.nodes[1].text_content(), "a d e");
+
+        // inline elements do not separate words
+        let graph = HtmlRenderGraph::new(
+            "<p>un<b>believ</b>able <i>words</i></p><div><span>a</span><div>b</div></div>",
+        );
+        assert_eq!(graph.nodes[0].text_content(), "unbelievable words");
+        assert_eq!(graph.nodes[1].text_content(), "a b");
+    }
+
+    #[test]
+    fn test_text_next_to_elements_is_kept() {
+        let graph = HtmlRenderGraph::new("<div>a<br>b <i>c</i> d</div>");
+
+        let div = &graph.nodes[0];
+        assert_eq!(div.content, None);
+        let elements: Vec<_> =
+            div.children.iter().map(|child| child.element).collect();
+        assert_eq!(
+            elements,
+            [HtmlElement::Text, HtmlElement::Italic, HtmlElement::Text]
+        );
+        // the spaces around <i> separate it from its neighbours
+        assert_eq!(div.children[0].content.as_deref(), Some("a\nb "));
+        assert_eq!(div.children[2].content.as_deref(), Some(" d"));
+    }
+
+    #[test]
+    fn test_template_is_not_rendered() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html("<template><p>x</p></template><p>y</p>");
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+
+        let texts: Vec<_> =
+            canvas.texts.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(texts, ["y"]);
+        assert_eq!(
+            html_renderer.content_height(),
+            get_line_height_of_text("y", 16.0)
+        );
+
+        let graph = html_renderer.render_graph.as_ref().unwrap();
+        let template = &graph.nodes[0];
+        assert_eq!(template.element, HtmlElement::Template);
+        assert!(template.children.is_empty());
+        let content = template.template_content();
+        assert_eq!(content[0].element, HtmlElement::Paragraph);
+        assert_eq!(content[0].content.as_deref(), Some("x"));
+        // the rows after the template do not count its content
+        assert_eq!(graph.nodes[1].position(), (0, 1));
+    }
+
+    #[test]
+    fn test_code_is_monospace_on_gray() {
+        let graph = HtmlRenderGraph::new(
+            r#"<code>let x;</code><code style="background-color: #ffffff">y</code>"#,
+        );
+
+        let code = &graph.nodes[0];
+        assert_eq!(code.element, HtmlElement::Code);
+        assert_eq!(code.font_style.font_id(), FontStyle::Monospace.font_id());
+        assert_eq!(code.bg_color, CODE_BG_COLOR);
+        // the page's own background wins
+        assert_eq!(graph.nodes[1].bg_color, [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_code_is_inline() {
+        let canvas = paint("<p>Use <code>let x;</code> here</p>");
+        let line_height = get_line_height_of_text("a", 16.0);
+
+        // the code sits in the line of the text around it, its background
+        // only behind its own text
+        let (background, _) = canvas
+            .quads
+            .iter()
+            .find(|(_, color)| *color == CODE_BG_COLOR)
+            .unwrap();
+        assert_eq!(*background, Rect::new(40.0, 0.0, 60.0, line_height));
+        let positions: Vec<_> = canvas
+            .texts
+            .iter()
+            .map(|(text, config)| (text.as_str(), config.screen_pos))
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                ("Use ", (0.0, 0.0)),
+                ("let x;", (40.0, 0.0)),
+                (" here", (100.0, 0.0))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_font_id_per_element() {
+        let graph = HtmlRenderGraph::new(
+            "<h1>a</h1><p>b</p><b>c</b><strong>d</strong><i>e</i><em>f</em>\
+             <em><p>g</p></em>",
+        );
+
+        let font_ids: Vec<FontId> = graph
+            .nodes
+            .iter()
+            .map(|node| node.font_style.font_id())
+            .collect();
+        assert_eq!(
+            font_ids,
+            vec![
+                FontStyle::Bold.font_id(),
+                FontStyle::Regular.font_id(),
+                FontStyle::Bold.font_id(),
+                FontStyle::Bold.font_id(),
+                FontStyle::Italic.font_id(),
+                FontStyle::Italic.font_id(),
+                FontStyle::Italic.font_id(),
+            ]
+        );
+        assert_ne!(FontStyle::Regular.font_id(), FontStyle::Bold.font_id());
+
+        // children without a style of their own inherit the parent's
+        let nested = &graph.nodes[6].children[0];
+        assert_eq!(nested.font_style, FontStyle::Italic);
+    }
+
+    #[test]
+    fn test_anchor_href() {
+        let graph =
+            HtmlRenderGraph::new(r#"<a href="https://example.com">link</a>"#);
+
+        assert_eq!(graph.nodes[0].element, HtmlElement::Anchor);
+        assert_eq!(graph.nodes[0].href.as_deref(), Some("https://example.com"));
+        assert_eq!(graph.nodes[0].fg_color, LINK_COLOR);
+    }
+
+    #[test]
+    fn test_hit_test() {
+        let mut html_renderer = HtmlRenderer::new();
+        html_renderer.anchor_regions = vec![
+            AnchorRegion {
+                rect: Rect::new(0.0, 0.0, 800.0, 100.0),
+                href: "outer".to_string(),
+                depth: 0,
+            },
+            AnchorRegion {
+                rect: Rect::new(0.0, 20.0, 800.0, 20.0),
+                href: "inner".to_string(),
+                depth: 1,
+            },
+            AnchorRegion {
+                rect: Rect::new(0.0, 200.0, 800.0, 20.0),
+                href: "other".to_string(),
+                depth: 0,
+            },
+        ];
+
+        assert_eq!(html_renderer.hit_test(10.0, 5.0), Some("outer"));
+        assert_eq!(html_renderer.hit_test(10.0, 25.0), Some("inner"));
+        assert_eq!(html_renderer.hit_test(10.0, 210.0), Some("other"));
+        assert_eq!(html_renderer.hit_test(10.0, 150.0), None);
+        assert_eq!(html_renderer.hit_test(900.0, 5.0), None);
+    }
+
+    #[test]
+    fn test_hit_test_while_zoomed() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer
+            .load_html(r#"<p style="height: 300px">a</p><a href="link">b</a>"#);
+        let mut canvas = RecordingCanvas {
+            zoom: Some(2.0),
+            ..Default::default()
+        };
+        html_renderer.paint(&mut canvas);
+
+        // scrolled to the top at zoom 2, a point of the page shows twice as
+        // far from the top left corner of the screen
+        let p_height = get_line_height_of_text("a", 16.0);
+        assert_eq!(
+            html_renderer.hit_test(10.0, (300.0 + 1.0) * 2.0),
+            Some("link")
+        );
+        assert_eq!(html_renderer.hit_test(10.0, 300.0 * 2.0 - 1.0), None);
+        assert_eq!(
+            html_renderer.hit_test(10.0, (300.0 + p_height) * 2.0 + 1.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_update_hover() {
+        let mut html_renderer = HtmlRenderer::new();
+        html_renderer.anchor_regions = vec![
+            AnchorRegion {
+                rect: Rect::new(0.0, 0.0, 100.0, 20.0),
+                href: "a".to_string(),
+                depth: 0,
+            },
+            AnchorRegion {
+                rect: Rect::new(0.0, 20.0, 100.0, 20.0),
+                href: "b".to_string(),
+                depth: 0,
+            },
+        ];
+
+        assert!(html_renderer.update_hover(10.0, 5.0));
+        assert_eq!(html_renderer.hovered_link(), Some("a"));
+        assert!(!html_renderer.update_hover(50.0, 10.0));
+
+        // moving straight onto another link changes the target only
+        assert!(html_renderer.update_hover(10.0, 25.0));
+        assert_eq!(html_renderer.hovered_link(), Some("b"));
+
+        assert!(html_renderer.update_hover(200.0, 25.0));
+        assert_eq!(html_renderer.hovered_link(), None);
+        assert!(!html_renderer.update_hover(300.0, 25.0));
+    }
+
+    #[test]
+    fn test_hover_while_zoomed() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(r#"<a href="a">link</a>"#);
+        let mut canvas = RecordingCanvas {
+            zoom: Some(2.0),
+            ..Default::default()
+        };
+        html_renderer.paint(&mut canvas);
+
+        // the line of the link is twice as high on the screen
+        let line_height = get_line_height_of_text("a", 16.0);
+        assert!(html_renderer.update_hover(10.0, 30.0));
+        assert_eq!(html_renderer.hovered_link(), Some("a"));
+        assert!(!html_renderer.update_hover(70.0, 2.0 * line_height - 1.0));
+        assert!(html_renderer.update_hover(10.0, 2.0 * line_height + 1.0));
+        assert_eq!(html_renderer.hovered_link(), None);
+    }
+
+    #[test]
+    fn test_resolve_link() {
+        let mut html_renderer = HtmlRenderer::new();
+        html_renderer.load_html("<a href=\"b.html\">b</a>");
+        // html loaded directly has no place to resolve against
+        assert_eq!(html_renderer.resolve_link("b.html"), "b.html");
+
+        let dir = std::env::temp_dir()
+            .join(format!("browser-rs-links-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let page = dir.join("a.html");
+        fs::write(&page, "<a href=\"b.html\">b</a>").unwrap();
+        html_renderer.load_file(&page).unwrap();
+        assert_eq!(
+            html_renderer.resolve_link("b.html"),
+            dir.join("b.html").to_string_lossy()
+        );
+        assert_eq!(html_renderer.resolve_link("/about"), "/about");
+        assert_eq!(
+            html_renderer.resolve_link("https://example.com/"),
+            "https://example.com/"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        let base = "https://example.com/docs/guide/intro.html?lang=en#top";
+        let resolve = |href| resolve_href(base, href);
+        assert_eq!(
+            resolve("setup.html"),
+            "https://example.com/docs/guide/setup.html"
+        );
+        assert_eq!(resolve("../api/"), "https://example.com/docs/api/");
+        assert_eq!(resolve("./a/../b#c"), "https://example.com/docs/guide/b#c");
+        assert_eq!(resolve("/about"), "https://example.com/about");
+        assert_eq!(resolve("//cdn.example.com/x"), "https://cdn.example.com/x");
+        assert_eq!(
+            resolve("?lang=de"),
+            "https://example.com/docs/guide/intro.html?lang=de"
+        );
+        assert_eq!(
+            resolve_href("http://example.com", "a"),
+            "http://example.com/a"
+        );
+        assert_eq!(
+            resolve_href("http://example.com/a/b", ".."),
+            "http://example.com/"
+        );
+    }
+
+    #[test]
+    fn test_load_data_url() {
+        let mut html_renderer = HtmlRenderer::new();
+        html_renderer
+            .load_data_url("data:text/html,%3Cp%20id=%22a%22%3EHi%3C/p%3E")
+            .unwrap();
+        let expected = HtmlRenderGraph::new(r#"<p id="a">Hi</p>"#);
+        let graph = html_renderer.render_graph.as_ref().unwrap();
+        assert_eq!(graph.to_debug_string(), expected.to_debug_string());
+
+        let url = "data:text/html;base64,PGgxPlRpdGxlPC9oMT4=";
+        html_renderer.load_data_url(url).unwrap();
+        let graph = html_renderer.render_graph.as_ref().unwrap();
+        assert_eq!(graph.nodes[0].element, HtmlElement::H1);
+        assert_eq!(html_renderer.history().current(), Some(url));
+
+        let err = html_renderer
+            .load_data_url("data:image/png;base64,iVBORw0KGgo=")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LoadError::DataUrl(DataUrlError::UnsupportedMediaType(_))
+        ));
+    }
+
+    #[test]
+    fn test_go_back_and_forward() {
+        let dir = std::env::temp_dir()
+            .join(format!("browser-rs-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let page = |name: &str| {
+            let path = dir.join(name);
+            fs::write(&path, format!("<p id=\"{}\">{}</p>", name, name))
+                .unwrap();
+            path
+        };
+        let (a, b, c) = (page("a"), page("b"), page("c"));
+        let shown = |html_renderer: &HtmlRenderer| {
+            let graph = html_renderer.render_graph.as_ref().unwrap();
+            graph.nodes[0].id().map(str::to_string)
+        };
+
+        let mut html_renderer = HtmlRenderer::new();
+        assert!(!html_renderer.go_back().unwrap());
+        for path in [&a, &b, &c] {
+            html_renderer.load_file(path).unwrap();
+        }
+
+        assert!(html_renderer.go_back().unwrap());
+        assert!(html_renderer.go_back().unwrap());
+        assert_eq!(shown(&html_renderer).as_deref(), Some("a"));
+        assert!(!html_renderer.go_back().unwrap());
+
+        assert!(html_renderer.go_forward().unwrap());
+        assert_eq!(shown(&html_renderer).as_deref(), Some("b"));
+
+        // loading a page forgets the ones that could be gone forward to
+        html_renderer.load_file(&a).unwrap();
+        assert!(!html_renderer.go_forward().unwrap());
+        assert!(html_renderer.go_back().unwrap());
+        assert_eq!(shown(&html_renderer).as_deref(), Some("b"));
+
+        // a page that can not be loaded again keeps the history where it is
+        fs::remove_file(&a).unwrap();
+        assert!(html_renderer.go_forward().is_err());
+        assert_eq!(html_renderer.history().current(), b.to_str());
+        assert_eq!(shown(&html_renderer).as_deref(), Some("b"));
+        assert!(html_renderer.go_back().is_err());
+        assert_eq!(html_renderer.history().current(), b.to_str());
+        assert!(html_renderer.history().can_go_back());
+        assert!(html_renderer.history().can_go_forward());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_link_click() {
+        let mut html_renderer = HtmlRenderer::new();
+        html_renderer.anchor_regions = vec![AnchorRegion {
+            rect: Rect::new(0.0, 0.0, 100.0, 20.0),
+            href: "next.html".to_string(),
+            depth: 0,
+        }];
+
+        let clicked = Rc::new(RefCell::new(Vec::new()));
+        html_renderer.set_on_link_click({
+            let clicked = clicked.clone();
+            move |href| clicked.borrow_mut().push(href.to_string())
+        });
+
+        html_renderer.mouse_down(10.0, 5.0);
+        assert!(html_renderer.mouse_up(12.0, 6.0));
+        assert_eq!(*clicked.borrow(), vec!["next.html"]);
+
+        // dragging over the link selects instead, and outside of it there
+        // is nothing to follow
+        html_renderer.mouse_down(10.0, 5.0);
+        assert!(!html_renderer.mouse_up(60.0, 5.0));
+        html_renderer.mouse_down(10.0, 50.0);
+        assert!(!html_renderer.mouse_up(10.0, 50.0));
+        assert!(!html_renderer.mouse_up(10.0, 5.0));
+        assert_eq!(clicked.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_link_click_while_zoomed() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(
+            r#"<p style="height: 100px">a</p><a href="next.html">b</a>"#,
+        );
+        let mut canvas = RecordingCanvas {
+            zoom: Some(2.0),
+            ..Default::default()
+        };
+        html_renderer.paint(&mut canvas);
+
+        let clicked = Rc::new(RefCell::new(Vec::new()));
+        html_renderer.set_on_link_click({
+            let clicked = clicked.clone();
+            move |href| clicked.borrow_mut().push(href.to_string())
+        });
+
+        // the link is 100px down the page and 200px down the screen
+        html_renderer.mouse_down(10.0, 150.0);
+        assert!(!html_renderer.mouse_up(10.0, 150.0));
+        html_renderer.mouse_down(10.0, 210.0);
+        assert!(html_renderer.mouse_up(10.0, 210.0));
+        assert_eq!(*clicked.borrow(), vec!["next.html"]);
+    }
+
+    #[test]
+    fn test_tooltip_stays_on_screen() {
+        let size = (100.0, 20.0);
+        let screen = Rect::new(0.0, 0.0, 800.0, 600.0);
+
+        assert_eq!(
+            tooltip_rect((10.0, 10.0), size, screen),
+            Rect::new(22.0, 28.0, 100.0, 20.0)
+        );
+        // flipped left of the mouse at the right edge
+        assert_eq!(
+            tooltip_rect((750.0, 10.0), size, screen),
+            Rect::new(650.0, 28.0, 100.0, 20.0)
+        );
+        // and above it at the bottom
+        assert_eq!(
+            tooltip_rect((10.0, 590.0), size, screen),
+            Rect::new(22.0, 570.0, 100.0, 20.0)
+        );
+        // wider than the screen it starts at the left edge
+        assert_eq!(tooltip_rect((50.0, 10.0), (900.0, 20.0), screen).x, 0.0);
+
+        // zoomed in twice, only the middle of the canvas is on the screen
+        let zoomed = Rect::new(200.0, 150.0, 400.0, 300.0);
+        assert_eq!(
+            tooltip_rect((590.0, 440.0), size, zoomed),
+            Rect::new(490.0, 420.0, 100.0, 20.0)
+        );
+        assert_eq!(tooltip_rect((250.0, 10.0), (900.0, 20.0), zoomed).x, 200.0);
+    }
+
+    #[test]
+    fn test_hover_title() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(
+            r#"<div title="outer" style="height: 100px"><p title="inner">a</p></div>"#,
+        );
+        html_renderer.paint(&mut RecordingCanvas::default());
+
+        html_renderer.update_hover(10.0, 5.0);
+        assert_eq!(html_renderer.hovered_title(), Some("inner"));
+        let due = html_renderer.tooltip_due().unwrap();
+
+        // moving inside the same element does not restart the delay
+        html_renderer.update_hover(20.0, 6.0);
+        assert_eq!(html_renderer.tooltip_due(), Some(due));
+
+        html_renderer.update_hover(10.0, 80.0);
+        assert_eq!(html_renderer.hovered_title(), Some("outer"));
+        html_renderer.update_hover(10.0, 300.0);
+        assert_eq!(html_renderer.hovered_title(), None);
+        assert_eq!(html_renderer.tooltip_due(), None);
+    }
+
+    #[test]
+    fn test_tooltip_while_zoomed() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer
+            .load_html(r#"<div title="title" style="height: 300px"></div>"#);
+        let zoomed = || RecordingCanvas {
+            zoom: Some(2.0),
+            ..Default::default()
+        };
+        html_renderer.paint(&mut zoomed());
+
+        // the div covers the whole screen, the tooltip near its bottom
+        // right corner goes above and left of the mouse
+        html_renderer.update_hover(790.0, 590.0);
+        assert_eq!(html_renderer.hovered_title(), Some("title"));
+        html_renderer.tooltip.as_mut().unwrap().since -= TOOLTIP_DELAY;
+        let mut canvas = zoomed();
+        html_renderer.paint(&mut canvas);
+
+        let (rect, _) = canvas
+            .quads
+            .iter()
+            .find(|(_, color)| *color == TOOLTIP_BG_COLOR)
+            .unwrap();
+        let mouse = (595.0, 445.0);
+        assert_eq!(rect.x + rect.width, mouse.0);
+        assert_eq!(rect.y + rect.height, mouse.1);
+    }
+
+    #[test]
+    fn test_ordered_list_markers() {
+        let graph = HtmlRenderGraph::new(
+            "<ol><li>a</li><li>b</li><li>c</li></ol><ul><li>d</li></ul>",
+        );
+
+        let markers: Vec<_> = graph.nodes[0]
+            .children
+            .iter()
+            .map(|item| item.list_marker.as_deref())
+            .collect();
+        assert_eq!(markers, vec![Some("1. "), Some("2. "), Some("3. ")]);
+
+        assert_eq!(
+            graph.nodes[1].children[0].list_marker.as_deref(),
+            Some("• ")
+        );
+    }
+
+    #[test]
+    fn test_nested_list_markers() {
+        let graph = HtmlRenderGraph::new(
+            "<ol><li>a</li><li><ol><li>x</li><li>y</li></ol></li><li>b</li></ol>",
+        );
+
+        let outer = &graph.nodes[0].children;
+        assert_eq!(outer[2].list_marker.as_deref(), Some("3. "));
+
+        let inner = &outer[1].children[0].children;
+        assert_eq!(inner[0].list_marker.as_deref(), Some("1. "));
+        assert_eq!(inner[1].list_marker.as_deref(), Some("2. "));
+    }
+
+    #[test]
+    fn test_load_file_decodes_lossily() {
+        let path = std::env::temp_dir().join("browser-rs-load-file.html");
+        fs::write(&path, b"<p>caf\xe9</p>").unwrap();
+
+        let mut html_renderer = HtmlRenderer::new();
+        html_renderer.load_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let nodes = &html_renderer.render_graph.as_ref().unwrap().nodes;
+        assert_eq!(nodes[0].content.as_deref(), Some("caf\u{fffd}"));
+
+        assert!(html_renderer.load_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_parse_line_height() {
+        assert_eq!(parse_line_height("1.5"), Some(LineHeight::Multiple(1.5)));
+        assert_eq!(parse_line_height(" 24px "), Some(LineHeight::Px(24.0)));
+        assert_eq!(parse_line_height("normal"), None);
+        assert_eq!(parse_line_height("-1"), None);
+
+        assert_eq!(LineHeight::Multiple(1.5).resolve(20.0), 30.0);
+        assert_eq!(LineHeight::Px(24.0).resolve(20.0), 24.0);
+        assert_eq!(LineHeight::default().resolve(16.0), 32.0);
+    }
+
+    #[test]
+    fn test_parse_text_decoration() {
+        assert_eq!(
+            parse_text_decoration("underline"),
+            Some(TextDecoration::Underline)
+        );
+        assert_eq!(
+            parse_text_decoration("red Line-Through wavy"),
+            Some(TextDecoration::LineThrough)
+        );
+        assert_eq!(parse_text_decoration("none"), Some(TextDecoration::None));
+        assert_eq!(parse_text_decoration("overline"), None);
+
+        let graph = HtmlRenderGraph::new(
+            r#"<a href="x">a<b>b</b></a><a style="text-decoration: none">c</a><u>d</u>"#,
+        );
+        assert_eq!(graph.nodes[0].text_decoration, TextDecoration::Underline);
+        assert_eq!(
+            graph.nodes[0].children[0].text_decoration,
+            TextDecoration::Underline
+        );
+        assert_eq!(graph.nodes[1].text_decoration, TextDecoration::None);
+        assert_eq!(graph.nodes[2].text_decoration, TextDecoration::Underline);
+    }
+
+    #[test]
+    fn test_text_decoration_per_line() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(
+            r#"<pre style="text-decoration: underline">one
+three  </pre><p style="text-decoration: line-through">x</p><p>y</p>"#,
+        );
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+
+        // the recording canvas puts characters 10px apart in lines as high
+        // as the font size, trailing whitespace is not underlined
+        let rects: Vec<_> =
+            canvas.quads.iter().map(|(rect, _)| *rect).collect();
+        let line_height = get_line_height_of_text("x", 16.0);
+        let p_top = 2.0 * line_height;
+        assert_eq!(
+            rects,
+            [
+                Rect::new(0.0, 16.0 * UNDERLINE_POSITION, 30.0, 1.0),
+                Rect::new(0.0, 16.0 + 16.0 * UNDERLINE_POSITION, 50.0, 1.0),
+                Rect::new(0.0, p_top + 16.0 * LINE_THROUGH_POSITION, 10.0, 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_font_size() {
+        assert_eq!(parse_font_size("16px", 10.0), Some(16.0));
+        assert_eq!(parse_font_size("2em", 16.0), Some(32.0));
+        assert_eq!(parse_font_size("50%", 16.0), Some(8.0));
+        assert_eq!(parse_font_size("1.5 em", 20.0), Some(30.0));
+        assert_eq!(parse_font_size("large", 16.0), None);
+        assert_eq!(parse_font_size("-2px", 16.0), None);
+    }
+
+    #[test]
+    fn test_small_headings() {
+        let graph = HtmlRenderGraph::new(
+            r#"<h4>a</h4><h5>b</h5><h6>c</h6><h6 style="font-size: 20px">d</h6>"#,
+        );
+        let nodes = &graph.nodes;
+
+        let elements: Vec<_> = nodes.iter().map(|node| node.element).collect();
+        assert_eq!(
+            elements,
+            vec![
+                HtmlElement::H4,
+                HtmlElement::H5,
+                HtmlElement::H6,
+                HtmlElement::H6
+            ]
+        );
+        assert_eq!(nodes[0].font_size, 18.0);
+        assert_eq!(nodes[1].font_size, 14.0);
+        assert_eq!(nodes[2].font_size, 13.0);
+        assert!(nodes[..3].iter().all(|h| h.font_style == FontStyle::Bold));
+        assert_eq!(nodes[3].font_size, 20.0);
+    }
+
+    #[test]
+    fn test_override_ua_styles() {
+        let mut ua_styles = UserAgentStyleSheet::default();
+        ua_styles.set(
+            HtmlElement::H1,
+            TagStyle {
+                font_scale: Some(3.0),
+                ..ua_styles.get(HtmlElement::H1)
+            },
+        );
+        ua_styles.set(
+            HtmlElement::Paragraph,
+            TagStyle {
+                margin: parse_box_edges("8px 0"),
+                ..Default::default()
+            },
+        );
+
+        let mut html_renderer = HtmlRenderer::new();
+        html_renderer
+            .load_html(r#"<h1>a</h1><p>b</p><p style="margin: 0">c</p>"#);
+        let h1 = &html_renderer.render_graph.as_ref().unwrap().nodes[0];
+        assert_eq!(h1.font_size, 2.0 * DEFAULT_FONT_SIZE);
+
+        html_renderer.set_ua_styles(ua_styles);
+        assert!(html_renderer.needs_redraw());
+
+        let nodes = &html_renderer.render_graph.as_ref().unwrap().nodes;
+        assert_eq!(nodes[0].font_size, 3.0 * DEFAULT_FONT_SIZE);
+        assert_eq!(nodes[0].font_style, FontStyle::Bold);
+        assert_eq!(nodes[1].margin.top, Length::Px(8.0));
+        // the page's own styles still win
+        assert_eq!(nodes[2].margin.top, Length::Px(0.0));
+    }
+
+    #[test]
+    fn test_nested_relative_font_sizes() {
+        let graph = HtmlRenderGraph::new(
+            r#"<div style="font-size: 10px"><div style="font-size: 2em"><div style="font-size: 150%"><p>a</p><h1>b</h1></div></div></div>"#,
+        );
+
+        let outer = &graph.nodes[0];
+        let middle = &outer.children[0];
+        let inner = &middle.children[0];
+        assert_eq!(outer.font_size, 10.0);
+        assert_eq!(middle.font_size, 20.0);
+        assert_eq!(inner.font_size, 30.0);
+
+        // paragraphs inherit and headings scale the inherited size
+        assert_eq!(inner.children[0].font_size, 30.0);
+        assert_eq!(inner.children[1].font_size, 60.0);
+    }
+
+    #[test]
+    fn test_font_size_from_inline_style() {
+        let graph = HtmlRenderGraph::new(
+            r#"<div style="font-size: 20px"><p style="font-size: 2em">a</p><b>b</b></div>
+            <h1 style="font-size: 50%">c</h1>"#,
+        );
+
+        let div = &graph.nodes[0];
+        assert_eq!(div.font_size, 20.0);
+        assert_eq!(div.children[0].font_size, 40.0);
+        // elements without a size of their own inherit it
+        assert_eq!(div.children[1].font_size, 20.0);
+
+        // relative sizes resolve against the parent, not the tag default
+        assert_eq!(graph.nodes[1].font_size, DEFAULT_FONT_SIZE / 2.0);
+    }
+
+    #[test]
+    fn test_text_align() {
+        let graph = HtmlRenderGraph::new(
+            r#"<p style="text-align: center">a</p><p style="text-align:right">b</p>
+            <p style="text-align: justify">c</p>"#,
+        );
+
+        assert_eq!(graph.nodes[0].layout.h_align, HorizontalAlign::Center);
+        assert_eq!(graph.nodes[1].layout.h_align, HorizontalAlign::Right);
+        assert_eq!(graph.nodes[2].layout.h_align, HorizontalAlign::Left);
+    }
+
+    /// Records draw calls instead of sending them to the GPU.
+    #[derive(Default)]
+    struct RecordingCanvas {
+        quads: Vec<(Rect, [f32; 4])>,
+        // radius of every quad drawn with rounded corners, which are also
+        // in `quads`
+        corner_radii: Vec<f32>,
+        texts: Vec<(String, TextDrawConfig)>,
+        zoom: Option<f32>,
+        clear_color: Option<[f32; 4]>,
+    }
+
+    impl Canvas for RecordingCanvas {
+        fn screen_size(&self) -> (u32, u32) {
+            (800, 600)
+        }
+
+        fn set_clear_color(&mut self, color: [f32; 4]) {
+            self.clear_color = Some(color);
+        }
+
+        fn zoom(&self) -> f32 {
+            self.zoom.unwrap_or(1.0)
+        }
+
+        fn draw_quad(
+            &mut self,
+            screen_pos: (f32, f32),
+            bounds: (f32, f32),
+            color: [f32; 4],
+        ) {
+            self.quads.push((
+                Rect::new(screen_pos.0, screen_pos.1, bounds.0, bounds.1),
+                color,
+            ));
+        }
+
+        fn draw_text(&mut self, text: &str, _size: f32, cfg: TextDrawConfig) {
+            self.texts.push((text.to_string(), cfg));
+        }
+
+        fn draw_rounded_quad(
+            &mut self,
+            screen_pos: (f32, f32),
+            bounds: (f32, f32),
+            radius: f32,
+            color: [f32; 4],
+        ) {
+            self.corner_radii.push(radius);
+            self.draw_quad(screen_pos, bounds, color);
+        }
+
+        /// Lays text out in a monospace font, 10px wide and as high as its
+        /// size, without wrapping.
+        fn glyph_boxes(
+            &self,
+            text: &str,
+            size: f32,
+            cfg: &TextDrawConfig,
+        ) -> Vec<GlyphBox> {
+            let (x, y) = cfg.screen_pos;
+            text.lines()
+                .enumerate()
+                .flat_map(|(line, chars)| {
+                    chars.chars().enumerate().map(move |(column, ch)| {
+                        GlyphBox {
+                            rect: Rect::new(
+                                x + column as f32 * 10.0,
+                                y + line as f32 * size,
+                                10.0,
+                                size,
+                            ),
+                            ch,
+                        }
+                    })
+                })
+                .collect()
+        }
+
+        /// Measures text like `glyph_boxes` lays it out.
+        fn measure_text(
+            &self,
+            text: &str,
+            _size: f32,
+            _style: FontStyle,
+            _max_width: Option<f32>,
+            line_height: f32,
+        ) -> (f32, f32) {
+            if text.is_empty() {
+                return (0.0, 0.0);
+            }
+
+            let lines = text.split('\n');
+            let columns = lines.clone().map(|line| line.chars().count());
+            let width = columns.max().unwrap_or(0) as f32 * 10.0;
+            (width, lines.count() as f32 * line_height)
+        }
+    }
+
+    /// A renderer that puts the page right at the top left corner, so
+    /// positions on the page and on the screen are the same.
+    fn renderer_without_margin() -> HtmlRenderer {
+        let mut html_renderer = HtmlRenderer::new();
+        html_renderer.set_page_margin(0.0);
+        html_renderer
+    }
+
+    fn paint(html: &str) -> RecordingCanvas {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(html);
+
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+        canvas
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[ignore = "needs an OpenGL driver"]
+    fn test_render_to_buffer() {
+        let display = crate::renderer::tests::headless_display();
+        let mut renderer = Renderer::new(&display);
+
+        let mut html_renderer = HtmlRenderer::new();
+        html_renderer.load_html(
+            r#"<div style="background-color: #ff0000; width: 50px; height: 20px"></div>"#,
+        );
+        let (width, height) = (100, 60);
+        let pixels =
+            html_renderer.render_to_buffer(&mut renderer, (width, height));
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+
+        let pixel = |x: u32, y: u32| {
+            let offset = ((y * width + x) * 4) as usize;
+            &pixels[offset..offset + 4]
+        };
+        // the center of the div and the white page below it
+        assert_eq!(pixel(25, 10), [255, 0, 0, 255]);
+        assert_eq!(pixel(25, 40), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_body_background_sets_clear_color() {
+        let canvas = paint(r#"<body style="background-color:#000"></body>"#);
+        assert_eq!(canvas.clear_color, Some([0.0, 0.0, 0.0, 1.0]));
+
+        // the background of <html> wins over the one of <body>
+        let canvas = paint(
+            r#"<html style="background-color: #fff"><body style="background-color: #000"></body></html>"#,
+        );
+        assert_eq!(canvas.clear_color, Some([1.0, 1.0, 1.0, 1.0]));
+
+        let canvas = paint("<body><p>a</p></body>");
+        assert_eq!(canvas.clear_color, Some(DEFAULT_CLEAR_COLOR));
+    }
+
+    #[test]
+    fn test_selected_text() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html("<p>Hello world</p><p>Second</p>");
+        html_renderer.paint(&mut RecordingCanvas::default());
+        assert_eq!(html_renderer.selected_text(), None);
+
+        // from in front of "w" to in front of the "o" of the second line
+        let p_height = get_line_height_of_text("a", 16.0);
+        html_renderer.start_selection(62.0, 5.0);
+        assert_eq!(html_renderer.selected_text(), None);
+        html_renderer.extend_selection(32.0, p_height + 5.0);
+        assert_eq!(
+            html_renderer.selected_text().as_deref(),
+            Some("world\nSec")
+        );
+
+        // the highlight covers the selected part of both lines
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+        let highlights: Vec<_> = canvas
+            .quads
+            .iter()
+            .filter(|(_, color)| *color == SELECTION_COLOR)
+            .map(|(rect, _)| *rect)
+            .collect();
+        assert_eq!(
+            highlights,
+            vec![
+                Rect::new(60.0, 0.0, 50.0, 16.0),
+                Rect::new(0.0, p_height, 30.0, 16.0),
+            ]
+        );
+
+        html_renderer.clear_selection();
+        assert_eq!(html_renderer.selected_text(), None);
+    }
+
+    #[test]
+    fn test_find_counts_matches_across_text_nodes() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(
+            "<p>The cat sat</p><p>Concatenate CATS</p><div><p>scat</p></div>",
+        );
+        html_renderer.paint(&mut RecordingCanvas::default());
+
+        assert_eq!(html_renderer.find("cat"), 4);
+        assert_eq!(html_renderer.find_matches()[0], 4..7);
+        assert_eq!(html_renderer.current_find_match(), Some(0));
+        // a match can not join the text of two elements
+        assert_eq!(html_renderer.find("satcon"), 0);
+        assert_eq!(html_renderer.find("sat con"), 0);
+
+        assert_eq!(html_renderer.find("CAT"), 4);
+        assert_eq!(html_renderer.find_next(), Some(1));
+        assert_eq!(html_renderer.find_previous(), Some(0));
+        assert_eq!(html_renderer.find_previous(), Some(3));
+        assert_eq!(html_renderer.find_next(), Some(0));
+
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+        let highlights =
+            |color| canvas.quads.iter().filter(|(_, c)| *c == color).count();
+        assert_eq!(highlights(CURRENT_FIND_MATCH_COLOR), 1);
+        assert_eq!(highlights(FIND_MATCH_COLOR), 3);
+
+        assert_eq!(html_renderer.find(""), 0);
+        assert_eq!(html_renderer.find_next(), None);
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+        assert!(canvas
+            .quads
+            .iter()
+            .all(|(_, color)| *color != FIND_MATCH_COLOR));
+    }
+
+    #[test]
+    fn test_find_status() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html("<p>cat cat</p>");
+        html_renderer.paint(&mut RecordingCanvas::default());
+        assert_eq!(html_renderer.find_status(), None);
+
+        html_renderer.find("dog");
+        assert!(html_renderer.needs_redraw());
+        assert_eq!(
+            html_renderer.find_status().as_deref(),
+            Some("No matches for \"dog\"")
+        );
+
+        html_renderer.find("cat");
+        html_renderer.find_next();
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+        // drawn in the bottom right corner of the screen
+        let status = "2 of 2 matches";
+        let (_, config) = canvas
+            .texts
+            .iter()
+            .find(|(text, _)| text == status)
+            .unwrap();
+        let line_height = TOOLTIP_FONT_SIZE * DEFAULT_LINE_HEIGHT;
+        assert_eq!(
+            config.screen_pos,
+            (
+                800.0 - FIND_STATUS_MARGIN - TOOLTIP_PADDING - 140.0,
+                600.0 - FIND_STATUS_MARGIN - TOOLTIP_PADDING - line_height,
+            )
+        );
+
+        html_renderer.clear_find();
+        assert!(html_renderer.needs_redraw());
+        assert_eq!(html_renderer.find_status(), None);
+    }
+
+    #[test]
+    fn test_find_scrolls_to_the_match() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(
+            r#"<p>needle</p><p style="height: 2000px">hay</p><p>needle</p>
+            <p style="height: 2000px">hay</p>"#,
+        );
+        html_renderer.paint(&mut RecordingCanvas::default());
+
+        // the first match is already in view
+        assert_eq!(html_renderer.find("needle"), 2);
+        assert_eq!(html_renderer.scroll_y(), 0.0);
+
+        // the second one is centered in the 600px high viewport
+        let second = html_renderer.find_matches()[1].start;
+        let top = html_renderer.glyphs[second].rect.y;
+        html_renderer.find_next();
+        assert_eq!(html_renderer.scroll_y(), top + (16.0 - 600.0) / 2.0);
+        assert!(html_renderer.needs_redraw());
+
+        // the match is found where it was painted, not where it is now
+        html_renderer.paint(&mut RecordingCanvas::default());
+        html_renderer.find_next();
+        assert_eq!(html_renderer.scroll_y(), 0.0);
+    }
+
+    #[test]
+    fn test_select_while_zoomed() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html("<p>Hello world</p>");
+        let mut canvas = RecordingCanvas {
+            zoom: Some(2.0),
+            ..Default::default()
+        };
+        html_renderer.paint(&mut canvas);
+
+        // glyphs are 10px wide on the page and 20px on the screen
+        html_renderer.start_selection(122.0, 10.0);
+        html_renderer.extend_selection(218.0, 10.0);
+        assert_eq!(html_renderer.selected_text().as_deref(), Some("world"));
+    }
+
+    #[test]
+    fn test_redraw_after_load_and_scroll() {
+        let mut html_renderer = renderer_without_margin();
+        assert!(!html_renderer.needs_redraw());
+
+        html_renderer.load_html(r#"<p style="height: 1000px">a</p>"#);
+        assert!(html_renderer.needs_redraw());
+
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+        assert!(!html_renderer.needs_redraw());
+
+        // already at the top, nothing moves
+        html_renderer.scroll_by(-10.0);
+        assert!(!html_renderer.needs_redraw());
+
+        html_renderer.scroll_by(10.0);
+        assert!(html_renderer.needs_redraw());
+        assert_eq!(html_renderer.scroll_y(), 10.0);
+
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+        assert!(!html_renderer.needs_redraw());
+        assert_eq!(canvas.texts[0].1.screen_pos, (0.0, -10.0));
+
+        // stops at the bottom of the page
+        html_renderer.scroll_by(1000.0);
+        assert_eq!(html_renderer.scroll_y(), 400.0);
+    }
+
+    #[test]
+    fn test_scroll_to_element() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(
+            r#"<div style="height: 500px"></div><p id="middle">a</p><div style="height: 1000px"></div><p id="last">b</p>"#,
+        );
+        html_renderer.paint(&mut RecordingCanvas::default());
+
+        assert!(html_renderer.scroll_to_element("middle"));
+        assert_eq!(html_renderer.scroll_y(), 500.0);
+        assert!(html_renderer.needs_redraw());
+
+        // the last element can not be scrolled to the top of the viewport
+        assert!(html_renderer.scroll_to_element("last"));
+        let content_height = html_renderer.content_height();
+        assert_eq!(html_renderer.scroll_y(), content_height - 600.0);
+
+        assert!(!html_renderer.scroll_to_element("missing"));
+        assert_eq!(html_renderer.scroll_y(), content_height - 600.0);
+    }
+
+    #[test]
+    fn test_load_file_scrolls_to_fragment() {
+        let path = std::env::temp_dir().join("browser-rs-fragment.html");
+        fs::write(
+            &path,
+            r#"<div style="height: 700px"></div><p id="intro">a</p><div style="height: 700px"></div>"#,
+        )
+        .unwrap();
+
+        let mut html_renderer = renderer_without_margin();
+        let with_fragment = format!("{}#intro", path.display());
+        html_renderer.load_file(Path::new(&with_fragment)).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // the element is scrolled to once the page is laid out
+        assert_eq!(html_renderer.scroll_y(), 0.0);
+        html_renderer.paint(&mut RecordingCanvas::default());
+        assert_eq!(html_renderer.scroll_y(), 700.0);
+    }
+
+    #[test]
+    fn test_scroll_wide_page_sideways() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(r#"<pre style="width: 1200px">a</pre>"#);
+        html_renderer.paint(&mut RecordingCanvas::default());
+        assert_eq!(html_renderer.content_width(), 1200.0);
+
+        html_renderer.scroll_x_by(150.0);
+        assert!(html_renderer.needs_redraw());
+        assert_eq!(html_renderer.scroll_x(), 150.0);
+
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+        assert_eq!(canvas.texts[0].1.screen_pos, (-150.0, 0.0));
+
+        // stops with the right edge of the page at the right of the viewport
+        html_renderer.scroll_x_by(1000.0);
+        assert_eq!(html_renderer.scroll_x(), 400.0);
+        html_renderer.scroll_x_by(-1000.0);
+        assert_eq!(html_renderer.scroll_x(), 0.0);
+
+        // a page as wide as the viewport does not scroll sideways
+        html_renderer.load_html("<p>a</p>");
+        html_renderer.paint(&mut RecordingCanvas::default());
+        html_renderer.scroll_x_by(10.0);
+        assert_eq!(html_renderer.scroll_x(), 0.0);
+    }
+
+    #[test]
+    fn test_reload_keeps_scroll_position() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(r#"<p style="height: 1000px">a</p>"#);
+        html_renderer.paint(&mut RecordingCanvas::default());
+        html_renderer.scroll_by(300.0);
+
+        html_renderer.reload();
+        assert!(html_renderer.needs_redraw());
+        html_renderer.paint(&mut RecordingCanvas::default());
+        assert_eq!(html_renderer.scroll_y(), 300.0);
+
+        // the shorter page can only be scrolled to its new bottom
+        html_renderer.reload_html(r#"<p style="height: 700px">a</p>"#);
+        html_renderer.paint(&mut RecordingCanvas::default());
+        assert_eq!(html_renderer.scroll_y(), 100.0);
+    }
+
+    #[test]
+    fn test_tab_lifecycle() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html("<p>first</p>");
+        let first = html_renderer.active_tab();
+
+        // new tabs open behind the one shown
+        let second = html_renderer.new_tab("<p>second</p>");
+        let third = html_renderer.new_tab("<p>third</p>");
+        assert_eq!(html_renderer.tabs(), [first, second, third]);
+        assert_eq!(html_renderer.active_tab(), first);
+
+        let shown = |html_renderer: &mut HtmlRenderer| {
+            let mut canvas = RecordingCanvas::default();
+            html_renderer.paint(&mut canvas);
+            canvas.texts[0].0.clone()
+        };
+        assert_eq!(shown(&mut html_renderer), "first");
+
+        assert!(html_renderer.switch_tab(second));
+        assert!(html_renderer.needs_redraw());
+        assert_eq!(shown(&mut html_renderer), "second");
+
+        // closing the active tab shows the next one, or the one before it
+        // when there is none
+        assert!(html_renderer.close_tab(second));
+        assert_eq!(html_renderer.active_tab(), third);
+        assert_eq!(shown(&mut html_renderer), "third");
+        assert!(html_renderer.close_tab(third));
+        assert_eq!(html_renderer.active_tab(), first);
+        assert_eq!(shown(&mut html_renderer), "first");
+
+        assert!(!html_renderer.switch_tab(second));
+        assert!(!html_renderer.close_tab(second));
+        assert!(!html_renderer.close_tab(first));
+        assert_eq!(html_renderer.tabs(), [first]);
+    }
+
+    #[test]
+    fn test_tabs_keep_their_scroll_position() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(r#"<p style="height: 1000px">a</p>"#);
+        let first = html_renderer.active_tab();
+        html_renderer.paint(&mut RecordingCanvas::default());
+        html_renderer.scroll_by(300.0);
+
+        let second =
+            html_renderer.new_tab(r#"<p style="height: 1000px">b</p>"#);
+        html_renderer.switch_tab(second);
+        html_renderer.paint(&mut RecordingCanvas::default());
+        assert_eq!(html_renderer.scroll_y(), 0.0);
+        html_renderer.scroll_by(100.0);
+
+        html_renderer.switch_tab(first);
+        html_renderer.paint(&mut RecordingCanvas::default());
+        assert_eq!(html_renderer.scroll_y(), 300.0);
+
+        html_renderer.switch_tab(second);
+        html_renderer.paint(&mut RecordingCanvas::default());
+        assert_eq!(html_renderer.scroll_y(), 100.0);
+    }
+
+    #[test]
+    fn test_page_margin() {
+        let mut html_renderer = HtmlRenderer::new();
+        assert_eq!(html_renderer.page_margin(), DEFAULT_PAGE_MARGIN);
+        html_renderer.load_html(r#"<p style="background-color: red">a</p>"#);
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+
+        assert_eq!(canvas.quads[0].0, Rect::new(8.0, 8.0, 784.0, 32.0));
+        assert_eq!(canvas.texts[0].1.screen_pos, (8.0, 8.0));
+        assert_eq!(html_renderer.content_height(), 32.0 + 16.0);
+        // the margins do not make the page wider than the screen
+        assert_eq!(html_renderer.content_width(), 800.0);
+
+        html_renderer.set_page_margin(20.0);
+        assert!(html_renderer.needs_redraw());
+        let mut canvas = RecordingCanvas::default();
+        html_renderer.paint(&mut canvas);
+        assert_eq!(canvas.texts[0].1.screen_pos, (20.0, 20.0));
+    }
+
+    #[test]
+    fn test_content_height() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html("<p>a</p><p>b</p><p>c</p>");
+        assert_eq!(html_renderer.content_height(), 0.0);
+
+        html_renderer.paint(&mut RecordingCanvas::default());
+        assert_eq!(
+            html_renderer.content_height(),
+            3.0 * get_line_height_of_text("a", 16.0)
+        );
+
+        // a new page is not laid out yet
+        html_renderer.load_html("<p>a</p>");
+        assert_eq!(html_renderer.content_height(), 0.0);
+    }
+
+    #[test]
+    fn test_zoom_keeps_scroll_position_at_top() {
+        let mut html_renderer = renderer_without_margin();
+        html_renderer.load_html(r#"<p style="height: 1000px">a</p>"#);
+        html_renderer.paint(&mut RecordingCanvas::default());
+        html_renderer.scroll_by(100.0);
+
+        let mut canvas = RecordingCanvas {
+            zoom: Some(2.0),
+            ..Default::default()
+        };
+        html_renderer.paint(&mut canvas);
+        assert_eq!(html_renderer.viewport_height(), 300.0);
+
+        // the visible part of the screen starts 200px right and 150px down,
+        // the scrolled to line is drawn there
+        assert_eq!(canvas.texts[0].1.screen_pos, (200.0, 50.0));
+
+        // less of the page fits, so it scrolls further
+        html_renderer.scroll_by(1000.0);
+        assert_eq!(html_renderer.scroll_y(), 700.0);
+    }
+
+    #[test]
+    fn test_background_of_empty_element() {
+        let canvas = paint(
+            r#"<div style="background-color: #ff0000; height: 40px"></div>"#,
+        );
+
+        assert_eq!(
+            canvas.quads,
+            vec![(Rect::new(0.0, 0.0, 800.0, 40.0), [1.0, 0.0, 0.0, 1.0])]
+        );
+        assert!(canvas.texts.is_empty());
+    }
+
+    #[test]
+    fn test_parse_length() {
+        assert_eq!(parse_length("200px"), Some(Length::Px(200.0)));
+        assert_eq!(parse_length(" 50% "), Some(Length::Percent(50.0)));
+        assert_eq!(parse_length("-5%"), None);
+        assert_eq!(parse_length("auto"), Some(Length::Auto));
+        assert_eq!(parse_length("wide"), None);
+        assert_eq!(parse_length("50vw"), Some(Length::Vw(50.0)));
+        assert_eq!(parse_length("10 vh"), Some(Length::Vh(10.0)));
+        assert_eq!(parse_length("-1vh"), None);
+
+        let ctx = |containing| LengthContext {
+            viewport: (1000.0, 800.0),
+            containing,
+        };
+        assert_eq!(
+            Length::Percent(50.0).resolve(ctx(Some(600.0))),
+            Some(300.0)
+        );
+        assert_eq!(Length::Percent(50.0).resolve(ctx(None)), None);
+        assert_eq!(Length::Px(10.0).resolve(ctx(None)), Some(10.0));
+        assert_eq!(Length::Vw(50.0).resolve(ctx(None)), Some(500.0));
+        assert_eq!(Length::Vh(10.0).resolve(ctx(None)), Some(80.0));
+        assert_eq!(Length::Auto.resolve(ctx(Some(600.0))), None);
+    }
+
+    #[test]
+    fn test_parse_box_edges() {
+        let px = Length::Px;
+
+        assert_eq!(
+            parse_box_edges("10px"),
+            Some(BoxEdges {
+                top: px(10.0),
+                right: px(10.0),
+                bottom: px(10.0),
+                left: px(10.0),
+            })
+        );
+        assert_eq!(
+            parse_box_edges("10px 5px 10px 5px"),
+            Some(BoxEdges {
+                top: px(10.0),
+                right: px(5.0),
+                bottom: px(10.0),
+                left: px(5.0),
+            })
+        );
+        assert_eq!(
+            parse_box_edges("0 auto"),
+            Some(BoxEdges {
+                top: px(0.0),
+                right: Length::Auto,
+                bottom: px(0.0),
+                left: Length::Auto,
+            })
+        );
+        assert_eq!(parse_box_edges(""), None);
+        assert_eq!(parse_box_edges("1px 2px 3px 4px 5px"), None);
+    }
+
+    #[test]
+    fn test_box_width_and_height() {
+        let canvas = paint(
+            r#"<div style="background-color: #ff0000; width: 50%; height: 100px"><div style="background-color: #00ff00; width: 50%; height: 50%"></div></div>"#,
+        );
+
+        assert_eq!(
+            canvas.quads,
+            vec![
+                (Rect::new(0.0, 0.0, 400.0, 100.0), [1.0, 0.0, 0.0, 1.0]),
+                (Rect::new(0.0, 0.0, 200.0, 50.0), [0.0, 1.0, 0.0, 1.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_background_covers_children() {
+        let canvas = paint(
+            r#"<div style="background-color: #0000ff"><p>a</p><p>b</p></div><p>c</p>"#,
+        );
+
+        let p_height = get_line_height_of_text("a", 16.0);
+        assert_eq!(canvas.quads.len(), 1);
+        assert_eq!(
+            canvas.quads[0].0,
+            Rect::new(0.0, 0.0, 800.0, 2.0 * p_height)
+        );
+
+        // text itself no longer draws its own background
+        assert!(canvas.texts.iter().all(|(_, cfg)| cfg.bg_color[3] == 0.0));
+        assert_eq!(canvas.texts[2].1.screen_pos, (0.0, 2.0 * p_height));
+    }
+
+    #[test]
+    fn test_inherit_fg_color() {
+        let graph = HtmlRenderGraph::new(
+            r#"<div style="color: #ff0000"><p>hi</p><p style="color: #0000ff">a</p>
+            <a href="x">link</a></div>"#,
+        );
+
+        let div = &graph.nodes[0];
+        assert_eq!(div.children[0].fg_color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(div.children[1].fg_color, [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(div.children[2].fg_color, LINK_COLOR);
+    }
+
+    #[test]
+    fn test_inherit_bg_color_only_when_asked() {
+        let graph = HtmlRenderGraph::new(
+            r#"<div style="background-color: #00ff00"><p>a</p>
+            <p style="background-color: inherit">b</p></div>"#,
+        );
+
+        let div = &graph.nodes[0];
+        assert_eq!(div.children[0].bg_color, [0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(div.children[1].bg_color, [0.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_opacity_fades_element_and_children() {
+        let graph = HtmlRenderGraph::new(
+            r#"<div style="opacity: 0.5; background-color: #00ff00; color: #ff0000">
+            <p>a</p><p style="opacity: 50%; border: 1px solid #0000ff">b</p></div>"#,
+        );
+
+        let div = &graph.nodes[0];
+        assert_eq!(div.bg_color, [0.0, 1.0, 0.0, 0.5]);
+        assert_eq!(div.children[0].fg_color, [1.0, 0.0, 0.0, 0.5]);
+
+        // nested opacities multiply
+        let faded = &div.children[1];
+        assert_eq!(faded.fg_color, [1.0, 0.0, 0.0, 0.25]);
+        assert_eq!(faded.borders.top.color, [0.0, 0.0, 1.0, 0.25]);
+
+        assert_eq!(parse_opacity("2"), Some(1.0));
+        assert_eq!(parse_opacity("-1"), Some(0.0));
+        assert_eq!(parse_opacity("half"), None);
+    }
+
+    #[test]
+    fn test_horizontal_rule() {
+        let canvas = paint("<hr><p>a</p>");
+
+        // the rule sits between its margins and pushes the text down
+        let top = 8.0;
+        assert_eq!(
+            canvas.quads,
+            vec![(
+                Rect::new(0.0, top, 800.0, layout::RULE_THICKNESS),
+                RULE_COLOR
+            )]
+        );
+        let text_y = top + layout::RULE_THICKNESS + 8.0;
+        assert_eq!(canvas.texts[0].1.screen_pos, (0.0, text_y));
+
+        let canvas = paint(r#"<hr style="width: 200px; color: #ff0000">"#);
+        assert_eq!(
+            canvas.quads,
+            vec![(
+                Rect::new(300.0, top, 200.0, layout::RULE_THICKNESS),
+                [1.0, 0.0, 0.0, 1.0]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_line_breaks() {
+        let mut graph = HtmlRenderGraph::new(
+            "<p>a<br>b</p><p>one \n<BR/>\n two<br>three</p><div><br></div>",
+        );
+        assert_eq!(graph.nodes[0].content.as_deref(), Some("a\nb"));
+        assert_eq!(graph.nodes[1].content.as_deref(), Some("one\ntwo\nthree"));
+
+        // breaks do not get a box of their own
+        assert!(graph.nodes[0].children.is_empty());
+        assert!(graph.nodes[2].children.is_empty());
+        assert!(graph.diagnostics().is_empty());
+
+        let mut canvas = RecordingCanvas::default();
+        graph.layout(&mut canvas, Rect::new(0.0, 0.0, 800.0, 600.0));
+        let line_height = get_line_height_of_text("a", DEFAULT_FONT_SIZE);
+        assert_eq!(graph.nodes[0].rect.height, 2.0 * line_height);
+        assert_eq!(graph.nodes[1].rect.y, 2.0 * line_height);
+    }
+
+    #[test]
+    fn test_collapse_whitespace() {
+        assert_eq!(collapse_whitespace("  a\n   b  "), "a b");
+        assert_eq!(collapse_whitespace("a\t\tb\r\nc"), "a b c");
+        assert_eq!(collapse_whitespace(" \n "), "");
+    }
+
+    #[test]
+    fn test_unknown_tags_keep_their_children() {
+        let graph = HtmlRenderGraph::new(
+            "<div>\n\n  <marquee><p>hi</p></marquee></div>",
+        );
+
+        let marquee = &graph.nodes[0].children[0];
+        assert_eq!(marquee.element, HtmlElement::Unknown);
+        assert_eq!(marquee.children[0].element, HtmlElement::Paragraph);
+        assert_eq!(marquee.children[0].content.as_deref(), Some("hi"));
+
+        // the tag is on the third line
+        let span = Span {
+            line: 3,
+            col: 3,
+            byte_offset: 9,
+        };
+        assert_eq!(
+            graph.diagnostics(),
+            [Diagnostic::UnknownTag {
+                tag: "marquee".to_string(),
+                span,
+            }]
+        );
+        assert_eq!(
+            graph.diagnostics()[0].to_string(),
+            "unknown tag <marquee> at line 3, column 3"
+        );
+    }
+
+    #[test]
+    fn test_visit() {
+        let mut graph = HtmlRenderGraph::new(
+            "<div><p>a</p><ul><li>b</li><li>c</li></ul></div>",
+        );
+
+        let mut visited = Vec::new();
+        visit(&graph.nodes[0], 0, &mut |node, depth| {
+            visited.push((node.element, depth))
+        });
+        assert_eq!(
+            visited,
+            vec![
+                (HtmlElement::Div, 0),
+                (HtmlElement::Paragraph, 1),
+                (HtmlElement::UnorderedList, 1),
+                (HtmlElement::ListItem, 2),
+                (HtmlElement::ListItem, 2),
+            ]
+        );
+
+        visit_mut(&mut graph.nodes[0], 0, &mut |node, depth| {
+            node.font_size = depth as f32
+        });
+        assert_eq!(graph.nodes[0].children[1].children[0].font_size, 2.0);
+    }
+
+    #[test]
+    fn test_get_element_by_id() {
+        let graph = HtmlRenderGraph::new(
+            r#"<div id="nav"><p>a</p></div><div><p id="main">b</p><p id="main">c</p></div>"#,
+        );
+
+        let nav = graph.get_element_by_id("nav").unwrap();
+        assert_eq!(nav.element, HtmlElement::Div);
+
+        // the first of the duplicate ids wins
+        let main = graph.get_element_by_id("main").unwrap();
+        assert_eq!(main.content.as_deref(), Some("b"));
+        assert_eq!(main.id(), Some("main"));
+        assert!(std::ptr::eq(main, &graph.nodes[1].children[0]));
+
+        assert!(graph.get_element_by_id("missing").is_none());
+    }
+
+    #[test]
+    fn test_mismatched_tags_are_reported() {
+        let graph = HtmlRenderGraph::new("<div><p>a</div><p>b");
+
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.diagnostics(), [Diagnostic::MismatchedTags]);
+    }
+
+    #[test]
+    fn test_parse_errors_are_reported() {
+        let deep = "<div>".repeat(10_000);
+        let graph = HtmlRenderGraph::new(&deep);
+        assert!(graph.nodes.is_empty());
+        assert!(matches!(
+            graph.diagnostics(),
+            [Diagnostic::ParseFailed(ParseError::TooDeep { .. })]
+        ));
+
+        let graph = HtmlRenderGraph::new("<p title=\"a");
+        assert!(graph.nodes.is_empty());
+        assert!(matches!(
+            graph.diagnostics(),
+            [Diagnostic::LexFailed(
+                LexError::UnterminatedAttribute { .. }
+            )]
+        ));
+    }
+
+    #[test]
+    fn test_stylesheet_rules() {
+        let graph = HtmlRenderGraph::new(
+            r#"<head><style>.warn { color: red } p { font-size: 20px; text-align: center }</style></head>
+            <p class="warn">a</p><p class="warn" style="color: #00ff00">b</p><p>c</p>"#,
+        );
+
+        let warn = &graph.nodes[0];
+        assert_eq!(warn.fg_color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(warn.font_size, 20.0);
+        assert_eq!(warn.layout.h_align, HorizontalAlign::Center);
+
+        // inline styles win
+        assert_eq!(graph.nodes[1].fg_color, [0.0, 1.0, 0.0, 1.0]);
+
+        assert_eq!(graph.nodes[2].fg_color, [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(graph.nodes[2].font_size, 20.0);
+    }
+
+    #[test]
+    fn test_head_is_not_rendered() {
+        let graph = HtmlRenderGraph::new(
+            "<html><head><title>Document</title></head><body></body></html>",
+        );
+
+        let html = &graph.nodes[0];
+        assert_eq!(html.children.len(), 1);
+        assert_eq!(html.children[0].element, HtmlElement::Body);
+        assert!(graph.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_parse_borders() {
+        let fg = [0.0, 0.0, 0.0, 1.0];
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        let borders = |style: &str| {
+            parse_borders(|property| parse_style(style, property), fg)
+        };
+        let side = |width, color| Border { width, color };
+
+        assert_eq!(
+            borders("border: 2px solid red").uniform(),
+            Some(side(2.0, red))
+        );
+        assert_eq!(
+            borders("border: rgb(0, 0, 255) DASHED thin").uniform(),
+            Some(side(1.0, blue))
+        );
+        assert_eq!(borders("border: solid").uniform(), Some(side(3.0, fg)));
+
+        // borders without a visible style are not drawn
+        assert_eq!(borders("border: 1px #000"), Borders::default());
+        assert_eq!(borders("border: none"), Borders::default());
+        assert_eq!(borders("border: 1px solid nocolor"), Borders::default());
+
+        assert_eq!(
+            borders("border: 1px solid red; border-left: 4px solid blue"),
+            Borders {
+                top: side(1.0, red),
+                right: side(1.0, red),
+                bottom: side(1.0, red),
+                left: side(4.0, blue),
+            }
+        );
+        assert_eq!(
+            borders(
+                "border-style: solid none; border-width: 1px 2px 3px; \
+                 border-color: red blue"
+            ),
+            Borders {
+                top: side(1.0, red),
+                right: Border::default(),
+                bottom: side(3.0, red),
+                left: Border::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_border_quads() {
+        let canvas = paint(
+            r#"<div style="border: 1px solid #00f; height: 10px"></div><div style="border: 0px solid #00f"></div>"#,
+        );
+
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        assert_eq!(
+            canvas.quads,
+            vec![
+                (Rect::new(0.0, 0.0, 800.0, 1.0), blue),
+                (Rect::new(799.0, 1.0, 1.0, 10.0), blue),
+                (Rect::new(0.0, 11.0, 800.0, 1.0), blue),
+                (Rect::new(0.0, 1.0, 1.0, 10.0), blue),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_border_radius() {
+        let graph = HtmlRenderGraph::new(
+            r#"<div style="border-radius: 6px"></div><div style="border-radius: 50%"></div><div></div>"#,
+        );
+        let radii: Vec<f32> =
+            graph.nodes.iter().map(|node| node.border_radius).collect();
+        assert_eq!(radii, vec![6.0, 0.0, 0.0]);
+
+        // only the background is rounded
+        let canvas = paint(
+            r#"<div style="border-radius: 4px; background-color: #f00; border: 1px solid #00f; height: 10px"></div>"#,
+        );
+        assert_eq!(canvas.corner_radii, vec![4.0]);
+        assert_eq!(canvas.quads.len(), 5);
+    }
+
+    #[test]
+    fn test_hidden_elements() {
+        let canvas = paint(
+            r#"<div hidden><p>a</p></div><div style="display:none">b</div>
+            <div style="display: block; background-color: #f00">c</div>"#,
+        );
+
+        assert_eq!(canvas.texts.len(), 1);
+        assert_eq!(canvas.texts[0].0, "c");
+        assert_eq!(canvas.texts[0].1.screen_pos, (0.0, 0.0));
+        assert_eq!(canvas.quads.len(), 1);
+        assert_eq!(canvas.quads[0].0.y, 0.0);
+    }
+
+    #[test]
+    fn test_hidden_by_stylesheet() {
+        let graph = HtmlRenderGraph::new(
+            "<style>.gone { display: none }</style>\
+             <p class=\"gone\">a</p><p class=\"shown\">b</p><bogus hidden></bogus>",
+        );
+
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(graph.nodes[0].content.as_deref(), Some("b"));
+        // hidden unknown tags are still reported
+        assert_eq!(graph.diagnostics().len(), 1);
+    }
+
+    #[test]
+    fn test_image_placeholder() {
+        // the recording canvas can not load images
+        let canvas = paint(r#"<img src="cat.png" width="100" height="50">"#);
+
+        assert_eq!(
+            canvas.quads,
+            vec![(Rect::new(0.0, 0.0, 100.0, 50.0), IMAGE_PLACEHOLDER_COLOR)]
+        );
+    }
+
+    #[test]
+    fn test_debug_string() {
+        let graph = HtmlRenderGraph::new(
+            "<div style=\"background-color: #eee\"><h1>Title</h1>\
+             <p style=\"color: red\">Some text</p></div><p>end</p>",
+        );
+
+        assert_eq!(
+            graph.to_debug_string(),
+            "Div (0, 0) fg=#000000ff bg=#eeeeeeff\n\
+             \x20 H1 (1, 1) fg=#000000ff bg=#00000000 \"Title\"\n\
+             \x20 Paragraph (1, 2) fg=#ff0000ff bg=#00000000 \"Some text\"\n\
+             Paragraph (0, 3) fg=#000000ff bg=#00000000 \"end\"\n"
+        );
+    }
+
+    #[test]
+    fn test_whitespace_in_text_nodes() {
+        let graph = HtmlRenderGraph::new(
+            "<p>Lorem   ipsum\n        dolor  </p><pre>a  b\n  c</pre>\
+             <pre><b>x\n y</b></pre>",
+        );
+
+        assert_eq!(
+            graph.nodes[0].content.as_deref(),
+            Some("Lorem ipsum dolor")
+        );
+        assert_eq!(graph.nodes[1].element, HtmlElement::Pre);
+        assert_eq!(graph.nodes[1].content.as_deref(), Some("a  b\n  c"));
+        assert_eq!(
+            graph.nodes[2].children[0].content.as_deref(),
+            Some("x\n y")
+        );
+    }
+
+    #[test]
+    fn test_pre_keeps_lines_and_spacing() {
+        let mut graph = HtmlRenderGraph::new(
+            "<pre>\nfn main() {\n\tlet  a = 1;\n}</pre><p>after</p>",
+        );
+        let pre = &graph.nodes[0];
+        assert_eq!(
+            pre.content.as_deref(),
+            Some("fn main() {\n        let  a = 1;\n}")
+        );
+        assert_eq!(pre.font_style, FontStyle::Monospace);
+
+        let mut canvas = RecordingCanvas::default();
+        layout::layout(
+            &mut graph.nodes,
+            &mut canvas,
+            Rect::new(0.0, 0.0, 800.0, 600.0),
+        );
+        let line_height = get_line_height_of_text("a", DEFAULT_FONT_SIZE);
+        assert_eq!(graph.nodes[0].rect.height, 3.0 * line_height);
+        assert_eq!(graph.nodes[1].rect.y, 3.0 * line_height);
+    }
+
+    #[test]
+    fn test_white_space_property() {
+        let graph = HtmlRenderGraph::new(
+            r#"<p style="white-space: pre-wrap">a  b</p><pre style="white-space: normal">a  b</pre>"#,
+        );
+
+        assert_eq!(graph.nodes[0].content.as_deref(), Some("a  b"));
+        assert_eq!(graph.nodes[1].content.as_deref(), Some("a b"));
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("\ta"), "        a");
+        assert_eq!(expand_tabs("ab\tc\n\td"), "ab      c\n        d");
+        assert_eq!(expand_tabs("12345678\tx"), "12345678        x");
+    }
+
+    #[test]
+    fn test_hex_to_rgba() {
+        let color = "#ffaa00";
+        assert_eq!(hex_to_rgba(color).unwrap(), [1.0, 0.6666667, 0.0, 1.0]);
+        assert_eq!(hex_to_rgba("ffaa00").unwrap(), [1.0, 0.6666667, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#f90"), Some([1.0, 0.6, 0.0, 1.0]));
+        assert_eq!(
+            parse_color("#ff990080"),
+            Some([1.0, 0.6, 0.0, 128.0 / 255.0])
+        );
+        assert_eq!(parse_color("rgb(255, 153, 0)"), Some([1.0, 0.6, 0.0, 1.0]));
+        assert_eq!(parse_color("rgba(0,0,0,0.5)"), Some([0.0, 0.0, 0.0, 0.5]));
+        assert_eq!(
+            parse_color("RGB(100%, 0%, 0%)"),
+            Some([1.0, 0.0, 0.0, 1.0])
+        );
+        assert_eq!(parse_color("transparent"), Some([0.0, 0.0, 0.0, 0.0]));
+        assert_eq!(parse_color("White"), Some([1.0, 1.0, 1.0, 1.0]));
+
+        assert_eq!(parse_color("#ff99"), Some([1.0, 1.0, 0.6, 0.6]));
+        assert_eq!(parse_color("#ff9g00"), None);
+        assert_eq!(parse_color("#ff99000"), None);
+        assert_eq!(parse_color("rgb(1, 2)"), None);
+        assert_eq!(parse_color("notacolor"), None);
     }
 }
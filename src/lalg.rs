@@ -9,3 +9,32 @@ pub fn mat4_to_array(mat: &Mat4) -> [[f32; 4]; 4] {
         [slice[12], slice[13], slice[14], slice[15]],
     ]
 }
+
+/// Axis aligned rectangle in screen pixels, `(x, y)` being the top-left
+/// corner.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x
+            && x < self.x + self.width
+            && y >= self.y
+            && y < self.y + self.height
+    }
+}
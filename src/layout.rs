@@ -0,0 +1,829 @@
+use glyph_brush::{HorizontalAlign, VerticalAlign};
+
+use crate::html_renderer::{
+    node_text, visit, visit_mut, HtmlElement, Length, LengthContext, RenderNode,
+};
+use crate::lalg::Rect;
+use crate::renderer::Canvas;
+
+/// Horizontal indent of the content of a list.
+pub const LIST_INDENT: f32 = 24.0;
+
+/// Width and height of an image that could not be loaded and sets no size.
+pub const IMAGE_PLACEHOLDER_SIZE: f32 = 24.0;
+
+/// Height of a horizontal rule that sets no height.
+pub const RULE_THICKNESS: f32 = 2.0;
+
+/// Content box of the element a node is laid out in. Its height is only
+/// known when it was set explicitly.
+#[derive(Debug, Clone, Copy)]
+struct ContainingBlock {
+    x: f32,
+    width: f32,
+    height: Option<f32>,
+    // size of the whole layout area, for viewport units
+    viewport: (f32, f32),
+}
+
+impl ContainingBlock {
+    /// Context of lengths along the x axis, like widths and margins.
+    fn horizontal(&self) -> LengthContext {
+        LengthContext {
+            viewport: self.viewport,
+            containing: Some(self.width),
+        }
+    }
+
+    /// Context of lengths along the y axis, like heights.
+    fn vertical(&self) -> LengthContext {
+        LengthContext {
+            viewport: self.viewport,
+            containing: self.height,
+        }
+    }
+}
+
+/// Assigns every node a rectangle in page coordinates. Block elements take
+/// the full width of their parent unless they set a `width`, and stack
+/// from the top of `viewport` down. Runs of inline siblings are as wide as
+/// their content and go side by side on lines, see `layout_line_boxes`.
+/// Returns the width and height of the laid out content. It is wider than
+/// `viewport` when an element sets a width larger than its parent's.
+pub fn layout(
+    nodes: &mut [RenderNode],
+    canvas: &mut impl Canvas,
+    viewport: Rect,
+) -> (f32, f32) {
+    let containing_block = ContainingBlock {
+        x: viewport.x,
+        width: viewport.width,
+        height: Some(viewport.height),
+        viewport: (viewport.width, viewport.height),
+    };
+
+    let y = viewport.y
+        + layout_children(
+            nodes,
+            canvas,
+            containing_block,
+            HorizontalAlign::Left,
+            viewport.y,
+        );
+
+    // boxes never start left of the viewport, but may end right of it
+    let mut right = viewport.x;
+    for node in nodes.iter() {
+        visit(node, 0, &mut |node, _| {
+            right = right.max(node.rect.x + node.rect.width);
+        });
+    }
+
+    (right - viewport.x, y - viewport.y)
+}
+
+/// Lays out `node` with the top of its margin at `y` and returns the
+/// vertical space it takes up, margins included.
+fn layout_node(
+    node: &mut RenderNode,
+    canvas: &mut impl Canvas,
+    parent: ContainingBlock,
+    y: f32,
+) -> f32 {
+    // the content of a template is not part of the page
+    if node.element == HtmlElement::Template {
+        node.rect = Rect::new(parent.x, y, 0.0, 0.0);
+        node.content_rect = node.rect;
+        return 0.0;
+    }
+
+    // percentages of margins and padding refer to the parent's width
+    let edge = |length: Length| resolve_or_zero(length, parent.horizontal());
+    let margin = &node.margin;
+    let padding = &node.padding;
+    // space between the border box and the content box on each axis,
+    // borders sit between the margin and the padding
+    let borders = &node.borders;
+    let inset_x = edge(padding.left)
+        + edge(padding.right)
+        + borders.left.width
+        + borders.right.width;
+    let inset_y = edge(padding.top)
+        + edge(padding.bottom)
+        + borders.top.width
+        + borders.bottom.width;
+
+    // images fall back to their natural size
+    let natural_size = match node.element {
+        HtmlElement::Img => Some(natural_size(node, canvas)),
+        _ => None,
+    };
+
+    let explicit_width = node
+        .width
+        .and_then(|width| width.resolve(parent.horizontal()))
+        .or(natural_size.map(|(width, _)| width));
+    // a `max-width` narrower than the box would get turns into its width,
+    // so auto margins can still center it
+    let max_width = node
+        .max_width
+        .and_then(|max_width| max_width.resolve(parent.horizontal()));
+    let explicit_width = match (explicit_width, max_width) {
+        (Some(width), Some(max_width)) => Some(width.min(max_width)),
+        (None, Some(max_width)) => {
+            let margin_x = edge(margin.left) + edge(margin.right);
+            let auto_width = parent.width - margin_x - inset_x;
+            (max_width < auto_width).then_some(max_width)
+        }
+        (width, None) => width,
+    };
+    let explicit_height = node
+        .height
+        .and_then(|height| height.resolve(parent.vertical()))
+        .or(natural_size.map(|(_, height)| height))
+        .or((node.element == HtmlElement::HorizontalRule)
+            .then_some(RULE_THICKNESS));
+
+    // width of the border box and its offset from the parent's content box
+    let (width, margin_left) = match explicit_width {
+        Some(width) => {
+            let width = width + inset_x;
+            let free = parent.width - width;
+            let margin_left = match (margin.left, margin.right) {
+                (Length::Auto, Length::Auto) => free / 2.0,
+                (Length::Auto, right) => free - edge(right),
+                (left, _) => edge(left),
+            };
+
+            (width, margin_left.max(0.0))
+        }
+        None => {
+            let margin_x = edge(margin.left) + edge(margin.right);
+            ((parent.width - margin_x).max(0.0), edge(margin.left))
+        }
+    };
+
+    let x = parent.x + margin_left;
+    let top = y + edge(margin.top);
+
+    // lists indent their items to leave room for the markers
+    let indent = match node.element {
+        HtmlElement::UnorderedList | HtmlElement::OrderedList => LIST_INDENT,
+        _ => 0.0,
+    };
+    let content_x = x + borders.left.width + edge(padding.left) + indent;
+    let content_y = top + borders.top.width + edge(padding.top);
+    let content_box = ContainingBlock {
+        x: content_x,
+        width: (width - inset_x - indent).max(0.0),
+        height: explicit_height,
+        ..parent
+    };
+
+    let text_height = node_text(node)
+        .map(|text| {
+            let (_, height) = canvas.measure_text(
+                &text,
+                node.font_size,
+                node.font_style,
+                Some(content_box.width),
+                node.line_height,
+            );
+            height
+        })
+        .unwrap_or(0.0);
+
+    let mut cursor = content_y + text_height;
+    if node.element == HtmlElement::Table {
+        cursor += layout_table(&mut node.children, canvas, content_box, cursor);
+    } else {
+        cursor += layout_children(
+            &mut node.children,
+            canvas,
+            content_box,
+            node.layout.h_align,
+            cursor,
+        );
+    }
+
+    // an explicit height can make the box taller than its content
+    let content_height =
+        (cursor - content_y).max(explicit_height.unwrap_or(0.0));
+    let height = content_height + inset_y;
+
+    node.rect = Rect::new(x, top, width, height);
+    node.content_rect =
+        Rect::new(content_x, content_y, content_box.width, content_height);
+
+    edge(margin.top) + height + edge(margin.bottom)
+}
+
+/// Lays out `children` from `y` down and returns the height they take up.
+/// Blocks go below each other, runs of inline children on line boxes
+/// aligned by the parent's `h_align`.
+fn layout_children(
+    children: &mut [RenderNode],
+    canvas: &mut impl Canvas,
+    parent: ContainingBlock,
+    h_align: HorizontalAlign,
+    y: f32,
+) -> f32 {
+    let mut cursor = y;
+    let mut rest = children;
+    while !rest.is_empty() {
+        let inline = rest
+            .iter()
+            .take_while(|child| child.element.is_inline())
+            .count();
+        let (run, tail) = rest.split_at_mut(inline.max(1));
+        cursor += if inline > 0 {
+            layout_line_boxes(run, canvas, parent, h_align, cursor)
+        } else {
+            layout_node(&mut run[0], canvas, parent, cursor)
+        };
+        rest = tail;
+    }
+
+    cursor - y
+}
+
+/// Lays out inline `items` on lines from `y` down and returns their
+/// height. Every item gets a box as wide as its content, limited to the
+/// width of the parent, and a line holds as many as fit next to each other.
+/// A line is as high as its tallest item, `vertical-align` moves shorter
+/// ones to its top, middle or bottom.
+fn layout_line_boxes(
+    items: &mut [RenderNode],
+    canvas: &mut impl Canvas,
+    parent: ContainingBlock,
+    h_align: HorizontalAlign,
+    y: f32,
+) -> f32 {
+    // items of the line being filled with their outer heights
+    let mut line: Vec<(usize, f32)> = Vec::new();
+    let mut line_width = 0.0;
+    let mut line_top = y;
+    for index in 0..items.len() {
+        let width =
+            preferred_width(&items[index], canvas, parent).min(parent.width);
+        if !line.is_empty() && line_width + width > parent.width {
+            line_top += finish_line(items, &line, line_width, parent, h_align);
+            line.clear();
+            line_width = 0.0;
+        }
+
+        let item_block = ContainingBlock {
+            x: parent.x + line_width,
+            width,
+            height: None,
+            ..parent
+        };
+        let height =
+            layout_node(&mut items[index], canvas, item_block, line_top);
+        line.push((index, height));
+        line_width += width;
+    }
+    line_top += finish_line(items, &line, line_width, parent, h_align);
+
+    line_top - y
+}
+
+/// Moves the items of a line to where `h_align` and their
+/// `vertical-align` put them and returns the height of the line.
+fn finish_line(
+    items: &mut [RenderNode],
+    line: &[(usize, f32)],
+    line_width: f32,
+    parent: ContainingBlock,
+    h_align: HorizontalAlign,
+) -> f32 {
+    let line_height =
+        line.iter().map(|&(_, height)| height).fold(0.0, f32::max);
+    let free = (parent.width - line_width).max(0.0);
+    let dx = match h_align {
+        HorizontalAlign::Left => 0.0,
+        HorizontalAlign::Center => free / 2.0,
+        HorizontalAlign::Right => free,
+    };
+
+    for &(index, height) in line {
+        let extra = line_height - height;
+        let dy = match items[index].layout.v_align {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Center => extra / 2.0,
+            VerticalAlign::Bottom => extra,
+        };
+        if dx != 0.0 || dy != 0.0 {
+            visit_mut(&mut items[index], 0, &mut |node, _| {
+                node.rect.x += dx;
+                node.rect.y += dy;
+                node.content_rect.x += dx;
+                node.content_rect.y += dy;
+            });
+        }
+    }
+
+    line_height
+}
+
+/// Width `node` takes up with its margins when it is only as wide as its
+/// content: its text on one line, the widest of its block children or the
+/// widest line of its inline ones. Sizes set by the page and the natural
+/// size of images win.
+fn preferred_width(
+    node: &RenderNode,
+    canvas: &mut impl Canvas,
+    parent: ContainingBlock,
+) -> f32 {
+    let edge = |length: Length| resolve_or_zero(length, parent.horizontal());
+    let margin_x = edge(node.margin.left) + edge(node.margin.right);
+    let inset_x = edge(node.padding.left)
+        + edge(node.padding.right)
+        + node.borders.left.width
+        + node.borders.right.width;
+
+    let explicit_width = node
+        .width
+        .and_then(|width| width.resolve(parent.horizontal()))
+        .or_else(|| {
+            (node.element == HtmlElement::Img)
+                .then(|| natural_size(node, canvas).0)
+        });
+    let content_width = explicit_width.unwrap_or_else(|| {
+        let text_width = node_text(node).map_or(0.0, |text| {
+            let (width, _) = canvas.measure_text(
+                &text,
+                node.font_size,
+                node.font_style,
+                None,
+                node.line_height,
+            );
+            width
+        });
+
+        // children are measured against the parent, their own box is not
+        // known yet
+        let mut widest = text_width;
+        let mut inline_run = 0.0;
+        for child in &node.children {
+            let width = preferred_width(child, canvas, parent);
+            if child.element.is_inline() {
+                inline_run += width;
+                widest = widest.max(inline_run);
+            } else {
+                inline_run = 0.0;
+                widest = widest.max(width);
+            }
+        }
+        widest
+    });
+
+    margin_x + inset_x + content_width
+}
+
+/// Size of an image, or of the placeholder drawn when it can not be loaded.
+fn natural_size(node: &RenderNode, canvas: &mut impl Canvas) -> (f32, f32) {
+    node.src
+        .as_deref()
+        .and_then(|src| canvas.image_size(src))
+        .map(|(w, h)| (w as f32, h as f32))
+        .unwrap_or((IMAGE_PLACEHOLDER_SIZE, IMAGE_PLACEHOLDER_SIZE))
+}
+
+/// Lays out the rows of a table from `y` down and returns their height.
+/// Every column gets the same width, enough for the row with the most
+/// cells. Cells of a row are as tall as the tallest one among them, other
+/// children of the table are laid out as blocks.
+fn layout_table(
+    children: &mut [RenderNode],
+    canvas: &mut impl Canvas,
+    table: ContainingBlock,
+    y: f32,
+) -> f32 {
+    let is_cell = |node: &RenderNode| node.element == HtmlElement::TableCell;
+    let columns = children
+        .iter()
+        .filter(|child| child.element == HtmlElement::TableRow)
+        .map(|row| row.children.iter().filter(|cell| is_cell(cell)).count())
+        .max()
+        .unwrap_or(0);
+    let column_width = table.width / columns.max(1) as f32;
+
+    let mut cursor = y;
+    for child in children {
+        if child.element != HtmlElement::TableRow {
+            cursor += layout_node(child, canvas, table, cursor);
+            continue;
+        }
+
+        // vertical space each cell takes up, margins included
+        let mut heights = Vec::new();
+        for cell in child.children.iter_mut().filter(|cell| is_cell(cell)) {
+            let column_block = ContainingBlock {
+                x: table.x + heights.len() as f32 * column_width,
+                width: column_width,
+                height: None,
+                ..table
+            };
+            heights.push(layout_node(cell, canvas, column_block, cursor));
+        }
+        let row_height = heights.iter().copied().fold(0.0, f32::max);
+
+        // shorter cells are stretched to the height of the row, their
+        // content moves down as far as `vertical-align` asks
+        let cells = child.children.iter_mut().filter(|cell| is_cell(cell));
+        for (cell, height) in cells.zip(heights) {
+            let extra = row_height - height;
+            let offset = match cell.layout.v_align {
+                VerticalAlign::Top => 0.0,
+                VerticalAlign::Center => extra / 2.0,
+                VerticalAlign::Bottom => extra,
+            };
+
+            cell.rect.height += extra;
+            cell.content_rect.y += offset;
+            cell.content_rect.height += extra - offset;
+            for content in &mut cell.children {
+                visit_mut(content, 0, &mut |node, _| {
+                    node.rect.y += offset;
+                    node.content_rect.y += offset;
+                });
+            }
+        }
+
+        child.rect = Rect::new(table.x, cursor, table.width, row_height);
+        child.content_rect = child.rect;
+        cursor += row_height;
+    }
+
+    cursor - y
+}
+
+/// Resolves a margin or padding length, `auto` counts as nothing.
+fn resolve_or_zero(length: Length, ctx: LengthContext) -> f32 {
+    length.resolve(ctx).unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_renderer::HtmlRenderGraph;
+    use crate::renderer::{get_line_height_of_text, FontStyle, TextDrawConfig};
+
+    /// Canvas that only knows its size, enough to lay pages out.
+    struct MeasureCanvas;
+
+    impl Canvas for MeasureCanvas {
+        fn screen_size(&self) -> (u32, u32) {
+            (800, 600)
+        }
+
+        fn draw_quad(&mut self, _: (f32, f32), _: (f32, f32), _: [f32; 4]) {}
+
+        fn draw_text(&mut self, _: &str, _: f32, _: TextDrawConfig) {}
+
+        fn image_size(&mut self, src: &str) -> Option<(u32, u32)> {
+            (src == "cat.png").then_some((120, 80))
+        }
+
+        // every character is 10px wide, so inline boxes have a width
+        fn measure_text(
+            &self,
+            text: &str,
+            _size: f32,
+            _style: FontStyle,
+            _max_width: Option<f32>,
+            line_height: f32,
+        ) -> (f32, f32) {
+            if text.is_empty() {
+                return (0.0, 0.0);
+            }
+
+            let lines = text.split('\n');
+            let columns = lines.clone().map(|line| line.chars().count());
+            let width = columns.max().unwrap_or(0) as f32 * 10.0;
+            (width, lines.count() as f32 * line_height)
+        }
+    }
+
+    fn layout_html(html: &str) -> (HtmlRenderGraph, f32) {
+        let mut graph = HtmlRenderGraph::new(html);
+        let (_, height) = layout(
+            &mut graph.nodes,
+            &mut MeasureCanvas,
+            Rect::new(0.0, 0.0, 800.0, 600.0),
+        );
+
+        (graph, height)
+    }
+
+    #[test]
+    fn test_blocks_stack_vertically() {
+        let (graph, height) =
+            layout_html("<h1>Title</h1><div><p>a</p><p>b</p></div>");
+        let h1_height = get_line_height_of_text("Title", 32.0);
+        let p_height = get_line_height_of_text("a", 16.0);
+
+        let h1 = &graph.nodes[0];
+        assert_eq!(h1.rect, Rect::new(0.0, 0.0, 800.0, h1_height));
+
+        let div = &graph.nodes[1];
+        assert_eq!(div.rect, Rect::new(0.0, h1_height, 800.0, 2.0 * p_height));
+        assert_eq!(
+            div.children[0].rect,
+            Rect::new(0.0, h1_height, 800.0, p_height)
+        );
+        assert_eq!(
+            div.children[1].rect,
+            Rect::new(0.0, h1_height + p_height, 800.0, p_height)
+        );
+
+        assert_eq!(height, h1_height + 2.0 * p_height);
+    }
+
+    #[test]
+    fn test_line_height_sets_line_advance() {
+        let (graph, _) = layout_html(
+            r#"<p style="line-height: 1.0">a</p><div style="line-height: 24px"><pre>a
+b</pre><p style="font-size: 32px">c</p></div><div style="line-height: 1.5"><p style="font-size: 20px">d</p></div>"#,
+        );
+
+        assert_eq!(graph.nodes[0].rect.height, 16.0);
+
+        // pixels are inherited as they are, whatever the font size
+        let px = &graph.nodes[1];
+        assert_eq!(px.children[0].rect.height, 2.0 * 24.0);
+        assert_eq!(px.children[1].rect.height, 24.0);
+
+        // multiples follow the font size of the element using them
+        assert_eq!(graph.nodes[2].children[0].rect.height, 30.0);
+    }
+
+    #[test]
+    fn test_explicit_size() {
+        let (graph, height) = layout_html(
+            r#"<div style="width: 50%; height: 100px"><p style="width: 50%">a</p></div><p>b</p>"#,
+        );
+
+        let div = &graph.nodes[0];
+        assert_eq!(div.rect, Rect::new(0.0, 0.0, 400.0, 100.0));
+        assert_eq!(div.children[0].rect.width, 200.0);
+        assert_eq!(graph.nodes[1].rect.y, 100.0);
+        assert_eq!(height, 100.0 + get_line_height_of_text("b", 16.0));
+    }
+
+    #[test]
+    fn test_margin_and_padding() {
+        let (graph, height) = layout_html(
+            r#"<div style="margin: 10px; padding: 10px 20px 30px 40px"><p>a</p></div><p style="margin: 5px 0">b</p>"#,
+        );
+        let p_height = get_line_height_of_text("a", 16.0);
+
+        let div = &graph.nodes[0];
+        assert_eq!(div.rect, Rect::new(10.0, 10.0, 780.0, p_height + 40.0));
+        assert_eq!(div.content_rect, Rect::new(50.0, 20.0, 720.0, p_height));
+        assert_eq!(
+            div.children[0].rect,
+            Rect::new(50.0, 20.0, 720.0, p_height)
+        );
+
+        // the margins of both siblings separate them
+        let p = &graph.nodes[1];
+        assert_eq!(p.rect.y, 10.0 + div.rect.height + 10.0 + 5.0);
+        assert_eq!(height, p.rect.y + p_height + 5.0);
+    }
+
+    #[test]
+    fn test_auto_margin_centers_block() {
+        let (graph, _) = layout_html(
+            r#"<div style="width: 200px; margin: 0 auto"></div><div style="width: 200px; margin-left: 10px; margin: 0 10px 0 auto"></div>"#,
+        );
+
+        assert_eq!(graph.nodes[0].rect, Rect::new(300.0, 0.0, 200.0, 0.0));
+        assert_eq!(graph.nodes[1].rect.x, 590.0);
+    }
+
+    #[test]
+    fn test_max_width_limits_block() {
+        let mut graph = HtmlRenderGraph::new(
+            r#"<div style="max-width: 600px; margin: 0 auto"><p>a</p></div><div style="max-width: 50%; width: 800px"></div><div style="max-width: 2000px"></div>"#,
+        );
+        layout(
+            &mut graph.nodes,
+            &mut MeasureCanvas,
+            Rect::new(0.0, 0.0, 1200.0, 600.0),
+        );
+
+        let centered = &graph.nodes[0];
+        assert_eq!(centered.rect.x, 300.0);
+        assert_eq!(centered.rect.width, 600.0);
+        // text wraps inside the narrower box
+        assert_eq!(centered.children[0].rect.width, 600.0);
+
+        assert_eq!(graph.nodes[1].rect.width, 600.0);
+        assert_eq!(graph.nodes[2].rect.width, 1200.0);
+    }
+
+    #[test]
+    fn test_content_width() {
+        let mut graph = HtmlRenderGraph::new(
+            r#"<p>a</p><div style="margin: 0 0 0 100px"><pre style="width: 1000px">a</pre></div>"#,
+        );
+        let viewport = Rect::new(0.0, 0.0, 800.0, 600.0);
+        let (width, _) = layout(&mut graph.nodes, &mut MeasureCanvas, viewport);
+        assert_eq!(width, 1100.0);
+
+        // content that fits takes the width of the viewport
+        let mut graph = HtmlRenderGraph::new("<p>a</p>");
+        let (width, _) = layout(&mut graph.nodes, &mut MeasureCanvas, viewport);
+        assert_eq!(width, 800.0);
+    }
+
+    #[test]
+    fn test_viewport_units() {
+        // the units refer to the viewport even inside a narrower parent
+        let (graph, _) = layout_html(
+            r#"<div style="width: 100px"><div style="width: 50vw; height: 10vh"></div></div>"#,
+        );
+        let div = &graph.nodes[0].children[0];
+        assert_eq!(div.rect, Rect::new(0.0, 0.0, 400.0, 60.0));
+    }
+
+    #[test]
+    fn test_border_surrounds_padding() {
+        let (graph, _) = layout_html(
+            r#"<div style="border: 2px solid red; padding: 10px; width: 100px"><p>a</p></div>"#,
+        );
+        let p_height = get_line_height_of_text("a", 16.0);
+
+        let div = &graph.nodes[0];
+        assert_eq!(div.rect, Rect::new(0.0, 0.0, 124.0, p_height + 24.0));
+        assert_eq!(div.content_rect, Rect::new(12.0, 12.0, 100.0, p_height));
+    }
+
+    #[test]
+    fn test_per_side_borders_inset_content() {
+        let (graph, _) = layout_html(
+            r#"<div style="border-left: 4px solid blue; border-bottom: 1px solid; width: 100px">a</div>"#,
+        );
+        let text_height = get_line_height_of_text("a", 16.0);
+
+        let div = &graph.nodes[0];
+        assert_eq!(div.rect, Rect::new(0.0, 0.0, 104.0, text_height + 1.0));
+        assert_eq!(div.content_rect, Rect::new(4.0, 0.0, 100.0, text_height));
+    }
+
+    #[test]
+    fn test_image_size() {
+        let (graph, height) = layout_html(
+            r#"<img src="cat.png"><img src="cat.png" width="60" style="height: 40px"><img src="missing.png"><img src="missing.png" width="50">"#,
+        );
+
+        let rects: Vec<_> = graph.nodes.iter().map(|node| node.rect).collect();
+        // the images sit next to each other on one line
+        assert_eq!(
+            rects,
+            vec![
+                Rect::new(0.0, 0.0, 120.0, 80.0),
+                Rect::new(120.0, 0.0, 60.0, 40.0),
+                Rect::new(
+                    180.0,
+                    0.0,
+                    IMAGE_PLACEHOLDER_SIZE,
+                    IMAGE_PLACEHOLDER_SIZE
+                ),
+                Rect::new(
+                    180.0 + IMAGE_PLACEHOLDER_SIZE,
+                    0.0,
+                    50.0,
+                    IMAGE_PLACEHOLDER_SIZE
+                ),
+            ]
+        );
+        assert_eq!(height, 80.0);
+    }
+
+    #[test]
+    fn test_vertical_align_in_a_line() {
+        let (graph, height) = layout_html(
+            r#"<p><img src="cat.png" style="vertical-align: middle"><span style="vertical-align: middle">ab</span><span>c</span><span style="vertical-align: bottom">d</span></p>"#,
+        );
+        let text_height = get_line_height_of_text("a", 16.0);
+
+        let p = &graph.nodes[0];
+        let rects: Vec<_> = p.children.iter().map(|child| child.rect).collect();
+        assert_eq!(
+            rects,
+            [
+                Rect::new(0.0, 0.0, 120.0, 80.0),
+                Rect::new(120.0, (80.0 - text_height) / 2.0, 20.0, text_height),
+                // baseline is not supported and sits at the top
+                Rect::new(140.0, 0.0, 10.0, text_height),
+                Rect::new(150.0, 80.0 - text_height, 10.0, text_height),
+            ]
+        );
+        assert_eq!(p.rect.height, 80.0);
+        assert_eq!(height, 80.0);
+    }
+
+    #[test]
+    fn test_code_is_inline() {
+        let (graph, _) =
+            layout_html(r#"<p><img src="cat.png"><code>let x;</code></p>"#);
+
+        // the code sits in the line next to the image, as wide as its text
+        let code = &graph.nodes[0].children[1];
+        assert_eq!((code.rect.x, code.rect.y), (120.0, 0.0));
+        assert_eq!(code.rect.width, 60.0);
+    }
+
+    #[test]
+    fn test_inline_boxes_wrap_and_align() {
+        let (graph, _) = layout_html(
+            r#"<p style="text-align: center"><img src="cat.png" width="500"><img src="cat.png" width="400"></p>"#,
+        );
+
+        let p = &graph.nodes[0];
+        // the second image does not fit next to the first one
+        assert_eq!(p.children[0].rect, Rect::new(150.0, 0.0, 500.0, 80.0));
+        assert_eq!(p.children[1].rect, Rect::new(200.0, 80.0, 400.0, 80.0));
+        assert_eq!(p.rect.height, 160.0);
+    }
+
+    #[test]
+    fn test_table_cells_form_a_grid() {
+        let (graph, height) = layout_html(
+            "<table><tr><td>a</td><td>b</td></tr>\
+             <tr><td>c</td><td style=\"height: 50px\">d</td></tr></table>",
+        );
+        let text_height = get_line_height_of_text("a", 16.0);
+
+        let table = &graph.nodes[0];
+        let cells: Vec<_> = table
+            .children
+            .iter()
+            .flat_map(|row| row.children.iter().map(|cell| cell.rect))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                Rect::new(0.0, 0.0, 400.0, text_height),
+                Rect::new(400.0, 0.0, 400.0, text_height),
+                Rect::new(0.0, text_height, 400.0, 50.0),
+                Rect::new(400.0, text_height, 400.0, 50.0),
+            ]
+        );
+        assert_eq!(table.children[1].rect.height, 50.0);
+        assert_eq!(table.rect.height, text_height + 50.0);
+        assert_eq!(height, text_height + 50.0);
+    }
+
+    #[test]
+    fn test_vertical_align_in_table_row() {
+        let (graph, _) = layout_html(
+            r#"<table><tr><td style="height: 100px">tall</td><td style="vertical-align: middle"><p>a</p></td><td style="vertical-align: bottom">b</td></tr></table>"#,
+        );
+        let text_height = get_line_height_of_text("a", 16.0);
+
+        let cells = &graph.nodes[0].children[0].children;
+        assert_eq!(cells[0].content_rect.y, 0.0);
+        // both short cells still fill the row
+        assert!(cells.iter().all(|cell| cell.rect.height == 100.0));
+
+        let middle = (100.0 - text_height) / 2.0;
+        assert_eq!(cells[1].content_rect.y, middle);
+        assert_eq!(cells[1].children[0].rect.y, middle);
+        assert_eq!(cells[2].content_rect.y, 100.0 - text_height);
+    }
+
+    #[test]
+    fn test_nested_blockquotes_are_indented() {
+        let (graph, _) = layout_html(
+            "<blockquote><p>a</p><blockquote><p>b</p></blockquote></blockquote>",
+        );
+
+        let outer = &graph.nodes[0];
+        assert_eq!(outer.content_rect.x, 40.0);
+        assert_eq!(outer.content_rect.width, 720.0);
+        assert_eq!(outer.children[0].rect.x, 40.0);
+
+        let inner = &outer.children[1];
+        assert_eq!(inner.content_rect.x, 80.0);
+        assert_eq!(inner.children[0].rect.x, 80.0);
+    }
+
+    #[test]
+    fn test_list_items_are_indented() {
+        let (graph, _) = layout_html("<ul><li>a</li><ul><li>b</li></ul></ul>");
+
+        let list = &graph.nodes[0];
+        assert_eq!(list.rect.x, 0.0);
+        assert_eq!(list.content_rect.x, LIST_INDENT);
+        assert_eq!(list.children[0].rect.x, LIST_INDENT);
+        assert_eq!(list.children[1].children[0].rect.x, 2.0 * LIST_INDENT);
+        assert_eq!(
+            list.children[1].children[0].rect.width,
+            800.0 - 2.0 * LIST_INDENT
+        );
+    }
+}
@@ -0,0 +1,16 @@
+#[macro_use]
+extern crate glium;
+extern crate nalgebra_glm as glm;
+
+pub mod app;
+pub mod camera;
+pub mod css;
+pub mod data_url;
+pub mod history;
+pub mod html_renderer;
+pub mod lalg;
+pub mod layout;
+pub mod renderer;
+pub mod selection;
+pub mod texture;
+pub mod ua_styles;
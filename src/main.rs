@@ -1,19 +1,21 @@
-#[macro_use]
-extern crate glium;
-extern crate nalgebra_glm as glm;
-
-mod camera;
-mod html_renderer;
-mod lalg;
-mod renderer;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use glium::glutin;
 use glium::glutin::{Api, GlProfile, GlRequest};
-use glutin::event::{Event, WindowEvent};
+use glutin::event::{
+    ElementState, Event, KeyboardInput, ModifiersState, MouseButton,
+    MouseScrollDelta, VirtualKeyCode, WindowEvent,
+};
 use glutin::event_loop::{ControlFlow, EventLoop};
-use html_renderer::HtmlRenderer;
+use glutin::window::CursorIcon;
 
-use crate::renderer::Renderer;
+use browser_rs::app::App;
+use browser_rs::data_url::is_data_url;
+use browser_rs::html_renderer::HtmlRenderer;
+use browser_rs::renderer::{RenderStats, Renderer};
 
 fn main() {
     let event_loop = EventLoop::new();
@@ -22,32 +24,502 @@ fn main() {
         .with_gl_profile(GlProfile::Core)
         .with_gl(GlRequest::Specific(Api::OpenGl, (3, 2)))
         .with_srgb(true);
-    let mut display =
-        glium::Display::new(window, context, &event_loop).unwrap();
+    let display = glium::Display::new(window, context, &event_loop).unwrap();
 
     let mut renderer = Renderer::new(&display);
+    let mut app = App::new();
 
     let mut html_renderer = HtmlRenderer::new();
-    html_renderer.load_html(include_str!("../assets/test.html"));
+    match std::env::args().nth(1) {
+        Some(target) => {
+            if let Err(err) = load_target(&mut html_renderer, &target) {
+                eprintln!("Failed to load {}: {}", target, err);
+            }
+        }
+        None => html_renderer.load_html(include_str!("../assets/test.html")),
+    }
+
+    renderer.update_dimension(display.get_framebuffer_dimensions());
+
+    // the page can not be replaced while it handles the click, so the link
+    // is followed once it is done
+    let clicked_link = Rc::new(RefCell::new(None));
+    html_renderer.set_on_link_click({
+        let clicked_link = clicked_link.clone();
+        move |href: &str| *clicked_link.borrow_mut() = Some(href.to_string())
+    });
 
+    let mut modifiers = ModifiersState::empty();
+    // frame time and draw counts in the corner, toggled with F3
+    let mut debug_overlay = false;
+    let mut frame_time = Duration::ZERO;
+    let mut cursor = (0.0, 0.0);
+    // set while the left button is held down to drag out a selection
+    let mut selecting = false;
+    // on some platforms copied text is gone once the clipboard is dropped,
+    // so it lives as long as the window
+    #[cfg(feature = "clipboard")]
+    let mut clipboard = arboard::Clipboard::new()
+        .map_err(|err| eprintln!("Failed to open the clipboard: {}", err))
+        .ok();
     event_loop.run(move |event, _tgt, control_flow| {
+        *control_flow = ControlFlow::Wait;
+
         match event {
             Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
+                WindowEvent::CloseRequested => app.quit(control_flow),
+                WindowEvent::Resized(_) => {
+                    renderer
+                        .update_dimension(display.get_framebuffer_dimensions());
+                }
+                WindowEvent::ModifiersChanged(state) => modifiers = state,
+                WindowEvent::CursorMoved { position, .. } => {
+                    cursor = (position.x as f32, position.y as f32);
+                    if html_renderer.update_hover(cursor.0, cursor.1) {
+                        let icon = match html_renderer.hovered_link() {
+                            Some(_) => CursorIcon::Hand,
+                            None => CursorIcon::Default,
+                        };
+                        display.gl_window().window().set_cursor_icon(icon);
+                    }
+                    if selecting {
+                        html_renderer.extend_selection(cursor.0, cursor.1);
+                    }
+                }
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => {
+                    selecting = state == ElementState::Pressed;
+                    if selecting {
+                        html_renderer.mouse_down(cursor.0, cursor.1);
+                    } else if html_renderer.mouse_up(cursor.0, cursor.1) {
+                        if let Some(href) = clicked_link.borrow_mut().take() {
+                            follow_link(&mut html_renderer, &href);
+                        }
+                    }
                 }
-                _ => (),
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Other(button),
+                    ..
+                } => match button {
+                    BACK_BUTTON => {
+                        navigate(&mut html_renderer, Navigation::Back)
+                    }
+                    FORWARD_BUTTON => {
+                        navigate(&mut html_renderer, Navigation::Forward)
+                    }
+                    _ => {}
+                },
+                WindowEvent::MouseWheel { delta, .. } if modifiers.ctrl() => {
+                    renderer.set_zoom(renderer.zoom() * zoom_factor(delta));
+                }
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let (dx, dy) = scroll_deltas(delta, modifiers.shift());
+                    html_renderer.scroll_x_by(dx);
+                    html_renderer.scroll_by(dy);
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::C),
+                            ..
+                        },
+                    ..
+                } if modifiers.ctrl() => {
+                    #[cfg(feature = "clipboard")]
+                    if let (Some(clipboard), Some(text)) =
+                        (&mut clipboard, html_renderer.selected_text())
+                    {
+                        if let Err(err) = clipboard.set_text(text) {
+                            eprintln!("Failed to copy the selection: {}", err);
+                        }
+                    }
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } => match press_key(
+                    &mut app,
+                    key,
+                    modifiers,
+                    html_renderer.viewport_height(),
+                    control_flow,
+                ) {
+                    Some(KeyAction::Scroll(delta)) => {
+                        html_renderer.scroll_by(delta)
+                    }
+                    Some(KeyAction::Navigate(navigation)) => {
+                        navigate(&mut html_renderer, navigation)
+                    }
+                    Some(KeyAction::ToggleDebugOverlay) => {
+                        debug_overlay = !debug_overlay;
+                        display.gl_window().window().request_redraw();
+                    }
+                    // there is no search box, the selection is searched for
+                    // and nothing selected ends the search. The renderer
+                    // shows how many matches there are
+                    Some(KeyAction::Find) => {
+                        let selected =
+                            html_renderer.selected_text().unwrap_or_default();
+                        html_renderer.clear_selection();
+                        html_renderer.find(selected.trim());
+                    }
+                    Some(KeyAction::FindNext) => {
+                        html_renderer.find_next();
+                    }
+                    Some(KeyAction::FindPrevious) => {
+                        html_renderer.find_previous();
+                    }
+                    Some(KeyAction::Quit) | None => {}
+                },
+                _ => {}
             },
-            _ => (),
+            // the page is static, so it is only drawn again when something
+            // changed
+            Event::MainEventsCleared => {
+                // a title shows once the mouse rested long enough, so the
+                // loop wakes up for it
+                let tooltip_due = html_renderer.tooltip_due();
+                if renderer.needs_redraw()
+                    || html_renderer.needs_redraw()
+                    || tooltip_due.is_some_and(|due| due <= Instant::now())
+                {
+                    display.gl_window().window().request_redraw();
+                } else if let Some(due) = tooltip_due {
+                    *control_flow = ControlFlow::WaitUntil(due);
+                }
+            }
+            Event::RedrawRequested(_) => {
+                let frame_start = Instant::now();
+                let mut target = display.draw();
+                renderer.begin(&mut target);
+                {
+                    html_renderer.render(&mut renderer, &mut target);
+                }
+
+                // the scrollbar is drawn in window pixels, so the page is
+                // measured at its zoomed size
+                let zoom = renderer.zoom();
+                let screen_size = renderer.screen_size();
+                renderer.draw_scrollbar(
+                    &mut target,
+                    html_renderer.scroll_y() * zoom,
+                    html_renderer.content_height() * zoom,
+                    screen_size,
+                );
+                if debug_overlay {
+                    let text = debug_overlay_text(
+                        frame_time,
+                        renderer.last_frame_stats(),
+                    );
+                    renderer.draw_debug_overlay(&mut target, &text);
+                }
+                renderer.end(&mut target);
+                target.finish().unwrap();
+                frame_time = frame_start.elapsed();
+            }
+            _ => {}
         }
+    });
+}
+
+/// Pixels scrolled for one wheel notch.
+const SCROLL_LINE_HEIGHT: f32 = 40.0;
 
-        let screen_dims = display.get_framebuffer_dimensions();
-        renderer.update_dimension(screen_dims);
+/// Converts a wheel delta into how far the page moves down in pixels.
+fn scroll_delta(delta: MouseScrollDelta) -> f32 {
+    scroll_deltas(delta, false).1
+}
 
-        renderer.begin();
-        {
-            html_renderer.render(&mut renderer, &mut display);
+/// Converts a wheel delta into how far to scroll right and down. Holding
+/// shift turns the vertical wheel into a horizontal one.
+fn scroll_deltas(delta: MouseScrollDelta, shift: bool) -> (f32, f32) {
+    let (x, y) = match delta {
+        MouseScrollDelta::LineDelta(x, y) => {
+            (-x * SCROLL_LINE_HEIGHT, -y * SCROLL_LINE_HEIGHT)
         }
-        renderer.end(&mut display);
-    });
+        MouseScrollDelta::PixelDelta(position) => {
+            (-position.x as f32, -position.y as f32)
+        }
+    };
+
+    if shift {
+        (x + y, 0.0)
+    } else {
+        (x, y)
+    }
+}
+
+/// Zoom change for one wheel notch.
+const ZOOM_STEP: f32 = 1.1;
+
+/// Converts a wheel delta into the factor the zoom is multiplied with,
+/// scrolling up zooms in.
+fn zoom_factor(delta: MouseScrollDelta) -> f32 {
+    let notches = -scroll_delta(delta) / SCROLL_LINE_HEIGHT;
+    ZOOM_STEP.powf(notches)
+}
+
+/// Extra mouse buttons that go back and forward through the history, as
+/// numbered on X11 and Wayland.
+const BACK_BUTTON: u16 = 8;
+const FORWARD_BUTTON: u16 = 9;
+
+/// A move through the history of visited pages.
+#[derive(Debug, PartialEq)]
+enum Navigation {
+    Back,
+    Forward,
+}
+
+/// What pressing a key does to the window.
+#[derive(Debug, PartialEq)]
+enum KeyAction {
+    Quit,
+    /// Scrolls the page down by this many pixels.
+    Scroll(f32),
+    Navigate(Navigation),
+    ToggleDebugOverlay,
+    /// Searches the page for the selected text, there is no search box.
+    /// Nothing selected ends the search.
+    Find,
+    FindNext,
+    FindPrevious,
+}
+
+fn key_action(
+    key: VirtualKeyCode,
+    modifiers: ModifiersState,
+    viewport_height: f32,
+) -> Option<KeyAction> {
+    match key {
+        VirtualKeyCode::Escape => Some(KeyAction::Quit),
+        VirtualKeyCode::F3 => Some(KeyAction::ToggleDebugOverlay),
+        VirtualKeyCode::Left if modifiers.alt() => {
+            Some(KeyAction::Navigate(Navigation::Back))
+        }
+        VirtualKeyCode::Right if modifiers.alt() => {
+            Some(KeyAction::Navigate(Navigation::Forward))
+        }
+        VirtualKeyCode::F if modifiers.ctrl() => Some(KeyAction::Find),
+        VirtualKeyCode::G if modifiers.ctrl() && modifiers.shift() => {
+            Some(KeyAction::FindPrevious)
+        }
+        VirtualKeyCode::G if modifiers.ctrl() => Some(KeyAction::FindNext),
+        _ => key_scroll_delta(key, viewport_height).map(KeyAction::Scroll),
+    }
+}
+
+/// Handles a pressed key. Quitting is done right away through `app`, any
+/// other action is returned for the caller to carry out.
+fn press_key(
+    app: &mut App,
+    key: VirtualKeyCode,
+    modifiers: ModifiersState,
+    viewport_height: f32,
+    control_flow: &mut ControlFlow,
+) -> Option<KeyAction> {
+    let action = key_action(key, modifiers, viewport_height);
+    if action == Some(KeyAction::Quit) {
+        app.quit(control_flow);
+    }
+    action
+}
+
+/// Lines shown by the debug overlay. The counts are from the frame before
+/// the one the overlay is drawn in, which is still being drawn.
+fn debug_overlay_text(frame_time: Duration, stats: RenderStats) -> String {
+    format!(
+        "frame  {:.2} ms\nquads  {}\ndraws  {}\nglyphs {}",
+        frame_time.as_secs_f64() * 1000.0,
+        stats.quads_drawn,
+        stats.draw_calls,
+        stats.glyphs_queued
+    )
+}
+
+/// Loads the previous or next page of the history, if there is one.
+fn navigate(html_renderer: &mut HtmlRenderer, navigation: Navigation) {
+    let result = match navigation {
+        Navigation::Back => html_renderer.go_back(),
+        Navigation::Forward => html_renderer.go_forward(),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Failed to load page: {}", err);
+    }
+}
+
+/// How far a key scrolls the page down in pixels. Home and End scroll by
+/// an unbounded amount and rely on the clamping to stop at the page edges.
+fn key_scroll_delta(key: VirtualKeyCode, viewport_height: f32) -> Option<f32> {
+    match key {
+        VirtualKeyCode::Up => Some(-SCROLL_LINE_HEIGHT),
+        VirtualKeyCode::Down => Some(SCROLL_LINE_HEIGHT),
+        VirtualKeyCode::PageUp => Some(-viewport_height),
+        VirtualKeyCode::PageDown => Some(viewport_height),
+        VirtualKeyCode::Home => Some(f32::NEG_INFINITY),
+        VirtualKeyCode::End => Some(f32::INFINITY),
+        _ => None,
+    }
+}
+
+/// Loads a page given on the command line, either a local path, a `data:`
+/// URL or (with the `net` feature) an http(s) URL.
+fn load_target(
+    html_renderer: &mut HtmlRenderer,
+    target: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if is_data_url(target) {
+        return Ok(html_renderer.load_data_url(target)?);
+    }
+
+    #[cfg(feature = "net")]
+    if target.starts_with("http://") || target.starts_with("https://") {
+        return Ok(html_renderer.load_url(target)?);
+    }
+
+    Ok(html_renderer.load_file(Path::new(target))?)
+}
+
+/// Loads the page a clicked link points to, relative links are resolved
+/// against the page they are on. Links to `#id` only scroll to the element
+/// on the current page.
+fn follow_link(html_renderer: &mut HtmlRenderer, href: &str) {
+    if let Some(id) = href.strip_prefix('#') {
+        html_renderer.scroll_to_element(id);
+        return;
+    }
+
+    let target = html_renderer.resolve_link(href);
+    if let Err(err) = load_target(html_renderer, &target) {
+        eprintln!("Failed to load {}: {}", target, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn test_escape_quits() {
+        let none = ModifiersState::empty();
+        assert_eq!(
+            key_action(VirtualKeyCode::Escape, none, 600.0),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(
+            key_action(VirtualKeyCode::PageDown, none, 600.0),
+            Some(KeyAction::Scroll(600.0))
+        );
+        assert_eq!(key_action(VirtualKeyCode::A, none, 600.0), None);
+        assert_eq!(
+            key_action(VirtualKeyCode::F3, none, 600.0),
+            Some(KeyAction::ToggleDebugOverlay)
+        );
+
+        // the embedder's hook runs on the way out
+        let quit = Rc::new(Cell::new(false));
+        let mut app = App::new();
+        app.set_on_quit({
+            let quit = quit.clone();
+            move || quit.set(true)
+        });
+        let mut control_flow = ControlFlow::Wait;
+        press_key(
+            &mut app,
+            VirtualKeyCode::PageDown,
+            none,
+            600.0,
+            &mut control_flow,
+        );
+        assert!(!quit.get());
+        assert_eq!(control_flow, ControlFlow::Wait);
+        press_key(
+            &mut app,
+            VirtualKeyCode::Escape,
+            none,
+            600.0,
+            &mut control_flow,
+        );
+        assert!(quit.get());
+        assert_eq!(control_flow, ControlFlow::Exit);
+    }
+
+    #[test]
+    fn test_alt_arrows_navigate() {
+        assert_eq!(
+            key_action(VirtualKeyCode::Left, ModifiersState::ALT, 600.0),
+            Some(KeyAction::Navigate(Navigation::Back))
+        );
+        assert_eq!(
+            key_action(VirtualKeyCode::Right, ModifiersState::ALT, 600.0),
+            Some(KeyAction::Navigate(Navigation::Forward))
+        );
+        // without alt the page does not scroll sideways either
+        assert_eq!(
+            key_action(VirtualKeyCode::Left, ModifiersState::empty(), 600.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_keys() {
+        assert_eq!(
+            key_action(VirtualKeyCode::F, ModifiersState::CTRL, 600.0),
+            Some(KeyAction::Find)
+        );
+        assert_eq!(
+            key_action(VirtualKeyCode::G, ModifiersState::CTRL, 600.0),
+            Some(KeyAction::FindNext)
+        );
+        let ctrl_shift = ModifiersState::CTRL | ModifiersState::SHIFT;
+        assert_eq!(
+            key_action(VirtualKeyCode::G, ctrl_shift, 600.0),
+            Some(KeyAction::FindPrevious)
+        );
+        assert_eq!(
+            key_action(VirtualKeyCode::F, ModifiersState::empty(), 600.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_shift_wheel_scrolls_sideways() {
+        let notch_down = MouseScrollDelta::LineDelta(0.0, -1.0);
+        assert_eq!(scroll_deltas(notch_down, false), (0.0, SCROLL_LINE_HEIGHT));
+        assert_eq!(scroll_deltas(notch_down, true), (SCROLL_LINE_HEIGHT, 0.0));
+
+        let tilt_right = MouseScrollDelta::LineDelta(-1.0, 0.0);
+        assert_eq!(scroll_deltas(tilt_right, false), (SCROLL_LINE_HEIGHT, 0.0));
+    }
+
+    #[test]
+    fn test_follow_relative_link() {
+        let dir = std::env::temp_dir()
+            .join(format!("browser-rs-follow-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.html"), dir.join("b.html"));
+        std::fs::write(&a, "<a href=\"b.html\">b</a>").unwrap();
+        std::fs::write(&b, "<p>b</p>").unwrap();
+
+        // the link is next to the page, not the working directory
+        let mut html_renderer = HtmlRenderer::new();
+        load_target(&mut html_renderer, a.to_str().unwrap()).unwrap();
+        follow_link(&mut html_renderer, "b.html");
+        assert_eq!(html_renderer.history().current(), b.to_str());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
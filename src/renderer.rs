@@ -1,17 +1,89 @@
+use std::error::Error;
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::fmt;
+use std::rc::Rc;
+
+use glium::backend::{Context, Facade};
+use glium::framebuffer::SimpleFrameBuffer;
 use glium::index::PrimitiveType;
-use glium::{Display, IndexBuffer, Program, Surface, VertexBuffer};
+use glium::texture::{
+    MipmapsOption, RawImage2d, SrgbFormat, SrgbTexture2d, Texture2d,
+};
+use glium::{
+    Blend, DrawParameters, Frame, IndexBuffer, Program, Surface, VertexBuffer,
+};
 use glium_glyph::{GlyphBrush, GlyphBrushBuilder};
-use glyph_brush::ab_glyph::FontArc;
-use glyph_brush::{HorizontalAlign, Layout, Section, Text, VerticalAlign};
+use glyph_brush::ab_glyph::{Font, FontArc, InvalidFont, PxScale, ScaleFont};
+use glyph_brush::{
+    FontId, GlyphPositioner, HorizontalAlign, Layout, Section, SectionGeometry,
+    SectionText, Text, VerticalAlign,
+};
 
 use crate::camera::Camera;
-use crate::lalg::mat4_to_array;
+use crate::lalg::{mat4_to_array, Rect};
+use crate::selection::GlyphBox;
+use crate::texture::{load_texture, TextureCache};
 
+/// Number of quads drawn in one batch unless `Renderer::with_capacity` asks
+/// for a different amount.
 const QUAD_MAX_BATCHES: usize = 20000;
-const QUAD_MAX_VERTICES: usize = 4 * QUAD_MAX_BATCHES;
-const QUAD_MAX_INDICES: usize = 6 * QUAD_MAX_BATCHES;
+/// Size of the glyph atlas the brush starts with, it grows when the glyphs
+/// of a frame do not fit.
+const TEXT_CACHE_SIZE: (u32, u32) = (256, 256);
+/// Triangles each corner of a rounded quad is made of.
+const ROUNDED_CORNER_SEGMENTS: usize = 8;
 
-#[derive(Copy, Clone)]
+/// Color frames are cleared to unless the page sets its own background.
+pub const DEFAULT_CLEAR_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// Height of a line of text as a multiple of its font size, unless a
+/// `line-height` says otherwise.
+pub const DEFAULT_LINE_HEIGHT: f32 = 2.0;
+
+/// Width of the scrollbar on the right edge of the screen.
+pub const SCROLLBAR_WIDTH: f32 = 10.0;
+/// The thumb never gets shorter than this, so it can still be seen on very
+/// long pages.
+const SCROLLBAR_MIN_THUMB_HEIGHT: f32 = 20.0;
+const SCROLLBAR_TRACK_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const SCROLLBAR_THUMB_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+const DEBUG_OVERLAY_FONT_SIZE: f32 = 14.0;
+const DEBUG_OVERLAY_PADDING: f32 = 4.0;
+const DEBUG_OVERLAY_BG_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.7];
+const DEBUG_OVERLAY_FG_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+const DEFAULT_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+const DEFAULT_BOLD_FONT: &[u8] =
+    include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+const DEFAULT_ITALIC_FONT: &[u8] =
+    include_bytes!("../assets/fonts/DejaVuSans-Oblique.ttf");
+const DEFAULT_MONOSPACE_FONT: &[u8] =
+    include_bytes!("../assets/fonts/DejaVuSansMono.ttf");
+
+/// Returned when font data given to the renderer can not be parsed.
+#[derive(Debug)]
+pub struct FontError(InvalidFont);
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to load font: {}", self.0)
+    }
+}
+
+impl Error for FontError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl From<InvalidFont> for FontError {
+    fn from(err: InvalidFont) -> Self {
+        Self(err)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 struct QuadVertex {
     pub position: [f32; 2],
     pub color: [f32; 4],
@@ -19,6 +91,118 @@ struct QuadVertex {
 
 implement_vertex!(QuadVertex, position, color);
 
+#[derive(Copy, Clone)]
+struct ImageVertex {
+    pub position: [f32; 2],
+    pub tex_coords: [f32; 2],
+}
+
+implement_vertex!(ImageVertex, position, tex_coords);
+
+/// How much was drawn in a frame, as returned by `Renderer::end`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderStats {
+    pub quads_drawn: usize,
+    /// Quad batches and images sent to the GPU, text not counted.
+    pub draw_calls: usize,
+    /// Characters queued as text, whitespace not counted.
+    pub glyphs_queued: usize,
+}
+
+/// Quads waiting to be drawn together in a single draw call.
+struct QuadBatch {
+    vertices: Vec<QuadVertex>,
+    index_count: usize,
+    max_quads: usize,
+}
+
+impl QuadBatch {
+    fn new(max_quads: usize) -> Self {
+        assert!(max_quads > 0, "a quad batch needs room for one quad");
+
+        Self {
+            vertices: Vec::with_capacity(4 * max_quads),
+            index_count: 0,
+            max_quads,
+        }
+    }
+
+    fn max_vertices(&self) -> usize {
+        4 * self.max_quads
+    }
+
+    fn max_indices(&self) -> usize {
+        6 * self.max_quads
+    }
+
+    /// Whether one more quad still fits into both the vertex and the index
+    /// buffer.
+    fn has_room(&self) -> bool {
+        self.vertices.len() + 4 <= self.max_vertices()
+            && self.index_count + 6 <= self.max_indices()
+    }
+
+    /// Adds a quad to the batch. When the batch is full it is handed to
+    /// `flush` first, so no quad is ever dropped.
+    fn push(
+        &mut self,
+        quad: [QuadVertex; 4],
+        flush: impl FnOnce(&[QuadVertex], usize),
+    ) {
+        if !self.has_room() {
+            self.flush(flush);
+        }
+
+        self.vertices.extend_from_slice(&quad);
+        self.index_count += 6;
+    }
+
+    /// Hands the batched vertices and their index count to `flush` and
+    /// empties the batch. Nothing is flushed when the batch is empty.
+    fn flush(&mut self, flush: impl FnOnce(&[QuadVertex], usize)) {
+        if self.index_count > 0 {
+            flush(&self.vertices, self.index_count);
+        }
+
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        // keeps the capacity for the next frame
+        self.vertices.clear();
+        self.index_count = 0;
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum FontStyle {
+    #[default]
+    Regular,
+    Bold,
+    Italic,
+    /// Fixed width face used for preformatted text.
+    Monospace,
+}
+
+impl FontStyle {
+    /// Id of the face used for this style. The renderer registers its fonts
+    /// with the glyph brush in the same order as the variants.
+    pub fn font_id(self) -> FontId {
+        FontId(self as usize)
+    }
+}
+
+/// A piece of text with its own size, color and face. Runs drawn together
+/// share one layout, so lines wrap across them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextRun<'a> {
+    pub text: &'a str,
+    pub size: f32,
+    pub color: [f32; 4],
+    pub style: FontStyle,
+}
+
 pub struct TextDrawConfig {
     pub screen_pos: (f32, f32),
     pub bounds: (f32, f32),
@@ -26,55 +210,316 @@ pub struct TextDrawConfig {
     pub bg_color: [f32; 4],
     pub h_align: HorizontalAlign,
     pub v_align: VerticalAlign,
+    pub style: FontStyle,
 }
 
 impl Default for TextDrawConfig {
     fn default() -> Self {
         Self {
-            screen_pos: (0.0, 0.0).into(),
+            screen_pos: (0.0, 0.0),
             bounds: (f32::INFINITY, f32::INFINITY),
             fg_color: [0.0, 0.0, 0.0, 1.0],
             bg_color: [0.0, 0.0, 0.0, 0.0],
             h_align: HorizontalAlign::Left,
             v_align: VerticalAlign::Top,
+            style: FontStyle::Regular,
+        }
+    }
+}
+
+/// Drawing operations used to paint a page. `Renderer` provides them through
+/// `FrameCanvas`, which lets the html renderer be exercised without a GPU.
+pub trait Canvas {
+    fn screen_size(&self) -> (u32, u32);
+
+    fn draw_quad(
+        &mut self,
+        screen_pos: (f32, f32),
+        bounds: (f32, f32),
+        color: [f32; 4],
+    );
+
+    fn draw_text(&mut self, text: &str, size: f32, cfg: TextDrawConfig);
+
+    /// Fills a box whose corners are rounded by `radius`. Canvases that can
+    /// not round corners draw a plain quad.
+    fn draw_rounded_quad(
+        &mut self,
+        screen_pos: (f32, f32),
+        bounds: (f32, f32),
+        _radius: f32,
+        color: [f32; 4],
+    ) {
+        self.draw_quad(screen_pos, bounds, color);
+    }
+
+    /// Factor everything drawn is scaled by around the center of the
+    /// screen.
+    fn zoom(&self) -> f32 {
+        1.0
+    }
+
+    /// Sets the color behind everything drawn, filling the screen with it.
+    /// Only called before anything else is drawn in a frame.
+    fn set_clear_color(&mut self, _color: [f32; 4]) {}
+
+    /// Where the characters of `text` end up when it is drawn with `cfg`,
+    /// in text order. Canvases that can not lay out glyphs report none.
+    fn glyph_boxes(
+        &self,
+        _text: &str,
+        _size: f32,
+        _cfg: &TextDrawConfig,
+    ) -> Vec<GlyphBox> {
+        Vec::new()
+    }
+
+    /// Draws the outline of `rect` as four quads lying inside of it.
+    /// Nothing is drawn for a zero thickness.
+    fn draw_border(&mut self, rect: Rect, thickness: f32, color: [f32; 4]) {
+        if thickness <= 0.0 {
+            return;
         }
+
+        for side in border_rects(rect, [thickness; 4]) {
+            self.draw_quad((side.x, side.y), (side.width, side.height), color);
+        }
+    }
+
+    /// Draws the image at `src` stretched over the given box, with its
+    /// alpha multiplied by `opacity`. Returns `false` when the image can
+    /// not be drawn, so a placeholder can be shown instead.
+    fn draw_image(
+        &mut self,
+        _src: &str,
+        _screen_pos: (f32, f32),
+        _bounds: (f32, f32),
+        _opacity: f32,
+    ) -> bool {
+        false
+    }
+
+    /// Natural size of the image at `src`, or `None` when it can not be
+    /// loaded.
+    fn image_size(&mut self, _src: &str) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// `(width, height)` `text` takes up once it is wrapped to `max_width`,
+    /// with every line `line_height` pixels high. Canvases that can not lay
+    /// out glyphs only count the explicit line breaks and report no width.
+    fn measure_text(
+        &self,
+        text: &str,
+        _size: f32,
+        _style: FontStyle,
+        _max_width: Option<f32>,
+        line_height: f32,
+    ) -> (f32, f32) {
+        if text.is_empty() {
+            return (0.0, 0.0);
+        }
+
+        (0.0, height_of_lines(count_lines(text), line_height))
+    }
+}
+
+/// A `Renderer` paired with the frame it draws into, usually the window's
+/// but it can be any surface.
+pub struct FrameCanvas<'r, 'a, 't, S: Surface = Frame> {
+    pub renderer: &'r mut Renderer<'a>,
+    pub target: &'t mut S,
+}
+
+impl<S: Surface> Canvas for FrameCanvas<'_, '_, '_, S> {
+    fn screen_size(&self) -> (u32, u32) {
+        self.renderer.screen_size()
+    }
+
+    fn zoom(&self) -> f32 {
+        self.renderer.zoom()
+    }
+
+    fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.renderer.set_clear_color(color);
+
+        let [r, g, b, a] = color;
+        self.target.clear_color(r, g, b, a);
+    }
+
+    fn draw_quad(
+        &mut self,
+        screen_pos: (f32, f32),
+        bounds: (f32, f32),
+        color: [f32; 4],
+    ) {
+        self.renderer
+            .draw_quad(self.target, screen_pos, bounds, color);
+    }
+
+    fn draw_text(&mut self, text: &str, size: f32, cfg: TextDrawConfig) {
+        self.renderer.draw_text(self.target, text, size, cfg);
+    }
+
+    fn draw_rounded_quad(
+        &mut self,
+        screen_pos: (f32, f32),
+        bounds: (f32, f32),
+        radius: f32,
+        color: [f32; 4],
+    ) {
+        self.renderer.draw_rounded_quad(
+            self.target,
+            screen_pos,
+            bounds,
+            radius,
+            color,
+        );
+    }
+
+    fn glyph_boxes(
+        &self,
+        text: &str,
+        size: f32,
+        cfg: &TextDrawConfig,
+    ) -> Vec<GlyphBox> {
+        self.renderer.glyph_boxes(text, size, cfg)
+    }
+
+    fn draw_border(&mut self, rect: Rect, thickness: f32, color: [f32; 4]) {
+        self.renderer
+            .draw_border(self.target, rect, thickness, color);
+    }
+
+    fn draw_image(
+        &mut self,
+        src: &str,
+        screen_pos: (f32, f32),
+        bounds: (f32, f32),
+        opacity: f32,
+    ) -> bool {
+        self.renderer
+            .draw_image(self.target, src, screen_pos, bounds, opacity)
+    }
+
+    fn image_size(&mut self, src: &str) -> Option<(u32, u32)> {
+        self.renderer.image_size(src)
+    }
+
+    fn measure_text(
+        &self,
+        text: &str,
+        size: f32,
+        style: FontStyle,
+        max_width: Option<f32>,
+        line_height: f32,
+    ) -> (f32, f32) {
+        self.renderer
+            .measure_text(text, size, style, max_width, line_height)
     }
 }
 
 pub struct Renderer<'a> {
+    // the OpenGL context everything is drawn with, either a window or an
+    // off-screen one
+    context: Rc<Context>,
     camera: Camera,
     screen_size: (u32, u32),
+    // regular, bold, italic and monospace faces in `FontStyle` order
+    fonts: Vec<FontArc>,
     glyph_brush: GlyphBrush<'a, FontArc>,
+    // atlas size the glyph brush is built with, kept for rebuilding it
+    text_cache_size: (u32, u32),
     // quad resources
     quad_vb: VertexBuffer<QuadVertex>,
     quad_ib: IndexBuffer<u32>,
     quad_shader: Program,
-    quad_index_count: i32,
-    quad_vertices: Vec<QuadVertex>,
+    quad_batch: QuadBatch,
+    // image resources, drawn one textured quad at a time
+    image_vb: VertexBuffer<ImageVertex>,
+    image_shader: Program,
+    images: TextureCache,
+    clear_color: [f32; 4],
+    // counted since `begin`, and what the last finished frame drew
+    frame_stats: RenderStats,
+    last_frame_stats: RenderStats,
+    // set when something changed since the last presented frame
+    needs_redraw: bool,
 }
 
 impl<'a> Renderer<'a> {
-    pub fn new(display: &Display) -> Self {
-        let quad_vertices = Vec::with_capacity(QUAD_MAX_VERTICES);
+    pub fn new(display: &impl Facade) -> Self {
+        Self::with_capacity(display, QUAD_MAX_BATCHES)
+    }
 
-        // Define indices for quads
-        let mut quad_indices = Vec::with_capacity(QUAD_MAX_INDICES);
-        let mut offset = 0;
-        let mut i = 0;
-        while i < QUAD_MAX_INDICES {
-            quad_indices.push(offset + 0);
-            quad_indices.push(offset + 1);
-            quad_indices.push(offset + 2);
-            quad_indices.push(offset + 2);
-            quad_indices.push(offset + 3);
-            quad_indices.push(offset + 0);
+    /// Creates a renderer with the default fonts whose quad buffers hold
+    /// `max_quads` quads before a batch has to be flushed.
+    pub fn with_capacity(display: &impl Facade, max_quads: usize) -> Self {
+        let fonts = default_fonts().expect("embedded fonts should be valid");
+        Self::build(display, fonts, max_quads, TEXT_CACHE_SIZE)
+    }
 
-            i += 6;
-            offset += 4;
-        }
+    /// Creates a renderer with the default fonts whose glyph atlas starts
+    /// out `(width, height)` pixels large. A larger atlas saves it from
+    /// growing when many fonts or sizes are drawn.
+    pub fn with_text_cache_size(
+        display: &impl Facade,
+        text_cache_size: (u32, u32),
+    ) -> Self {
+        let fonts = default_fonts().expect("embedded fonts should be valid");
+        Self::build(display, fonts, QUAD_MAX_BATCHES, text_cache_size)
+    }
+
+    /// Creates a renderer that draws regular text with the given TTF/OTF
+    /// data. Bold, italic and monospace text use the embedded default
+    /// faces.
+    pub fn with_font(
+        display: &impl Facade,
+        font_bytes: &[u8],
+    ) -> Result<Self, FontError> {
+        Self::with_fonts(
+            display,
+            font_bytes,
+            DEFAULT_BOLD_FONT,
+            DEFAULT_ITALIC_FONT,
+        )
+    }
+
+    /// Creates a renderer with separate regular, bold and italic faces.
+    /// Monospace text uses the embedded default face.
+    pub fn with_fonts(
+        display: &impl Facade,
+        regular: &[u8],
+        bold: &[u8],
+        italic: &[u8],
+    ) -> Result<Self, FontError> {
+        let fonts = vec![
+            load_font(regular)?,
+            load_font(bold)?,
+            load_font(italic)?,
+            load_font(DEFAULT_MONOSPACE_FONT)?,
+        ];
+
+        Ok(Self::build(
+            display,
+            fonts,
+            QUAD_MAX_BATCHES,
+            TEXT_CACHE_SIZE,
+        ))
+    }
+
+    fn build(
+        display: &impl Facade,
+        fonts: Vec<FontArc>,
+        max_quads: usize,
+        text_cache_size: (u32, u32),
+    ) -> Self {
+        let quad_batch = QuadBatch::new(max_quads);
+        let quad_indices = quad_indices(max_quads);
 
         let quad_vb =
-            VertexBuffer::empty_dynamic(display, QUAD_MAX_VERTICES).unwrap();
+            VertexBuffer::empty_dynamic(display, quad_batch.max_vertices())
+                .unwrap();
         let quad_ib = IndexBuffer::new(
             display,
             PrimitiveType::TrianglesList,
@@ -92,154 +537,1378 @@ impl<'a> Renderer<'a> {
             None,
         )
         .unwrap();
-        let quad_index_count = 0;
+
+        let image_vb = VertexBuffer::empty_dynamic(display, 4).unwrap();
+        let image_shader = Program::from_source(
+            display,
+            include_str!("shaders/image.vert"),
+            include_str!("shaders/image.frag"),
+            None,
+        )
+        .unwrap();
 
         let camera = Camera::default();
 
         let screen_size = (0, 0);
 
-        // Initialize glyph_brush with font
-        // TODO: let user select their font
-        let font = FontArc::try_from_slice(include_bytes!(
-            "../assets/fonts/Roboto-Regular.ttf"
-        ) as &[u8])
-        .unwrap();
-
-        let glyph_brush = GlyphBrushBuilder::using_font(font).build(display);
+        let glyph_brush =
+            build_glyph_brush(display, fonts.clone(), text_cache_size);
 
         Self {
+            context: display.get_context().clone(),
             camera,
             screen_size,
+            fonts,
             glyph_brush,
+            text_cache_size,
             quad_vb,
             quad_ib,
             quad_shader,
-            quad_index_count,
-            quad_vertices,
+            quad_batch,
+            image_vb,
+            image_shader,
+            images: TextureCache::new(),
+            clear_color: DEFAULT_CLEAR_COLOR,
+            frame_stats: RenderStats::default(),
+            last_frame_stats: RenderStats::default(),
+            needs_redraw: true,
         }
     }
 
+    /// Replaces the font used for regular text. This rebuilds the glyph
+    /// brush, so every glyph cached for the previous font is thrown away and
+    /// gets rasterized again on the next draw.
+    pub fn set_font(&mut self, font_bytes: &[u8]) -> Result<(), FontError> {
+        self.fonts[FontStyle::Regular as usize] = load_font(font_bytes)?;
+        self.clear_text_cache();
+
+        Ok(())
+    }
+
+    /// Throws away every glyph cached in the atlas by rebuilding the glyph
+    /// brush at its initial size. Useful after drawing text at many
+    /// different sizes, like when zooming a lot, filled the atlas up.
+    pub fn clear_text_cache(&mut self) {
+        self.glyph_brush = build_glyph_brush(
+            &self.context,
+            self.fonts.clone(),
+            self.text_cache_size,
+        );
+        self.needs_redraw = true;
+    }
+
+    pub fn screen_size(&self) -> (u32, u32) {
+        self.screen_size
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.camera.zoom()
+    }
+
+    /// Scales everything drawn by `factor` around the center of the
+    /// screen. The factor is clamped between `MIN_ZOOM` and `MAX_ZOOM`.
+    pub fn set_zoom(&mut self, factor: f32) {
+        let zoom = self.camera.zoom();
+        self.camera.set_zoom(factor);
+
+        if self.camera.zoom() != zoom {
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn clear_color(&self) -> [f32; 4] {
+        self.clear_color
+    }
+
+    /// Sets the color `begin` clears frames to, white by default.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        if color != self.clear_color {
+            self.clear_color = color;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Whether the frame has to be drawn again, because the window size or
+    /// the fonts changed since the last `end`.
+    pub fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    pub fn request_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    /// `(width, height)` of `text` once glyph_brush wraps it to
+    /// `max_width`, without queuing anything for drawing.
+    pub fn measure_text(
+        &self,
+        text: &str,
+        size: f32,
+        style: FontStyle,
+        max_width: Option<f32>,
+        line_height: f32,
+    ) -> (f32, f32) {
+        measure_text(&self.fonts, text, size, style, max_width, line_height)
+    }
+
     pub fn update_dimension(&mut self, dims: (u32, u32)) {
+        if dims != self.screen_size {
+            self.needs_redraw = true;
+        }
+
         self.screen_size = dims;
         self.camera.screen_size = dims;
     }
 
-    pub fn begin(&mut self) {
-        // Clear the quad vertices buffer without changing capacity
-        self.quad_vertices.clear();
-        self.quad_index_count = 0;
+    /// Starts a frame by clearing `target` and the pending quad batch.
+    pub fn begin(&mut self, target: &mut impl Surface) {
+        let [r, g, b, a] = self.clear_color;
+        target.clear_color(r, g, b, a);
+
+        self.quad_batch.clear();
+        self.frame_stats = RenderStats::default();
     }
 
-    pub fn end(&mut self, display: &mut Display) {
-        let mut target = display.draw();
-        target.clear_color(1.0, 1.0, 1.0, 1.0);
+    /// What the last frame drawn between `begin` and `end` contained.
+    pub fn last_frame_stats(&self) -> RenderStats {
+        self.last_frame_stats
+    }
 
-        let view_matrix = mat4_to_array(&self.camera.get_view());
-        let projection_matrix = mat4_to_array(&self.camera.get_projection());
+    /// Draws a frame of the given size into an off-screen texture and reads
+    /// it back as RGBA bytes, with the top row first. `draw` gets the
+    /// renderer and the target between `begin` and `end`. The texture is
+    /// sRGB like the window's framebuffer, so the pixels match what is
+    /// shown on screen.
+    pub fn render_to_buffer(
+        &mut self,
+        (width, height): (u32, u32),
+        draw: impl FnOnce(&mut Self, &mut SimpleFrameBuffer),
+    ) -> Vec<u8> {
+        let texture = SrgbTexture2d::empty_with_format(
+            &self.context,
+            SrgbFormat::U8U8U8U8,
+            MipmapsOption::NoMipmap,
+            width,
+            height,
+        )
+        .unwrap();
 
-        if self.quad_index_count > 0 {
-            // Update GPU buffer with vertices
-            self.quad_vb
-                .slice_mut(0..self.quad_vertices.len())
-                .unwrap()
-                .write(&self.quad_vertices);
+        // the projection has to match the texture while drawing
+        let screen_size = self.screen_size;
+        self.update_dimension((width, height));
+        {
+            let mut target =
+                SimpleFrameBuffer::new(&self.context, &texture).unwrap();
+            self.begin(&mut target);
+            draw(self, &mut target);
+            self.end(&mut target);
+        }
+        self.update_dimension(screen_size);
+        self.needs_redraw = true;
 
-            let uniforms = uniform! {
-                view: view_matrix,
-                proj: projection_matrix,
-            };
+        let image: RawImage2d<u8> = texture.read();
+        flip_rows(&image.data, width as usize * 4)
+    }
 
-            target
-                .draw(
-                    &self.quad_vb,
-                    &self.quad_ib,
-                    &self.quad_shader,
-                    &uniforms,
-                    &Default::default(),
-                )
-                .unwrap();
-        }
+    /// Draws the remaining quads and all queued text into `target` and
+    /// returns what the frame drew. The caller still has to finish the
+    /// frame.
+    pub fn end(&mut self, target: &mut impl Surface) -> RenderStats {
+        self.flush_quads(target);
+        self.flush_text(target);
 
-        // Render all queued text
-        self.glyph_brush.draw_queued(display, &mut target);
+        self.last_frame_stats = self.frame_stats;
+        self.needs_redraw = false;
+        self.last_frame_stats
+    }
+
+    /// Draws all queued text into `target`.
+    fn flush_text(&mut self, target: &mut impl Surface) {
+        // placed for the size of the target rather than the window
+        let transform =
+            text_transform(target.get_dimensions(), self.camera.zoom());
+        self.glyph_brush.draw_queued_with_transform(
+            transform,
+            &self.context,
+            target,
+        );
+    }
+
+    /// Draws the batched quads into `target` and empties the batch.
+    fn flush_quads(&mut self, target: &mut impl Surface) {
+        let Self {
+            camera,
+            quad_vb,
+            quad_ib,
+            quad_shader,
+            quad_batch,
+            frame_stats,
+            ..
+        } = self;
 
-        target.finish().unwrap();
+        quad_batch.flush(|vertices, index_count| {
+            frame_stats.draw_calls += 1;
+            draw_quads(
+                target,
+                camera,
+                (quad_vb, quad_ib, quad_shader),
+                vertices,
+                index_count,
+            )
+        });
     }
 
     pub fn draw_quad(
         &mut self,
-        display: &mut Display,
+        target: &mut impl Surface,
         screen_pos: (f32, f32),
         bounds: (f32, f32),
         color: [f32; 4],
     ) {
-        if self.quad_index_count + 6 > QUAD_MAX_INDICES as i32 {
-            self.end(display);
-            self.begin();
+        self.push_quad(target, quad_vertices(screen_pos, bounds, color));
+    }
+
+    /// Fills a box whose corners are rounded by `radius`, made of several
+    /// quads in the quad batch. A radius of zero draws a plain quad.
+    pub fn draw_rounded_quad(
+        &mut self,
+        target: &mut impl Surface,
+        screen_pos: (f32, f32),
+        bounds: (f32, f32),
+        radius: f32,
+        color: [f32; 4],
+    ) {
+        for quad in rounded_quad_vertices(screen_pos, bounds, radius, color) {
+            self.push_quad(target, quad);
         }
+    }
 
-        let v1 = QuadVertex {
-            position: [screen_pos.0, screen_pos.1],
-            color,
-        };
-        let v2 = QuadVertex {
-            position: [screen_pos.0, screen_pos.1 + bounds.1],
-            color,
+    /// Adds a quad to the batch, counted in the frame's stats.
+    fn push_quad(&mut self, target: &mut impl Surface, quad: [QuadVertex; 4]) {
+        self.frame_stats.quads_drawn += 1;
+
+        let Self {
+            camera,
+            quad_vb,
+            quad_ib,
+            quad_shader,
+            quad_batch,
+            frame_stats,
+            ..
+        } = self;
+
+        // a full batch is drawn right away to make room for the new quad
+        quad_batch.push(quad, |vertices, index_count| {
+            frame_stats.draw_calls += 1;
+            draw_quads(
+                target,
+                camera,
+                (quad_vb, quad_ib, quad_shader),
+                vertices,
+                index_count,
+            )
+        });
+    }
+
+    /// Draws the outline of `rect` into the quad batch, one quad for each
+    /// side. Nothing is drawn for a zero thickness.
+    pub fn draw_border(
+        &mut self,
+        target: &mut impl Surface,
+        rect: Rect,
+        thickness: f32,
+        color: [f32; 4],
+    ) {
+        if thickness <= 0.0 {
+            return;
+        }
+
+        for side in border_rects(rect, [thickness; 4]) {
+            self.draw_quad(
+                target,
+                (side.x, side.y),
+                (side.width, side.height),
+                color,
+            );
+        }
+    }
+
+    /// Draws a scrollbar on the right edge of `viewport` for a page of
+    /// `content_height` pixels scrolled down by `scroll`. Unlike everything
+    /// else it is not zoomed. Nothing is drawn when the page fits.
+    pub fn draw_scrollbar(
+        &mut self,
+        target: &mut impl Surface,
+        scroll: f32,
+        content_height: f32,
+        viewport: (u32, u32),
+    ) {
+        let Some((track, thumb)) =
+            scrollbar_rects(scroll, content_height, viewport)
+        else {
+            return;
         };
-        let v3 = QuadVertex {
-            position: [screen_pos.0 + bounds.0, screen_pos.1 + bounds.1],
-            color,
+
+        // quads are projected when the batch is flushed, so the ones drawn
+        // so far have to go out with the zoom still applied
+        self.flush_quads(target);
+        let zoom = self.camera.zoom();
+        self.camera.set_zoom(1.0);
+
+        for (rect, color) in [
+            (track, SCROLLBAR_TRACK_COLOR),
+            (thumb, SCROLLBAR_THUMB_COLOR),
+        ] {
+            self.draw_quad(
+                target,
+                (rect.x, rect.y),
+                (rect.width, rect.height),
+                color,
+            );
+        }
+        self.flush_quads(target);
+        self.camera.set_zoom(zoom);
+    }
+
+    /// Draws `text` in the top left corner of the screen on a dark box, over
+    /// everything drawn so far. Like the scrollbar it is not zoomed.
+    pub fn draw_debug_overlay(
+        &mut self,
+        target: &mut impl Surface,
+        text: &str,
+    ) {
+        // the page has to go out with the zoom still applied, and below
+        // the overlay
+        self.flush_quads(target);
+        self.flush_text(target);
+        let zoom = self.camera.zoom();
+        self.camera.set_zoom(1.0);
+
+        let (width, height) = self.measure_text(
+            text,
+            DEBUG_OVERLAY_FONT_SIZE,
+            FontStyle::Monospace,
+            None,
+            DEBUG_OVERLAY_FONT_SIZE * DEFAULT_LINE_HEIGHT,
+        );
+        let padding = DEBUG_OVERLAY_PADDING;
+        self.draw_quad(
+            target,
+            (0.0, 0.0),
+            (width + 2.0 * padding, height + 2.0 * padding),
+            DEBUG_OVERLAY_BG_COLOR,
+        );
+        self.draw_text(
+            target,
+            text,
+            DEBUG_OVERLAY_FONT_SIZE,
+            TextDrawConfig {
+                screen_pos: (padding, padding),
+                fg_color: DEBUG_OVERLAY_FG_COLOR,
+                style: FontStyle::Monospace,
+                ..Default::default()
+            },
+        );
+        self.flush_quads(target);
+        self.flush_text(target);
+        self.camera.set_zoom(zoom);
+    }
+
+    /// Texture of the image at `src`, loaded the first time it is asked for.
+    fn image(&mut self, src: &str) -> Option<Rc<Texture2d>> {
+        let context = &self.context;
+        self.images
+            .get_or_load(src, |path| load_texture(context, path))
+    }
+
+    pub fn image_size(&mut self, src: &str) -> Option<(u32, u32)> {
+        self.image(src).map(|texture| texture.dimensions())
+    }
+
+    /// Draws the image at `src` stretched over the given box, faded by
+    /// `opacity`. Returns `false` without drawing anything when the image
+    /// can not be loaded.
+    pub fn draw_image(
+        &mut self,
+        target: &mut impl Surface,
+        src: &str,
+        screen_pos: (f32, f32),
+        bounds: (f32, f32),
+        opacity: f32,
+    ) -> bool {
+        let Some(texture) = self.image(src) else {
+            return false;
         };
-        let v4 = QuadVertex {
-            position: [screen_pos.0 + bounds.0, screen_pos.1],
-            color,
+
+        // quads queued so far lie below the image
+        self.flush_quads(target);
+
+        let (x, y) = screen_pos;
+        let (w, h) = bounds;
+        let vertices = [
+            ImageVertex {
+                position: [x, y],
+                tex_coords: [0.0, 0.0],
+            },
+            ImageVertex {
+                position: [x, y + h],
+                tex_coords: [0.0, 1.0],
+            },
+            ImageVertex {
+                position: [x + w, y + h],
+                tex_coords: [1.0, 1.0],
+            },
+            ImageVertex {
+                position: [x + w, y],
+                tex_coords: [1.0, 0.0],
+            },
+        ];
+        self.image_vb.write(&vertices);
+
+        let uniforms = uniform! {
+            view: mat4_to_array(&self.camera.get_view()),
+            proj: mat4_to_array(&self.camera.get_projection()),
+            tex: texture.sampled(),
+            opacity: opacity,
         };
 
-        self.quad_vertices.push(v1);
-        self.quad_vertices.push(v2);
-        self.quad_vertices.push(v3);
-        self.quad_vertices.push(v4);
+        target
+            .draw(
+                &self.image_vb,
+                self.quad_ib.slice(0..6).unwrap(),
+                &self.image_shader,
+                &uniforms,
+                &blended_draw_parameters(),
+            )
+            .unwrap();
+        self.frame_stats.draw_calls += 1;
 
-        self.quad_index_count += 6;
+        true
     }
 
     pub fn draw_text(
         &mut self,
-        display: &mut Display,
+        target: &mut impl Surface,
         text: &str,
         size: f32,
         cfg: TextDrawConfig,
     ) {
-        let section = Section::default()
-            .with_screen_position(cfg.screen_pos)
-            .with_bounds(cfg.bounds)
-            .with_text(vec![Text::new(text)
-                .with_scale(size)
-                .with_color(cfg.fg_color)
-                .with_z(1.0)])
-            .with_layout(
-                Layout::default().h_align(cfg.h_align).v_align(cfg.v_align),
-            );
+        let run = TextRun {
+            text,
+            size,
+            color: cfg.fg_color,
+            style: cfg.style,
+        };
+        self.draw_rich_text(target, &[run], cfg);
+    }
+
+    /// Where the characters of `text` end up when `draw_text` draws it with
+    /// `cfg`, see `glyph_boxes`.
+    pub fn glyph_boxes(
+        &self,
+        text: &str,
+        size: f32,
+        cfg: &TextDrawConfig,
+    ) -> Vec<GlyphBox> {
+        let run = TextRun {
+            text,
+            size,
+            color: cfg.fg_color,
+            style: cfg.style,
+        };
+        glyph_boxes(&self.fonts, &run, cfg, self.text_box_width(cfg))
+    }
+
+    /// Draws `runs` one after another in the box of `cfg`. The colors and
+    /// the face of `cfg` are ignored in favor of the ones of each run.
+    pub fn draw_rich_text(
+        &mut self,
+        target: &mut impl Surface,
+        runs: &[TextRun],
+        cfg: TextDrawConfig,
+    ) {
+        let box_width = self.text_box_width(&cfg);
+        self.frame_stats.glyphs_queued += runs
+            .iter()
+            .flat_map(|run| run.text.chars())
+            .filter(|c| !c.is_whitespace())
+            .count();
 
         // if background color is not transparent then a draw quad
         if cfg.bg_color[3] != 0.0 {
-            let quad_bounds = (
-                if cfg.bounds.0 == f32::INFINITY {
-                    self.screen_size.1 as f32
-                } else {
-                    cfg.bounds.0
-                },
-                get_line_height_of_text(text, size),
-            );
+            let height = runs_height(&self.fonts, runs, box_width);
+            let quad_bounds = (box_width, height);
 
-            self.draw_quad(display, cfg.screen_pos, quad_bounds, cfg.bg_color);
+            self.draw_quad(target, cfg.screen_pos, quad_bounds, cfg.bg_color);
         }
 
-        self.glyph_brush.queue(section);
+        self.glyph_brush
+            .queue(rich_text_section(runs, &cfg, box_width));
+    }
+
+    /// Width text drawn with `cfg` wraps at, unbounded text runs to the
+    /// right edge of the screen.
+    fn text_box_width(&self, cfg: &TextDrawConfig) -> f32 {
+        if cfg.bounds.0.is_finite() {
+            cfg.bounds.0
+        } else {
+            self.screen_size.0 as f32 - cfg.screen_pos.0
+        }
+    }
+}
+
+/// Section drawing `runs` in the box of `cfg`, which is `box_width` wide.
+fn rich_text_section<'r>(
+    runs: &[TextRun<'r>],
+    cfg: &TextDrawConfig,
+    box_width: f32,
+) -> Section<'r> {
+    // `screen_pos` is the top-left corner of the text box, while
+    // glyph_brush anchors centered/right aligned text at its center/right
+    let anchor_x = aligned_x(cfg.screen_pos.0, box_width, cfg.h_align);
+
+    let text = runs
+        .iter()
+        .map(|run| {
+            Text::new(run.text)
+                .with_font_id(run.style.font_id())
+                .with_scale(run.size)
+                .with_color(run.color)
+                .with_z(1.0)
+        })
+        .collect();
+
+    Section::default()
+        .with_screen_position((anchor_x, cfg.screen_pos.1))
+        .with_bounds(cfg.bounds)
+        .with_text(text)
+        .with_layout(
+            Layout::default().h_align(cfg.h_align).v_align(cfg.v_align),
+        )
+}
+
+/// Boxes of the characters of `run` laid out like `rich_text_section`
+/// places it. Line breaks get no box. Each box spans the font's ascent and
+/// descent, so boxes on the same line line up.
+fn glyph_boxes(
+    fonts: &[FontArc],
+    run: &TextRun,
+    cfg: &TextDrawConfig,
+    box_width: f32,
+) -> Vec<GlyphBox> {
+    let section = rich_text_section(std::slice::from_ref(run), cfg, box_width);
+    let glyphs = section.layout.calculate_glyphs(
+        fonts,
+        &SectionGeometry::from(&section),
+        &section.text,
+    );
+
+    let font = fonts[run.style.font_id().0].as_scaled(run.size);
+    glyphs
+        .iter()
+        .filter_map(|glyph| {
+            let ch = run.text[glyph.byte_index..].chars().next()?;
+            let position = glyph.glyph.position;
+            let rect = Rect::new(
+                position.x,
+                position.y - font.ascent(),
+                font.h_advance(glyph.glyph.id),
+                font.ascent() - font.descent(),
+            );
+            Some(GlyphBox { rect, ch })
+        })
+        .collect()
+}
+
+/// Height of `runs` wrapped to `max_width`, measured as if all of the text
+/// had the face of the first run and the largest size of any run.
+fn runs_height(fonts: &[FontArc], runs: &[TextRun], max_width: f32) -> f32 {
+    let Some(first) = runs.first() else {
+        return 0.0;
+    };
+
+    let text: String = runs.iter().map(|run| run.text).collect();
+    let size = runs.iter().map(|run| run.size).fold(0.0, f32::max);
+    let (_, height) = measure_text(
+        fonts,
+        &text,
+        size,
+        first.style,
+        Some(max_width),
+        size * DEFAULT_LINE_HEIGHT,
+    );
+    height
+}
+
+/// Draws `vertices` as quads into `target` with a single draw call.
+fn draw_quads(
+    target: &mut impl Surface,
+    camera: &Camera,
+    (vb, ib, shader): (
+        &mut VertexBuffer<QuadVertex>,
+        &IndexBuffer<u32>,
+        &Program,
+    ),
+    vertices: &[QuadVertex],
+    index_count: usize,
+) {
+    let view_matrix = mat4_to_array(&camera.get_view());
+    let projection_matrix = mat4_to_array(&camera.get_projection());
+
+    // Update GPU buffer with vertices
+    vb.slice_mut(0..vertices.len()).unwrap().write(vertices);
+
+    let uniforms = uniform! {
+        view: view_matrix,
+        proj: projection_matrix,
+    };
+
+    target
+        .draw(
+            &*vb,
+            ib.slice(0..index_count).unwrap(),
+            shader,
+            &uniforms,
+            &blended_draw_parameters(),
+        )
+        .unwrap();
+}
+
+/// Draw parameters for quads and images. Translucent colors are blended
+/// over what was drawn before, opaque ones simply replace it.
+fn blended_draw_parameters() -> DrawParameters<'static> {
+    DrawParameters {
+        blend: Blend::alpha_blending(),
+        ..Default::default()
+    }
+}
+
+/// X coordinate glyph_brush expects for a box starting at `x` with the given
+/// width and alignment.
+fn aligned_x(x: f32, width: f32, h_align: HorizontalAlign) -> f32 {
+    match h_align {
+        HorizontalAlign::Left => x,
+        HorizontalAlign::Center => x + width / 2.0,
+        HorizontalAlign::Right => x + width,
+    }
+}
+
+/// Maps pixel positions of queued text to the target, like glyph_brush's
+/// `draw_queued` does for the window, scaled by `zoom` around the center
+/// like the camera's projection.
+fn text_transform((width, height): (u32, u32), zoom: f32) -> [[f32; 4]; 4] {
+    [
+        [2.0 * zoom / width as f32, 0.0, 0.0, 0.0],
+        [0.0, 2.0 * zoom / height as f32, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [-zoom, -zoom, 0.0, 1.0],
+    ]
+}
+
+/// Reverses the order of the rows in an image. OpenGL reads pixels from
+/// the bottom row up.
+fn flip_rows(pixels: &[u8], row_len: usize) -> Vec<u8> {
+    pixels
+        .chunks_exact(row_len)
+        .rev()
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// Top, right, bottom and left sides of a border drawn inside `rect`, with
+/// the widths given in the same order. The top and bottom sides span the
+/// whole width, so the corners are not covered twice.
+pub fn border_rects(
+    rect: Rect,
+    [top, right, bottom, left]: [f32; 4],
+) -> [Rect; 4] {
+    let Rect {
+        x,
+        y,
+        width,
+        height,
+    } = rect;
+
+    // sides never reach past the opposite edge of the box
+    let top = top.clamp(0.0, height);
+    let bottom = bottom.clamp(0.0, height - top);
+    let left = left.clamp(0.0, width);
+    let right = right.clamp(0.0, width - left);
+    let side_height = height - top - bottom;
+
+    [
+        Rect::new(x, y, width, top),
+        Rect::new(x + width - right, y + top, right, side_height),
+        Rect::new(x, y + height - bottom, width, bottom),
+        Rect::new(x, y + top, left, side_height),
+    ]
+}
+
+/// Track and thumb of a scrollbar on the right edge of `viewport`, or
+/// `None` when the page fits and there is nothing to scroll. The thumb is
+/// as much shorter than the track as the viewport is than the page.
+pub fn scrollbar_rects(
+    scroll: f32,
+    content_height: f32,
+    (width, height): (u32, u32),
+) -> Option<(Rect, Rect)> {
+    let (width, height) = (width as f32, height as f32);
+    let max_scroll = content_height - height;
+    if max_scroll <= 0.0 {
+        return None;
+    }
+
+    let track =
+        Rect::new(width - SCROLLBAR_WIDTH, 0.0, SCROLLBAR_WIDTH, height);
+
+    let thumb_height = (height * height / content_height)
+        .max(SCROLLBAR_MIN_THUMB_HEIGHT)
+        .min(height);
+    let progress = (scroll / max_scroll).clamp(0.0, 1.0);
+    let thumb = Rect {
+        y: progress * (height - thumb_height),
+        height: thumb_height,
+        ..track
+    };
+
+    Some((track, thumb))
+}
+
+/// Corners of a box in the order `quad_indices` makes two triangles of.
+fn quad_vertices(
+    screen_pos: (f32, f32),
+    bounds: (f32, f32),
+    color: [f32; 4],
+) -> [QuadVertex; 4] {
+    let (x, y) = screen_pos;
+    let (width, height) = bounds;
+    [
+        [x, y],
+        [x, y + height],
+        [x + width, y + height],
+        [x + width, y],
+    ]
+    .map(|position| QuadVertex { position, color })
+}
+
+/// Quads filling a box with corners rounded by `radius`: a cross of three
+/// boxes and a fan of triangles in every corner, each triangle being a quad
+/// whose last two vertices are the same. The radius is limited to half the
+/// shorter side, with none left a single quad covers the box.
+fn rounded_quad_vertices(
+    screen_pos: (f32, f32),
+    bounds: (f32, f32),
+    radius: f32,
+    color: [f32; 4],
+) -> Vec<[QuadVertex; 4]> {
+    let (x, y) = screen_pos;
+    let (width, height) = bounds;
+    let radius = radius.min(width / 2.0).min(height / 2.0);
+    if radius <= 0.0 {
+        return vec![quad_vertices(screen_pos, bounds, color)];
+    }
+
+    let cross = [
+        Rect::new(x, y + radius, width, height - 2.0 * radius),
+        Rect::new(x + radius, y, width - 2.0 * radius, radius),
+        Rect::new(
+            x + radius,
+            y + height - radius,
+            width - 2.0 * radius,
+            radius,
+        ),
+    ];
+    let mut quads: Vec<[QuadVertex; 4]> = cross
+        .iter()
+        .filter(|rect| rect.width > 0.0 && rect.height > 0.0)
+        .map(|rect| {
+            quad_vertices((rect.x, rect.y), (rect.width, rect.height), color)
+        })
+        .collect();
+
+    // centers of the corner arcs and the angle each arc starts at, y
+    // pointing down so the arcs go clockwise from the top left corner
+    let (left, top) = (x + radius, y + radius);
+    let (right, bottom) = (x + width - radius, y + height - radius);
+    let corners = [
+        (left, top, PI),
+        (right, top, 1.5 * PI),
+        (right, bottom, 0.0),
+        (left, bottom, 0.5 * PI),
+    ];
+    let vertex = |position| QuadVertex { position, color };
+    for (cx, cy, start) in corners {
+        let point = |segment: usize| {
+            let angle = start
+                + FRAC_PI_2 * segment as f32 / ROUNDED_CORNER_SEGMENTS as f32;
+            [cx + radius * angle.cos(), cy + radius * angle.sin()]
+        };
+        for segment in 0..ROUNDED_CORNER_SEGMENTS {
+            let (from, to) = (point(segment), point(segment + 1));
+            quads.push([
+                vertex([cx, cy]),
+                vertex(from),
+                vertex(to),
+                vertex(to),
+            ]);
+        }
     }
+
+    quads
+}
+
+/// Indices for `max_quads` quads, two triangles for every four vertices.
+fn quad_indices(max_quads: usize) -> Vec<u32> {
+    (0..max_quads as u32)
+        .flat_map(|quad| {
+            let offset = quad * 4;
+            [
+                offset,
+                offset + 1,
+                offset + 2,
+                offset + 2,
+                offset + 3,
+                offset,
+            ]
+        })
+        .collect()
+}
+
+/// The embedded regular, bold, italic and monospace faces.
+fn default_fonts() -> Result<Vec<FontArc>, FontError> {
+    Ok(vec![
+        load_font(DEFAULT_FONT)?,
+        load_font(DEFAULT_BOLD_FONT)?,
+        load_font(DEFAULT_ITALIC_FONT)?,
+        load_font(DEFAULT_MONOSPACE_FONT)?,
+    ])
+}
+
+fn build_glyph_brush<'a>(
+    display: &impl Facade,
+    fonts: Vec<FontArc>,
+    cache_size: (u32, u32),
+) -> GlyphBrush<'a, FontArc> {
+    GlyphBrushBuilder::using_fonts(fonts)
+        .initial_cache_size(cache_size)
+        .build(display)
+}
+
+fn load_font(font_bytes: &[u8]) -> Result<FontArc, FontError> {
+    Ok(FontArc::try_from_vec(font_bytes.to_vec())?)
 }
 
+/// Height of `text` drawn at `size` without wrapping, one line for every
+/// `\n` separated part, with the default line height.
 pub fn get_line_height_of_text(text: &str, size: f32) -> f32 {
-    let line_count = text.chars().filter(|c| *c == '\n').count() + 1;
-    size * line_count as f32 * 2.0
+    height_of_lines(count_lines(text), size * DEFAULT_LINE_HEIGHT)
+}
+
+/// `(width, height)` of `text` laid out with `fonts` and wrapped to
+/// `max_width`, or not wrapped at all without one. Every line is
+/// `line_height` pixels high, empty text takes up no space.
+pub fn measure_text(
+    fonts: &[FontArc],
+    text: &str,
+    size: f32,
+    style: FontStyle,
+    max_width: Option<f32>,
+    line_height: f32,
+) -> (f32, f32) {
+    if text.is_empty() {
+        return (0.0, 0.0);
+    }
+
+    let font_id = style.font_id();
+    let geometry = SectionGeometry {
+        screen_position: (0.0, 0.0),
+        bounds: (max_width.unwrap_or(f32::INFINITY), f32::INFINITY),
+    };
+    let glyphs = Layout::default().calculate_glyphs(
+        fonts,
+        &geometry,
+        &[SectionText {
+            text,
+            scale: PxScale::from(size),
+            font_id,
+        }],
+    );
+
+    // every wrapped line moves the baseline down by the same advance, so
+    // the spread of glyph positions tells how many lines were laid out
+    let font = fonts[font_id.0].as_scaled(size);
+    let line_advance = font.height() + font.line_gap();
+    let (top, bottom, right) = glyphs.iter().fold(
+        (f32::INFINITY, f32::NEG_INFINITY, 0.0_f32),
+        |(top, bottom, right), glyph| {
+            let position = glyph.glyph.position;
+            let advance = font.h_advance(glyph.glyph.id);
+            (
+                top.min(position.y),
+                bottom.max(position.y),
+                right.max(position.x + advance),
+            )
+        },
+    );
+    let wrapped_lines = if glyphs.is_empty() {
+        0
+    } else {
+        ((bottom - top) / line_advance).round() as usize + 1
+    };
+
+    // blank lines have no glyphs, hard breaks still count for them
+    let hard_lines = count_lines(text);
+
+    (
+        right,
+        height_of_lines(wrapped_lines.max(hard_lines), line_height),
+    )
+}
+
+/// Number of `\n` separated lines in `text`.
+fn count_lines(text: &str) -> usize {
+    text.chars().filter(|c| *c == '\n').count() + 1
+}
+
+fn height_of_lines(line_count: usize, line_height: f32) -> f32 {
+    line_count as f32 * line_height
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Off-screen OpenGL context for tests that need a real renderer.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn headless_display() -> glium::HeadlessRenderer {
+        use glium::glutin;
+        use glutin::dpi::PhysicalSize;
+        use glutin::event_loop::EventLoopBuilder;
+        use glutin::platform::unix::EventLoopBuilderExtUnix;
+
+        let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
+        let context = glutin::ContextBuilder::new()
+            .build_headless(&event_loop, PhysicalSize::new(1, 1))
+            .unwrap();
+        glium::HeadlessRenderer::new(context).unwrap()
+    }
+
+    fn quad(x: f32) -> [QuadVertex; 4] {
+        let vertex = QuadVertex {
+            position: [x, 0.0],
+            color: [0.0, 0.0, 0.0, 1.0],
+        };
+        [vertex; 4]
+    }
+
+    #[test]
+    fn test_quad_batch_flushes_when_full() {
+        let cap = 4;
+        let mut batch = QuadBatch::new(cap);
+        let mut draw_calls = Vec::new();
+
+        for i in 0..cap + 1 {
+            batch.push(quad(i as f32), |vertices, index_count| {
+                assert!(vertices.len() <= 4 * cap);
+                draw_calls.push((vertices.to_vec(), index_count));
+            });
+        }
+        batch.flush(|vertices, index_count| {
+            draw_calls.push((vertices.to_vec(), index_count));
+        });
+
+        assert_eq!(draw_calls.len(), 2);
+        assert_eq!(draw_calls[0].0.len(), 4 * cap);
+        assert_eq!(draw_calls[0].1, 6 * cap);
+        assert_eq!(draw_calls[1].0.len(), 4);
+        assert_eq!(draw_calls[1].1, 6);
+
+        // every quad made it into exactly one draw call, in order
+        let xs: Vec<f32> = draw_calls
+            .iter()
+            .flat_map(|(vertices, _)| vertices.chunks(4))
+            .map(|quad| quad[0].position[0])
+            .collect();
+        assert_eq!(xs, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+
+        // an empty batch does not issue a draw call
+        batch.flush(|_, _| panic!("flushed an empty batch"));
+    }
+
+    #[test]
+    fn test_rounded_quad_vertices() {
+        let color = [1.0, 0.0, 0.0, 1.0];
+        let plain = quad_vertices((10.0, 20.0), (100.0, 50.0), color);
+        assert_eq!(
+            rounded_quad_vertices((10.0, 20.0), (100.0, 50.0), 0.0, color),
+            vec![plain]
+        );
+
+        // the cross and the fans of the four corners stay inside the box
+        let quads =
+            rounded_quad_vertices((10.0, 20.0), (100.0, 50.0), 10.0, color);
+        assert_eq!(quads.len(), 3 + 4 * ROUNDED_CORNER_SEGMENTS);
+        for vertex in quads.iter().flatten() {
+            let [x, y] = vertex.position;
+            assert!((10.0..=110.0).contains(&x), "{} is outside", x);
+            assert!((20.0..=70.0).contains(&y), "{} is outside", y);
+        }
+        // the top left corner is cut off
+        let top_left = quads.iter().flatten().any(|vertex| {
+            let [x, y] = vertex.position;
+            x < 12.0 && y < 22.0
+        });
+        assert!(!top_left);
+
+        // a radius larger than the box makes its short sides round
+        assert_eq!(
+            rounded_quad_vertices((0.0, 0.0), (100.0, 50.0), 100.0, color),
+            rounded_quad_vertices((0.0, 0.0), (100.0, 50.0), 25.0, color)
+        );
+    }
+
+    #[test]
+    fn test_quad_buffer_capacity() {
+        let batch = QuadBatch::new(2);
+        assert_eq!(batch.max_vertices(), 8);
+        assert_eq!(batch.max_indices(), 12);
+
+        assert_eq!(quad_indices(2), vec![0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4]);
+    }
+
+    #[test]
+    fn test_load_font() {
+        assert!(load_font(DEFAULT_FONT).is_ok());
+        assert!(load_font(DEFAULT_BOLD_FONT).is_ok());
+        assert!(load_font(DEFAULT_ITALIC_FONT).is_ok());
+        assert!(load_font(DEFAULT_MONOSPACE_FONT).is_ok());
+        assert!(load_font(b"definitely not a font").is_err());
+    }
+
+    #[test]
+    fn test_border_rects() {
+        let rect = Rect::new(10.0, 20.0, 100.0, 50.0);
+
+        assert_eq!(
+            border_rects(rect, [2.0; 4]),
+            [
+                Rect::new(10.0, 20.0, 100.0, 2.0),
+                Rect::new(108.0, 22.0, 2.0, 46.0),
+                Rect::new(10.0, 68.0, 100.0, 2.0),
+                Rect::new(10.0, 22.0, 2.0, 46.0),
+            ]
+        );
+
+        assert_eq!(
+            border_rects(rect, [1.0, 0.0, 3.0, 4.0]),
+            [
+                Rect::new(10.0, 20.0, 100.0, 1.0),
+                Rect::new(110.0, 21.0, 0.0, 46.0),
+                Rect::new(10.0, 67.0, 100.0, 3.0),
+                Rect::new(10.0, 21.0, 4.0, 46.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_draw_parameters_blend_alpha() {
+        let params = blended_draw_parameters();
+
+        assert_eq!(params.blend, Blend::alpha_blending());
+        assert_ne!(params.blend, DrawParameters::default().blend);
+    }
+
+    #[test]
+    fn test_text_transform_zooms_around_center() {
+        let transform = |zoom| {
+            let transform = text_transform((800, 600), zoom);
+            glm::Mat4::from(transform) * glm::vec4(200.0, 150.0, 0.0, 1.0)
+        };
+
+        let plain = transform(1.0);
+        assert_eq!((plain.x, plain.y), (-0.5, -0.5));
+        let zoomed = transform(2.0);
+        assert_eq!((zoomed.x, zoomed.y), (-1.0, -1.0));
+    }
+
+    #[test]
+    fn test_rich_text_section() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let runs = [
+            TextRun {
+                text: "Hello ",
+                size: 16.0,
+                color: [0.0, 0.0, 0.0, 1.0],
+                style: FontStyle::Regular,
+            },
+            TextRun {
+                text: "world",
+                size: 20.0,
+                color: red,
+                style: FontStyle::Bold,
+            },
+        ];
+        let cfg = TextDrawConfig {
+            screen_pos: (10.0, 20.0),
+            bounds: (200.0, f32::INFINITY),
+            ..Default::default()
+        };
+
+        let section = rich_text_section(&runs, &cfg, 200.0);
+        assert_eq!(section.screen_position, (10.0, 20.0));
+        assert_eq!(section.bounds, (200.0, f32::INFINITY));
+
+        let texts: Vec<_> = section
+            .text
+            .iter()
+            .map(|text| {
+                (text.text, text.scale.y, text.font_id, text.extra.color)
+            })
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("Hello ", 16.0, FontId(0), [0.0, 0.0, 0.0, 1.0]),
+                ("world", 20.0, FontStyle::Bold.font_id(), red),
+            ]
+        );
+
+        let fonts = default_fonts().unwrap();
+        assert_eq!(
+            runs_height(&fonts, &runs, 200.0),
+            get_line_height_of_text("a", 20.0)
+        );
+        assert_eq!(runs_height(&fonts, &[], 200.0), 0.0);
+    }
+
+    #[test]
+    fn test_scrollbar_rects() {
+        let viewport = (600, 800);
+        let track = Rect::new(590.0, 0.0, SCROLLBAR_WIDTH, 800.0);
+
+        // the viewport shows 800 of 2000 pixels, so the thumb covers 2/5 of
+        // the track
+        let (top_track, top) = scrollbar_rects(0.0, 2000.0, viewport).unwrap();
+        assert_eq!(top_track, track);
+        assert_eq!(top, Rect::new(590.0, 0.0, SCROLLBAR_WIDTH, 320.0));
+
+        let (_, middle) = scrollbar_rects(600.0, 2000.0, viewport).unwrap();
+        assert_eq!(middle.y, 240.0);
+
+        let (_, bottom) = scrollbar_rects(1200.0, 2000.0, viewport).unwrap();
+        assert_eq!(bottom.y + bottom.height, 800.0);
+
+        let (_, long) = scrollbar_rects(0.0, 1_000_000.0, viewport).unwrap();
+        assert_eq!(long.height, SCROLLBAR_MIN_THUMB_HEIGHT);
+
+        assert_eq!(scrollbar_rects(0.0, 800.0, viewport), None);
+        assert_eq!(scrollbar_rects(0.0, 300.0, viewport), None);
+    }
+
+    #[test]
+    fn test_aligned_x() {
+        assert_eq!(aligned_x(10.0, 200.0, HorizontalAlign::Left), 10.0);
+        assert_eq!(aligned_x(10.0, 200.0, HorizontalAlign::Center), 110.0);
+        assert_eq!(aligned_x(10.0, 200.0, HorizontalAlign::Right), 210.0);
+    }
+
+    #[test]
+    fn test_get_line_height_of_text() {
+        assert_eq!(get_line_height_of_text("Lorem", 16.0), 32.0);
+        assert_eq!(get_line_height_of_text("Lorem\nipsum", 16.0), 64.0);
+        assert_eq!(get_line_height_of_text("", 10.0), 20.0);
+    }
+
+    #[test]
+    fn test_glyph_boxes_follow_lines() {
+        let fonts = default_fonts().unwrap();
+        let run = TextRun {
+            text: "ab\ncd",
+            size: 16.0,
+            color: [0.0, 0.0, 0.0, 1.0],
+            style: FontStyle::Regular,
+        };
+        let cfg = TextDrawConfig {
+            screen_pos: (10.0, 20.0),
+            ..Default::default()
+        };
+
+        let boxes = glyph_boxes(&fonts, &run, &cfg, 200.0);
+        let chars: String = boxes.iter().map(|glyph| glyph.ch).collect();
+        assert_eq!(chars, "abcd");
+
+        let [a, b, c, _] = [boxes[0], boxes[1], boxes[2], boxes[3]];
+        assert_eq!(a.rect.x, 10.0);
+        assert_eq!(a.rect.y, 20.0);
+        assert_eq!(b.rect.y, a.rect.y);
+        assert!(b.rect.x >= a.rect.x + a.rect.width);
+        assert_eq!(c.rect.x, 10.0);
+        assert!(c.rect.y >= a.rect.y + a.rect.height);
+    }
+
+    #[test]
+    fn test_measure_wrapped_text() {
+        let fonts = default_fonts().unwrap();
+        let one_line = get_line_height_of_text("a", 16.0);
+        let text = "Lorem ipsum dolor sit amet, consectetur adipiscing elit";
+
+        let (_, short) = measure_text(
+            &fonts,
+            "Lorem ipsum",
+            16.0,
+            FontStyle::Regular,
+            Some(400.0),
+            one_line,
+        );
+        assert_eq!(short, one_line);
+
+        let (wrapped_width, wrapped_height) = measure_text(
+            &fonts,
+            text,
+            16.0,
+            FontStyle::Regular,
+            Some(100.0),
+            one_line,
+        );
+        let (width, height) = measure_text(
+            &fonts,
+            text,
+            16.0,
+            FontStyle::Regular,
+            None,
+            one_line,
+        );
+        assert_eq!(height, one_line);
+        assert!(wrapped_height > height);
+        assert!(wrapped_width <= 100.0);
+        assert!(width > wrapped_width);
+
+        let (_, bold) = measure_text(
+            &fonts,
+            text,
+            16.0,
+            FontStyle::Bold,
+            Some(100.0),
+            one_line,
+        );
+        assert!(bold > one_line);
+
+        let (_, blank_lines) = measure_text(
+            &fonts,
+            "a\n\n",
+            16.0,
+            FontStyle::Regular,
+            Some(400.0),
+            one_line,
+        );
+        assert_eq!(blank_lines, get_line_height_of_text("a\n\n", 16.0));
+
+        assert_eq!(
+            measure_text(&fonts, "", 16.0, FontStyle::Regular, None, one_line),
+            (0.0, 0.0)
+        );
+
+        // the line height sets the advance of every line, wrapped or not
+        let (_, tight) = measure_text(
+            &fonts,
+            text,
+            16.0,
+            FontStyle::Regular,
+            Some(100.0),
+            16.0,
+        );
+        assert_eq!(tight * 2.0, wrapped_height);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[ignore = "needs an OpenGL driver"]
+    fn test_render_stats_count_draws() {
+        let display = headless_display();
+        let mut renderer = Renderer::with_capacity(&display, 2);
+
+        renderer.render_to_buffer((100, 40), |renderer, target| {
+            // more quads than fit into one batch
+            for x in 0..5 {
+                renderer.draw_quad(
+                    target,
+                    (x as f32, 0.0),
+                    (1.0, 1.0),
+                    [1.0; 4],
+                );
+            }
+            renderer.draw_text(target, "a b", 16.0, TextDrawConfig::default());
+        });
+        assert_eq!(
+            renderer.last_frame_stats(),
+            RenderStats {
+                quads_drawn: 5,
+                // batches of two, two and one quads
+                draw_calls: 3,
+                glyphs_queued: 2
+            }
+        );
+
+        renderer.render_to_buffer((100, 40), |_, _| {});
+        assert_eq!(renderer.last_frame_stats(), RenderStats::default());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[ignore = "needs an OpenGL driver"]
+    fn test_end_returns_render_stats() {
+        let display = headless_display();
+        let mut renderer = Renderer::with_capacity(&display, 4);
+        let texture = SrgbTexture2d::empty(&display, 100, 40).unwrap();
+        let mut target = SimpleFrameBuffer::new(&display, &texture).unwrap();
+
+        renderer.begin(&mut target);
+        for x in 0..10 {
+            renderer.draw_quad(
+                &mut target,
+                (x as f32, 0.0),
+                (1.0, 1.0),
+                [1.0; 4],
+            );
+        }
+        let stats = renderer.end(&mut target);
+
+        assert_eq!(stats.quads_drawn, 10);
+        assert!(stats.draw_calls >= 2);
+        assert_eq!(stats, renderer.last_frame_stats());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[ignore = "needs an OpenGL driver"]
+    fn test_clear_text_cache_keeps_drawing_text() {
+        let display = headless_display();
+        let mut renderer = Renderer::with_text_cache_size(&display, (512, 512));
+        let draw = |renderer: &mut Renderer, target: &mut SimpleFrameBuffer| {
+            renderer.draw_text(target, "Lorem", 16.0, TextDrawConfig::default())
+        };
+
+        renderer.render_to_buffer((100, 40), draw);
+        renderer.clear_text_cache();
+        assert!(renderer.needs_redraw());
+
+        let pixels = renderer.render_to_buffer((100, 40), draw);
+        // the glyphs got rasterized again into the fresh atlas
+        assert!(pixels.chunks(4).any(|pixel| pixel != [255, 255, 255, 255]));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[ignore = "needs an OpenGL driver"]
+    fn test_update_dimension_updates_projection() {
+        let display = headless_display();
+        let mut renderer = Renderer::new(&display);
+        renderer.update_dimension((800, 600));
+
+        assert_eq!(renderer.camera.screen_size, (800, 600));
+        let projection = renderer.camera.get_projection();
+        assert!(projection.iter().all(|value| value.is_finite()));
+        assert!(projection.try_inverse().is_some());
+    }
 }
@@ -0,0 +1,225 @@
+use std::ops::Range;
+
+use crate::lalg::Rect;
+
+/// A character of text as it was drawn on the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphBox {
+    pub rect: Rect,
+    pub ch: char,
+}
+
+/// Text selected by dragging over the page. `start` is the caret position
+/// the drag began at and `end` the one it is at now, a caret position being
+/// the index of the glyph it is in front of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Selection {
+    /// Indices of the selected glyphs, whichever way the drag went.
+    pub fn range(&self) -> Range<usize> {
+        self.start.min(self.end)..self.start.max(self.end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Caret position closest to `(x, y)`: in front of the nearest glyph, or
+/// behind it when the point is past the glyph's middle. `None` when there
+/// is no text.
+pub fn caret_at(glyphs: &[GlyphBox], x: f32, y: f32) -> Option<usize> {
+    let distance = |rect: &Rect| {
+        let dx = (rect.x - x).max(x - (rect.x + rect.width)).max(0.0);
+        let dy = (rect.y - y).max(y - (rect.y + rect.height)).max(0.0);
+        dx * dx + dy * dy
+    };
+
+    let (index, glyph) =
+        glyphs.iter().enumerate().min_by(|(_, a), (_, b)| {
+            distance(&a.rect).total_cmp(&distance(&b.rect))
+        })?;
+
+    if x > glyph.rect.x + glyph.rect.width / 2.0 {
+        Some(index + 1)
+    } else {
+        Some(index)
+    }
+}
+
+/// Rectangles to highlight behind the selected glyphs, one for every line
+/// the selection covers.
+pub fn highlight_rects(glyphs: &[GlyphBox], selection: Selection) -> Vec<Rect> {
+    let mut rects: Vec<Rect> = Vec::new();
+
+    let selected = glyphs.get(selection.range()).unwrap_or_default();
+    for glyph in selected {
+        let rect = glyph.rect;
+        match rects.last_mut() {
+            // glyphs further right on the same line widen the last box
+            Some(line)
+                if line.y == rect.y
+                    && line.height == rect.height
+                    && rect.x >= line.x =>
+            {
+                line.width = line.width.max(rect.x + rect.width - line.x);
+            }
+            _ => rects.push(rect),
+        }
+    }
+
+    rects
+}
+
+/// The selected characters. Lines are joined with a newline, unless the
+/// line before ends in whitespace and so was only wrapped.
+pub fn selected_text(glyphs: &[GlyphBox], selection: Selection) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&GlyphBox> = None;
+
+    let selected = glyphs.get(selection.range()).unwrap_or_default();
+    for glyph in selected {
+        if let Some(previous) = previous {
+            let new_line =
+                glyph.rect.y >= previous.rect.y + previous.rect.height;
+            if new_line && !previous.ch.is_whitespace() {
+                text.push('\n');
+            }
+        }
+
+        text.push(glyph.ch);
+        previous = Some(glyph);
+    }
+
+    text
+}
+
+/// Where `query` occurs in the text, ignoring case, as ranges of glyph
+/// indices. `runs` are the glyphs of one element each and a match never
+/// reaches from one into the next. Matches do not overlap, `aa` is found
+/// once in `aaa` and twice in `aaaa`. An empty query matches nothing.
+pub fn match_ranges(
+    glyphs: &[GlyphBox],
+    runs: &[Range<usize>],
+    query: &str,
+) -> Vec<Range<usize>> {
+    let query: Vec<char> = query.chars().collect();
+    let mut matches = Vec::new();
+    if query.is_empty() {
+        return matches;
+    }
+
+    let same = |glyph: &GlyphBox, ch: &char| {
+        glyph.ch.to_lowercase().eq(ch.to_lowercase())
+    };
+    for run in runs {
+        let Some(text) = glyphs.get(run.clone()) else {
+            continue;
+        };
+
+        let mut start = 0;
+        while start + query.len() <= text.len() {
+            let candidate = &text[start..start + query.len()];
+            if candidate
+                .iter()
+                .zip(&query)
+                .all(|(glyph, ch)| same(glyph, ch))
+            {
+                matches
+                    .push(run.start + start..run.start + start + query.len());
+                start += query.len();
+            } else {
+                start += 1;
+            }
+        }
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Glyphs 10px wide and 20px high, with `text` wrapped after `columns`
+    /// characters.
+    fn glyphs(text: &str, columns: usize) -> Vec<GlyphBox> {
+        text.chars()
+            .enumerate()
+            .map(|(i, ch)| GlyphBox {
+                rect: Rect::new(
+                    (i % columns) as f32 * 10.0,
+                    (i / columns) as f32 * 20.0,
+                    10.0,
+                    20.0,
+                ),
+                ch,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_caret_at() {
+        let glyphs = glyphs("abcdef", 3);
+
+        assert_eq!(caret_at(&glyphs, 2.0, 5.0), Some(0));
+        assert_eq!(caret_at(&glyphs, 18.0, 5.0), Some(2));
+        // past the end of a line the caret goes behind its last glyph
+        assert_eq!(caret_at(&glyphs, 100.0, 25.0), Some(6));
+        assert_eq!(caret_at(&[], 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_selected_text() {
+        let glyphs = glyphs("ab cdef", 3);
+
+        // "ab " wraps after its space, "cde" ends a line of its own
+        let all = Selection { start: 0, end: 7 };
+        assert_eq!(selected_text(&glyphs, all), "ab cde\nf");
+        assert_eq!(
+            selected_text(&glyphs, Selection { start: 5, end: 1 }),
+            "b cd"
+        );
+        assert_eq!(selected_text(&glyphs, Selection { start: 2, end: 2 }), "");
+    }
+
+    #[test]
+    fn test_highlight_rects_per_line() {
+        let glyphs = glyphs("abcdefgh", 3);
+
+        // "bc" on the first line, "def" on the second and "g" on the third
+        let rects = highlight_rects(&glyphs, Selection { start: 7, end: 1 });
+        assert_eq!(
+            rects,
+            vec![
+                Rect::new(10.0, 0.0, 20.0, 20.0),
+                Rect::new(0.0, 20.0, 30.0, 20.0),
+                Rect::new(0.0, 40.0, 10.0, 20.0),
+            ]
+        );
+
+        let empty = Selection { start: 4, end: 4 };
+        assert!(empty.is_empty());
+        assert!(highlight_rects(&glyphs, empty).is_empty());
+    }
+
+    #[test]
+    fn test_match_ranges() {
+        let glyphs = glyphs("aaaa AaA", 100);
+        let whole = [Range { start: 0, end: 8 }];
+
+        assert_eq!(match_ranges(&glyphs, &whole, "aa"), vec![0..2, 2..4, 5..7]);
+        assert_eq!(match_ranges(&glyphs, &whole, "A A"), vec![3..6]);
+        assert!(match_ranges(&glyphs, &whole, "").is_empty());
+        assert!(match_ranges(&glyphs, &whole, "b").is_empty());
+
+        // "a A" only exists across the two runs
+        let runs = [0..4, 5..8];
+        assert!(match_ranges(&glyphs, &runs, "a A").is_empty());
+        assert_eq!(match_ranges(&glyphs, &runs, "aaa"), vec![0..3, 5..8]);
+    }
+}
@@ -0,0 +1,13 @@
+#version 450 core
+
+out vec4 o_color;
+
+in vec2 v_tex_coords;
+
+uniform sampler2D tex;
+uniform float opacity;
+
+void main() {
+    o_color = texture(tex, v_tex_coords);
+    o_color.a *= opacity;
+}
@@ -0,0 +1,15 @@
+#version 450 core
+
+in vec2 position;
+in vec2 tex_coords;
+
+out vec2 v_tex_coords;
+
+uniform mat4 view;
+uniform mat4 proj;
+
+void main() {
+    v_tex_coords = tex_coords;
+
+    gl_Position = proj * view * vec4(position, -1.0, 1.0);
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use glium::backend::Facade;
+use glium::texture::Texture2d;
+
+/// Textures loaded for image sources, keyed by their path. Sources that
+/// failed to load are remembered too, so they are not read again on every
+/// frame.
+pub struct TextureCache<T = Texture2d> {
+    entries: HashMap<String, Option<Rc<T>>>,
+}
+
+impl<T> TextureCache<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns the texture for `src`, calling `load` only the first time the
+    /// source is requested.
+    pub fn get_or_load(
+        &mut self,
+        src: &str,
+        load: impl FnOnce(&str) -> Option<T>,
+    ) -> Option<Rc<T>> {
+        if let Some(entry) = self.entries.get(src) {
+            return entry.clone();
+        }
+
+        let entry = load(src).map(Rc::new);
+        self.entries.insert(src.to_string(), entry.clone());
+        entry
+    }
+}
+
+impl<T> Default for TextureCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Media types of `data:` URLs that can be decoded as images.
+#[cfg(feature = "image")]
+const IMAGE_MEDIA_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif"];
+
+/// Decodes the image file at `path`, or the image in a `data:` URL, into a
+/// texture. Errors are reported on stderr and turn into `None`.
+#[cfg(feature = "image")]
+pub fn load_texture(display: &impl Facade, path: &str) -> Option<Texture2d> {
+    use glium::texture::RawImage2d;
+
+    use crate::data_url::{is_data_url, parse_data_url};
+
+    let image = if is_data_url(path) {
+        parse_data_url(path)
+            .and_then(|data_url| {
+                data_url.expect_media_type(IMAGE_MEDIA_TYPES)?;
+                Ok(data_url.data)
+            })
+            .map_err(|err| err.to_string())
+            .and_then(|data| {
+                image::load_from_memory(&data).map_err(|err| err.to_string())
+            })
+    } else {
+        image::open(path).map_err(|err| err.to_string())
+    };
+    let image = match image {
+        Ok(image) => image.to_rgba8(),
+        Err(err) => {
+            // data: URLs can be long, so only their start is shown
+            let name: String = path.chars().take(40).collect();
+            eprintln!("Failed to load image {}: {}", name, err);
+            return None;
+        }
+    };
+
+    // rows stay top to bottom, so the top of the image is at v = 0
+    let dimensions = image.dimensions();
+    let raw = RawImage2d::from_raw_rgba(image.into_raw(), dimensions);
+
+    match Texture2d::new(display, raw) {
+        Ok(texture) => Some(texture),
+        Err(err) => {
+            eprintln!("Failed to create texture for {}: {}", path, err);
+            None
+        }
+    }
+}
+
+/// Images can only be decoded with the `image` feature, without it every
+/// image shows up as a placeholder.
+#[cfg(not(feature = "image"))]
+pub fn load_texture(_display: &impl Facade, _path: &str) -> Option<Texture2d> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_src_reuses_texture() {
+        let mut cache = TextureCache::new();
+        let mut loads = 0;
+
+        let mut load = |src: &str| {
+            cache.get_or_load(src, |src| {
+                loads += 1;
+                (src != "missing.png").then_some(src.len())
+            })
+        };
+
+        let first = load("cat.png").unwrap();
+        let second = load("cat.png").unwrap();
+        assert!(Rc::ptr_eq(&first, &second));
+
+        assert!(load("missing.png").is_none());
+        assert!(load("missing.png").is_none());
+
+        assert_eq!(loads, 2);
+    }
+}
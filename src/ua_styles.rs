@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use crate::html_renderer::{BoxEdges, HtmlElement, Length, TextDecoration};
+use crate::renderer::FontStyle;
+
+/// Background behind `<code>`, a light gray that keeps text readable.
+pub const CODE_BG_COLOR: [f32; 4] = [0.94, 0.94, 0.94, 1.0];
+
+/// Defaults an element gets from its tag. Styles set by the page win over
+/// them, and unset fields fall back to what the parent passes down.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TagStyle {
+    /// Font size relative to the parent's, like a size in `em`.
+    pub font_scale: Option<f32>,
+    pub font_style: Option<FontStyle>,
+    pub bg_color: Option<[f32; 4]>,
+    pub text_decoration: Option<TextDecoration>,
+    pub margin: Option<BoxEdges>,
+}
+
+/// The browser's own styles for each tag, applied before the page's styles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserAgentStyleSheet {
+    styles: HashMap<HtmlElement, TagStyle>,
+}
+
+impl UserAgentStyleSheet {
+    /// A style sheet without any defaults, every element inherits from its
+    /// parent.
+    pub fn empty() -> Self {
+        Self {
+            styles: HashMap::new(),
+        }
+    }
+
+    /// Defaults for `element`.
+    pub fn get(&self, element: HtmlElement) -> TagStyle {
+        self.styles.get(&element).copied().unwrap_or_default()
+    }
+
+    /// Replaces the defaults for `element`.
+    pub fn set(&mut self, element: HtmlElement, style: TagStyle) {
+        self.styles.insert(element, style);
+    }
+}
+
+impl Default for UserAgentStyleSheet {
+    fn default() -> Self {
+        let mut sheet = Self::empty();
+
+        let heading = |scale| TagStyle {
+            font_scale: Some(scale),
+            font_style: Some(FontStyle::Bold),
+            ..Default::default()
+        };
+        sheet.set(HtmlElement::H1, heading(2.0));
+        sheet.set(HtmlElement::H2, heading(1.75));
+        sheet.set(HtmlElement::H3, heading(1.5));
+        sheet.set(HtmlElement::H4, heading(1.125));
+        sheet.set(HtmlElement::H5, heading(0.875));
+        sheet.set(HtmlElement::H6, heading(0.8125));
+
+        let font_style = |style| TagStyle {
+            font_style: Some(style),
+            ..Default::default()
+        };
+        sheet.set(HtmlElement::Bold, font_style(FontStyle::Bold));
+        sheet.set(HtmlElement::Strong, font_style(FontStyle::Bold));
+        sheet.set(HtmlElement::Italic, font_style(FontStyle::Italic));
+        sheet.set(HtmlElement::Emphasis, font_style(FontStyle::Italic));
+        sheet.set(HtmlElement::Pre, font_style(FontStyle::Monospace));
+        sheet.set(
+            HtmlElement::Code,
+            TagStyle {
+                bg_color: Some(CODE_BG_COLOR),
+                ..font_style(FontStyle::Monospace)
+            },
+        );
+
+        // links are underlined along with everything inside of them
+        let underline = TagStyle {
+            text_decoration: Some(TextDecoration::Underline),
+            ..Default::default()
+        };
+        sheet.set(HtmlElement::Anchor, underline);
+        sheet.set(HtmlElement::Underline, underline);
+
+        // rules get some room above and below, and are centered when they
+        // are narrower than the page
+        sheet.set(
+            HtmlElement::HorizontalRule,
+            TagStyle {
+                margin: Some(BoxEdges {
+                    top: Length::Px(8.0),
+                    right: Length::Auto,
+                    bottom: Length::Px(8.0),
+                    left: Length::Auto,
+                }),
+                ..Default::default()
+            },
+        );
+
+        // quotes are indented on both sides, nested ones further
+        sheet.set(
+            HtmlElement::Blockquote,
+            TagStyle {
+                margin: Some(BoxEdges {
+                    top: Length::Px(16.0),
+                    right: Length::Px(40.0),
+                    bottom: Length::Px(16.0),
+                    left: Length::Px(40.0),
+                }),
+                ..Default::default()
+            },
+        );
+
+        sheet
+    }
+}